@@ -7,7 +7,7 @@ extern crate test;
 
 
 mod mod3 {
-    use pi_world::{prelude::{App, Component, Query, Update}, query::EntryQuery, single_res::SingleRes, world::{Entity, World}};
+    use pi_world::{prelude::{App, Component, Query, Resource, Update}, query::EntryQuery, single_res::SingleRes, world::{Entity, World}};
     use  test::Bencher;
     use super::*;
 
@@ -38,12 +38,19 @@ mod mod3 {
     #[derive(Debug, Component)]
     pub struct Age13(usize);
 
-    pub type Entitys = Vec<Entity>;
+    #[derive(Resource)]
+    pub struct Entitys(Vec<Entity>);
+    impl std::ops::Deref for Entitys {
+        type Target = Vec<Entity>;
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
 
     fn init(world: &mut World) {
         let mut editor = world.make_entity_editor();
         let count = 50;
-        let mut entitys: Entitys = Vec::new();
+        let mut entitys = Vec::new();
         use rand::Rng;
         let mut rng = rand::thread_rng();
         for _ in 0..count {
@@ -62,7 +69,7 @@ mod mod3 {
             // entitys.push(editor.insert_entity((Age1(1), Age2(1), Age3(1), Age4(1), Age9(1))));
         }
 
-        world.insert_single_res(entitys);
+        world.insert_single_res(Entitys(entitys));
         
     }
     
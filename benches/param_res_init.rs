@@ -4,37 +4,37 @@ extern crate test;
 
 
 mod mod3 {
-    use pi_world::{prelude::{App, Component, Query, Update, SingleRes}};
+    use pi_world::{prelude::{App, Component, Query, Update, Resource, SingleRes}};
     use  test::Bencher;
     use super::*;
 
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age1(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age2(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age3(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age4(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age5(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age6(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age7(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age8(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age9(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age10(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age11(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age12(usize);
-    #[derive(Debug)]
+    #[derive(Debug, Resource)]
     pub struct Age13(usize);
     
     fn system(
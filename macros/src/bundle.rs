@@ -0,0 +1,132 @@
+use proc_macro::TokenStream;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{spanned::Spanned, Error, Meta};
+
+use crate::{ecs_path, get_named_struct_fields};
+
+#[derive(PartialEq, Eq)]
+enum FieldKind {
+    // 组件
+    Component,
+    // 嵌套的Bundle，`#[bundle]`
+    Bundle,
+    // 忽略的字段，`#[bundle(ignore)]`，必须实现Default
+    Ignore,
+}
+
+fn field_kind(field: &syn::Field) -> syn::Result<FieldKind> {
+    let mut kind = FieldKind::Component;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("bundle")) {
+        match &attr.meta {
+            Meta::Path(_) => kind = FieldKind::Bundle,
+            Meta::List(_) => attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("ignore") {
+                    kind = FieldKind::Ignore;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `bundle` attribute, expected `ignore`"))
+                }
+            })?,
+            Meta::NameValue(m) => {
+                return Err(Error::new(m.span(), "expected `#[bundle]` or `#[bundle(ignore)]`"))
+            }
+        }
+    }
+    Ok(kind)
+}
+
+/// Derive `Bundle`
+///
+/// `#[bundle]` flattens a nested bundle, `#[bundle(ignore)]` skips a field, which must implement `Default`.
+/// Duplicate component fields are a compile error, duplicates inside nested bundles panic on init.
+pub fn derive_bundle(ast: syn::DeriveInput) -> TokenStream {
+    let world_path = ecs_path();
+
+    let named_fields = match get_named_struct_fields(&ast.data) {
+        Ok(fields) => &fields.named,
+        Err(e) => return e.into_compile_error().into(),
+    };
+
+    let mut field_types = Vec::new();
+    let mut idens = Vec::new();
+    let mut ignores = Vec::new();
+    // 直接的组件字段，(类型, 字段名)，用于在编译时检查重复
+    let mut components: Vec<(String, &syn::Ident)> = Vec::new();
+    for field in named_fields.iter() {
+        let kind = match field_kind(field) {
+            Ok(r) => r,
+            Err(e) => return e.into_compile_error().into(),
+        };
+        let ident = field.ident.as_ref().unwrap();
+        if kind == FieldKind::Ignore {
+            ignores.push(&field.ty);
+            continue;
+        }
+        if kind == FieldKind::Component {
+            let ty = field.ty.to_token_stream().to_string();
+            if let Some((_, other)) = components.iter().find(|(t, _)| t == &ty) {
+                return Error::new(
+                    field.ty.span(),
+                    format!("duplicate component `{}` in bundle: `{}` and `{}`", ty, other, ident),
+                )
+                .into_compile_error()
+                .into();
+            }
+            components.push((ty, ident));
+        }
+        field_types.push(&field.ty);
+        idens.push(ident);
+    }
+
+    let indexs = (0..idens.len())
+        .map(syn::Index::from)
+        .collect::<Vec<_>>();
+    // 忽略的字段必须实现Default
+    let assert_default = ignores.iter().map(|ty| {
+        quote_spanned! { ty.span() => let _ = <#ty as Default>::default; }
+    });
+
+    let struct_name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    TokenStream::from(quote! {
+        const _: () = {
+            impl #impl_generics #world_path::insert::Bundle for #struct_name #ty_generics #where_clause {
+                type Item = (#(<#field_types as #world_path::insert::Bundle>::Item,)*);
+
+                #[inline(always)]
+                fn components(c: Vec<#world_path::archetype::ComponentInfo>) -> Vec<#world_path::archetype::ComponentInfo> {
+                    #(#assert_default)*
+                    let mut paths = Vec::new();
+                    <Self as #world_path::insert::Bundle>::component_paths(String::from(stringify!(#struct_name)), &mut paths);
+                    #world_path::insert::check_duplicate(&paths);
+                    #(let c = <#field_types as #world_path::insert::Bundle>::components(c);)*
+                    c
+                }
+                #[inline(always)]
+                fn init_item(_world: &#world_path::world::World, _archetype: & #world_path::archetype::Archetype) -> Self::Item {
+                    (#(<#field_types as #world_path::insert::Bundle>::init_item(_world, _archetype),)*)
+                }
+
+                #[inline(always)]
+                fn insert(
+                    _item: &Self::Item,
+                    components: Self,
+                    _e: #world_path::world::Entity,
+                    _row: #world_path::archetype::Row,
+                    tick: #world_path::world::Tick,
+                ) {
+                    #(
+                        <#field_types as #world_path::insert::Bundle>::insert(&_item.#indexs, components.#idens, _e, _row, tick);
+                    )*
+                }
+
+                fn component_paths(path: String, paths: &mut Vec<(#world_path::archetype::ComponentInfo, String)>) {
+                    #(
+                        <#field_types as #world_path::insert::Bundle>::component_paths(format!("{}.{}", path, stringify!(#idens)), paths);
+                    )*
+                }
+            }
+        };
+    })
+}
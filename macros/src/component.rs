@@ -0,0 +1,77 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parenthesized, parse::Parse, Error, LitStr, Token};
+
+/// Generate the `Component` impl from the `#[component(...)]` attributes
+///
+/// - `storage = "table"` or `storage = "sparse"`
+/// - `on_add = path` and `on_remove = path`, hooks of type `fn(&mut World, Entity)`
+/// - `require(A, B)`, components inserted with `Default` if the entity does not have them
+/// - `tick`, always track the change ticks
+pub fn component_impl(path: &syn::Path, input: &syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let mut storage = None;
+    let mut on_add = None;
+    let mut on_remove = None;
+    let mut required: Vec<syn::Type> = Vec::new();
+    let mut tick = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("component")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("storage") {
+                let s: LitStr = meta.value()?.parse()?;
+                storage = Some(match s.value().as_str() {
+                    "table" => quote! { #path::archetype::StorageKind::Table },
+                    "sparse" => quote! { #path::archetype::StorageKind::Sparse },
+                    _ => return Err(Error::new(s.span(), "expected `\"table\"` or `\"sparse\"`")),
+                });
+                Ok(())
+            } else if meta.path.is_ident("on_add") {
+                on_add = Some(meta.value()?.parse::<syn::Path>()?);
+                Ok(())
+            } else if meta.path.is_ident("on_remove") {
+                on_remove = Some(meta.value()?.parse::<syn::Path>()?);
+                Ok(())
+            } else if meta.path.is_ident("require") {
+                let content;
+                parenthesized!(content in meta.input);
+                let types = content.parse_terminated(syn::Type::parse, Token![,])?;
+                required.extend(types);
+                Ok(())
+            } else if meta.path.is_ident("tick") {
+                tick = true;
+                Ok(())
+            } else {
+                Err(meta.error(
+                    "unsupported `component` attribute, expected `storage`, `on_add`, `on_remove`, `require` or `tick`",
+                ))
+            }
+        })?;
+    }
+
+    let storage = storage.map(|s| quote! { const STORAGE: #path::archetype::StorageKind = #s; });
+    let tick = tick.then(|| quote! { const TICK: bool = true; });
+    let on_add = on_add.map(|f| {
+        quote! { const ON_ADD: Option<#path::archetype::ComponentHook> = Some(#f as #path::archetype::ComponentHook); }
+    });
+    let on_remove = on_remove.map(|f| {
+        quote! { const ON_REMOVE: Option<#path::archetype::ComponentHook> = Some(#f as #path::archetype::ComponentHook); }
+    });
+    let required = (!required.is_empty()).then(|| {
+        quote! {
+            const REQUIRED: &'static [#path::archetype::RequiredComponent] = &[
+                #(#path::archetype::RequiredComponent::of::<#required>(),)*
+            ];
+        }
+    });
+
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #path::archetype::Component for #struct_name #ty_generics #where_clause {
+            #storage
+            #tick
+            #on_add
+            #on_remove
+            #required
+        }
+    })
+}
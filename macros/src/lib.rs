@@ -5,8 +5,12 @@ extern crate proc_macro;
 // #[macro_use]
 // extern crate lazy_static;
 
+mod bundle;
+mod component;
 mod label;
 mod manifest;
+mod query_data;
+mod resource;
 
 
 use std::sync::Mutex;
@@ -45,10 +49,18 @@ pub fn derive_system_set(input: TokenStream) -> TokenStream {
     derive_label_inner(input, "SystemSet")
 }
 
-#[proc_macro_derive(Resource)]
-pub fn derive_resource(_input: TokenStream) -> TokenStream {
-    // component::derive_resource(input)
-    TokenStream::from(quote! {})
+/// Implement `Resource`, required by `SingleRes`, `SingleResMut` and `World::insert_single_res`
+#[proc_macro_derive(Resource, attributes(resource))]
+pub fn derive_resource(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    resource::derive_resource(ast)
+}
+
+/// Implement `EventData`, required by `Event` and `EventSender`
+#[proc_macro_derive(Event, attributes(event))]
+pub fn derive_event(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    resource::derive_event(ast)
 }
 
 /// Implement `SystemParam` to use a struct as a parameter in a system
@@ -387,75 +399,40 @@ pub fn derive_param_set_element(input: TokenStream) -> TokenStream {
 }
 
 
-#[proc_macro_derive(Bundle, attributes(bundle))]
-pub fn derive_bundle(input: TokenStream) -> TokenStream {
+/// Implement `FetchComponents` for a struct of query types, used as `Query<MyQuery>`.
+///
+/// Generates `{Name}Item<'w>`, `{Name}ReadOnly` and `{Name}ReadOnlyItem<'w>`.
+#[proc_macro_derive(QueryData)]
+pub fn derive_query_data(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    let world_path = ecs_path();
-
-    let named_fields = match get_named_struct_fields(&ast.data) {
-        Ok(fields) => &fields.named,
-        Err(e) => return e.into_compile_error().into(),
-    };
-
-    let field_types = named_fields
-        .iter()
-        .map(|field| &field.ty)
-        .collect::<Vec<_>>();
-
-    let idens = named_fields
-        .iter()
-        .map(|field| {let r = &field.ident; quote! { #r }})
-        .collect::<Vec<_>>();
-
-    let len = idens.len();
-    let indexs = (0..len).into_iter()
-        .map(|i| syn::Index::from(i) )
-        .collect::<Vec<_>>();
- 
-
-    let tuple_types: Vec<_> = field_types.iter().map(|x| quote! { #x }).collect();
-    let struct_name = &ast.ident;
-    let generics = ast.generics;
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    
-    TokenStream::from(quote! {
-        const _: () = {
-            impl #impl_generics #world_path::insert::Bundle for #struct_name #ty_generics #where_clause {
-                type Item = (#(<#tuple_types as #world_path::insert::Bundle>::Item,)*);
-
-                #[inline(always)]
-                fn components(c: Vec<#world_path::archetype::ComponentInfo>) -> Vec<#world_path::archetype::ComponentInfo> {
-                    #(let c = #tuple_types::components(c);)*
-                    c
-                }
-                #[inline(always)]
-                fn init_item(_world: &#world_path::world::World, _archetype: & #world_path::archetype::Archetype) -> Self::Item {
-                    (#(<#tuple_types as #world_path::insert::Bundle>::init_item(_world, _archetype),)*)
-                }
+    query_data::derive_query_data(ast)
+}
 
-                #[inline(always)]
-                fn insert(
-                    _item: &Self::Item,
-                    components: Self,
-                    _e: #world_path::world::Entity,
-                    _row: #world_path::archetype::Row,
-                    tick: #world_path::world::Tick,
-                ) {
-                    
-                    #(
-                        <#tuple_types as Bundle>::insert(&_item.#indexs, components.#idens, _e, _row, tick);
-                    )*
+/// Implement `FilterComponents` for a struct of filters, used as `Query<Q, MyFilter>`.
+#[proc_macro_derive(QueryFilter)]
+pub fn derive_query_filter(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    query_data::derive_query_filter(ast)
+}
 
-                }
-            }
-        };
-    })
+/// Implement `Bundle` for a struct of components.
+///
+/// `#[bundle]` flattens a nested bundle field, `#[bundle(ignore)]` skips a `Default` field.
+#[proc_macro_derive(Bundle, attributes(bundle))]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    bundle::derive_bundle(ast)
 }
 
-#[proc_macro_derive(Component)]
+/// Implement `Bundle` and `Component`, configured with `#[component(...)]`
+#[proc_macro_derive(Component, attributes(component))]
 pub fn derive_component(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
     let world_path = ecs_path();
+    let component_impl = match component::component_impl(&world_path, &ast) {
+        Ok(r) => r,
+        Err(e) => return e.into_compile_error().into(),
+    };
  
     // let tuple_types: Vec<_> = field_types.iter().map(|x| quote! { #x }).collect();
     let struct_name = &ast.ident;
@@ -489,6 +466,8 @@ pub fn derive_component(input: TokenStream) -> TokenStream {
                 }
             }
 
+            #component_impl
+
             // impl #impl_generics #world_path::insert::BundleExt for #struct_name #ty_generics #where_clause {
             //     fn add_components(editor: &mut #world_path::editor::EntityEditor, e: #world_path::world::Entity,  component: Self) -> Result<(), #world_path::prelude::QueryError> {
             //        todo!()
@@ -573,7 +552,7 @@ fn derive_label_inner(input: TokenStream, label: &str) -> TokenStream {
 
 /// Get the fields of a data structure if that structure is a struct with named fields;
 /// otherwise, return a compile error that points to the site of the macro invocation.
-fn get_named_struct_fields(data: &syn::Data) -> syn::Result<&FieldsNamed> {
+pub(crate) fn get_named_struct_fields(data: &syn::Data) -> syn::Result<&FieldsNamed> {
     match data {
         Data::Struct(DataStruct {
             fields: Fields::Named(fields),
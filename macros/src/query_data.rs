@@ -0,0 +1,261 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Group, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote};
+use syn::{spanned::Spanned, Error, Ident};
+
+use crate::{ecs_path, get_named_struct_fields};
+
+// 元组最多15个元素，超过时折叠为嵌套的元组
+const LIMIT: usize = 15;
+
+/// Derive `FetchComponents` for a struct whose fields are query types
+///
+/// Generates `{Name}Item<'w>` with the fetched items, `{Name}ReadOnly` with the readonly
+/// query types and `{Name}ReadOnlyItem<'w>`. All accesses are delegated to the tuple of
+/// the field types, so the relations registered in `init_state` are the same as the tuple query.
+pub fn derive_query_data(input: syn::DeriveInput) -> TokenStream {
+    let path = ecs_path();
+    let fields = match get_named_struct_fields(&input.data) {
+        Ok(fields) => &fields.named,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let lifetime = match single_lifetime(&input.generics, "QueryData") {
+        Ok(lt) => lt,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let idents: Vec<&Ident> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let vis_list: Vec<_> = fields.iter().map(|f| &f.vis).collect();
+    let types: Vec<TokenStream2> = fields
+        .iter()
+        .map(|f| static_type(&f.ty, lifetime.as_ref()))
+        .collect();
+    let ro_types: Vec<TokenStream2> = types
+        .iter()
+        .map(|t| quote! { <#t as #path::fetch::FetchComponents>::ReadOnly })
+        .collect();
+
+    let vis = &input.vis;
+    let struct_name = &input.ident;
+    let item_name = format_ident!("{}Item", struct_name);
+    let ro_name = format_ident!("{}ReadOnly", struct_name);
+    let ro_item_name = format_ident!("{}ReadOnlyItem", struct_name);
+    let self_ty = match &lifetime {
+        Some(_) => quote! { #struct_name<'_> },
+        None => quote! { #struct_name },
+    };
+
+    let impl_fetch = fetch_impl(&path, self_ty, &item_name, quote! { #ro_name }, &idents, &types);
+    let impl_ro = fetch_impl(&path, quote! { #ro_name }, &ro_item_name, quote! { #ro_name }, &idents, &ro_types);
+
+    quote! {
+        #vis struct #item_name<'__w> {
+            #(#vis_list #idents: <#types as #path::fetch::FetchComponents>::Item<'__w>,)*
+        }
+        #[allow(dead_code)]
+        #vis struct #ro_name {
+            #(#vis_list #idents: #ro_types,)*
+        }
+        #vis struct #ro_item_name<'__w> {
+            #(#vis_list #idents: <#ro_types as #path::fetch::FetchComponents>::Item<'__w>,)*
+        }
+        #impl_fetch
+        #impl_ro
+    }
+    .into()
+}
+
+fn fetch_impl(
+    path: &syn::Path,
+    self_ty: TokenStream2,
+    item_name: &Ident,
+    ro_ty: TokenStream2,
+    idents: &[&Ident],
+    types: &[TokenStream2],
+) -> TokenStream2 {
+    let locals: Vec<Ident> = (0..idents.len()).map(|i| format_ident!("__f{}", i)).collect();
+    let (tuple, pattern) = fold_tuple(types, &locals);
+    quote! {
+        impl #path::fetch::FetchComponents for #self_ty {
+            type Fetch<'__w> = <#tuple as #path::fetch::FetchComponents>::Fetch<'__w>;
+            type Item<'__w> = #item_name<'__w>;
+            type ReadOnly = #ro_ty;
+            type State = <#tuple as #path::fetch::FetchComponents>::State;
+
+            fn init_state(world: &mut #path::world::World, meta: &mut #path::system::SystemMeta) -> Self::State {
+                <#tuple as #path::fetch::FetchComponents>::init_state(world, meta)
+            }
+            fn get_state(
+                world: &#path::world::World,
+                related: &mut #path::system::Related<#path::world::ComponentIndex>,
+            ) -> Result<Self::State, #path::query::QueryError> {
+                <#tuple as #path::fetch::FetchComponents>::get_state(world, related)
+            }
+            fn init_fetch<'__w>(
+                world: &'__w #path::world::World,
+                state: &'__w Self::State,
+                index: #path::archetype::ArchetypeIndex,
+                tick: #path::world::Tick,
+                last_run: #path::world::Tick,
+            ) -> Self::Fetch<'__w> {
+                <#tuple as #path::fetch::FetchComponents>::init_fetch(world, state, index, tick, last_run)
+            }
+            fn init_fetch_opt<'__w>(
+                world: &'__w #path::world::World,
+                state: &'__w Self::State,
+                index: #path::archetype::ArchetypeIndex,
+                tick: #path::world::Tick,
+                last_run: #path::world::Tick,
+            ) -> Option<Self::Fetch<'__w>> {
+                <#tuple as #path::fetch::FetchComponents>::init_fetch_opt(world, state, index, tick, last_run)
+            }
+            fn fetch<'__w>(
+                fetch: &Self::Fetch<'__w>,
+                row: #path::archetype::Row,
+                e: #path::world::Entity,
+            ) -> Self::Item<'__w> {
+                let #pattern = <#tuple as #path::fetch::FetchComponents>::fetch(fetch, row, e);
+                #item_name {
+                    #(#idents: #locals,)*
+                }
+            }
+            fn skip<'__w>(
+                fetch: &Self::Fetch<'__w>,
+                row: #path::archetype::Row,
+                e: #path::world::Entity,
+            ) -> bool {
+                <#tuple as #path::fetch::FetchComponents>::skip(fetch, row, e)
+            }
+        }
+    }
+}
+
+/// Derive `FilterComponents` for a struct whose fields are filters, all filters must pass
+pub fn derive_query_filter(input: syn::DeriveInput) -> TokenStream {
+    let path = ecs_path();
+    let fields = match get_named_struct_fields(&input.data) {
+        Ok(fields) => &fields.named,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let lifetime = match single_lifetime(&input.generics, "QueryFilter") {
+        Ok(lt) => lt,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let types: Vec<TokenStream2> = fields
+        .iter()
+        .map(|f| static_type(&f.ty, lifetime.as_ref()))
+        .collect();
+    let locals: Vec<Ident> = (0..types.len()).map(|i| format_ident!("__f{}", i)).collect();
+    let (tuple, _) = fold_tuple(&types, &locals);
+
+    let struct_name = &input.ident;
+    let self_ty = match &lifetime {
+        Some(_) => quote! { #struct_name<'_> },
+        None => quote! { #struct_name },
+    };
+    quote! {
+        impl #path::filter::FilterComponents for #self_ty {
+            type Filter<'__w> = <#tuple as #path::filter::FilterComponents>::Filter<'__w>;
+            type State = <#tuple as #path::filter::FilterComponents>::State;
+
+            fn init_state(world: &mut #path::world::World, meta: &mut #path::system::SystemMeta) -> Self::State {
+                <#tuple as #path::filter::FilterComponents>::init_state(world, meta)
+            }
+            fn get_state(
+                world: &#path::world::World,
+                related: &mut #path::system::Related<#path::world::ComponentIndex>,
+            ) -> Result<Self::State, #path::query::QueryError> {
+                <#tuple as #path::filter::FilterComponents>::get_state(world, related)
+            }
+            fn init_filter<'__w>(
+                world: &'__w #path::world::World,
+                state: &'__w Self::State,
+                index: #path::archetype::ArchetypeIndex,
+                tick: #path::world::Tick,
+                last_run: #path::world::Tick,
+            ) -> Self::Filter<'__w> {
+                <#tuple as #path::filter::FilterComponents>::init_filter(world, state, index, tick, last_run)
+            }
+            fn init_filter_opt<'__w>(
+                world: &'__w #path::world::World,
+                state: &'__w Self::State,
+                index: #path::archetype::ArchetypeIndex,
+                tick: #path::world::Tick,
+                last_run: #path::world::Tick,
+            ) -> (Self::Filter<'__w>, bool) {
+                <#tuple as #path::filter::FilterComponents>::init_filter_opt(world, state, index, tick, last_run)
+            }
+            fn filter<'__w>(
+                filter: &Self::Filter<'__w>,
+                row: #path::archetype::Row,
+                e: #path::world::Entity,
+            ) -> bool {
+                <#tuple as #path::filter::FilterComponents>::filter(filter, row, e)
+            }
+        }
+    }
+    .into()
+}
+
+// 只支持最多一个生命周期参数，不支持类型和常量参数
+fn single_lifetime(generics: &syn::Generics, name: &str) -> syn::Result<Option<Ident>> {
+    if generics.type_params().next().is_some()
+        || generics.const_params().next().is_some()
+        || generics.lifetimes().count() > 1
+    {
+        return Err(Error::new(
+            generics.span(),
+            format!("`{name}` only supports structs with at most one lifetime parameter"),
+        ));
+    }
+    Ok(generics.lifetimes().next().map(|l| l.lifetime.ident.clone()))
+}
+
+// 将字段类型中结构体的生命周期替换为'static
+fn static_type(ty: &syn::Type, lifetime: Option<&Ident>) -> TokenStream2 {
+    let tokens = quote! { #ty };
+    match lifetime {
+        Some(lt) => replace_lifetime(tokens, lt),
+        None => tokens,
+    }
+}
+
+fn replace_lifetime(tokens: TokenStream2, lifetime: &Ident) -> TokenStream2 {
+    let mut result = Vec::new();
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(t) = iter.next() {
+        match t {
+            TokenTree::Punct(p) if p.as_char() == '\'' => {
+                if let Some(TokenTree::Ident(i)) = iter.peek() {
+                    if i == lifetime {
+                        let span = i.span();
+                        iter.next();
+                        result.push(TokenTree::Punct(p));
+                        result.push(TokenTree::Ident(Ident::new("static", span)));
+                        continue;
+                    }
+                }
+                result.push(TokenTree::Punct(p));
+            }
+            TokenTree::Group(g) => {
+                let mut group = Group::new(g.delimiter(), replace_lifetime(g.stream(), lifetime));
+                group.set_span(g.span());
+                result.push(TokenTree::Group(group));
+            }
+            t => result.push(t),
+        }
+    }
+    result.into_iter().collect()
+}
+
+// 将类型和变量组合为元组类型及对应的解构模式
+fn fold_tuple(types: &[TokenStream2], locals: &[Ident]) -> (TokenStream2, TokenStream2) {
+    let mut types: Vec<TokenStream2> = types.to_vec();
+    let mut patterns: Vec<TokenStream2> = locals.iter().map(|l| quote! { #l }).collect();
+    while types.len() > LIMIT {
+        let end: Vec<_> = types.drain(..LIMIT).collect();
+        types.push(quote! { (#(#end,)*) });
+        let end: Vec<_> = patterns.drain(..LIMIT).collect();
+        patterns.push(quote! { (#(#end,)*) });
+    }
+    (quote! { (#(#types,)*) }, quote! { (#(#patterns,)*) })
+}
@@ -0,0 +1,112 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Error, Lit};
+
+use crate::ecs_path;
+
+/// Derive `Resource`
+///
+/// `#[resource(from_world)]` creates the resource with `FromWorld` when a system using it is
+/// initialized and the resource has not been inserted.
+pub fn derive_resource(input: syn::DeriveInput) -> TokenStream {
+    let path = ecs_path();
+    let mut from_world = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("resource")) {
+        let r = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("from_world") {
+                from_world = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `resource` attribute, expected `from_world`"))
+            }
+        });
+        if let Err(e) = r {
+            return e.into_compile_error().into();
+        }
+    }
+
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let init = if from_world {
+        quote! {
+            fn init_res(world: &mut #path::world::World) -> Option<Self> {
+                Some(<Self as #path::world::FromWorld>::from_world(world))
+            }
+        }
+    } else {
+        quote! {}
+    };
+    // 泛型类型无法在定义处判断，只检查非泛型类型
+    let check = if input.generics.params.is_empty() {
+        not_bundle(&path, quote! { #struct_name })
+    } else {
+        quote! {}
+    };
+    quote! {
+        impl #impl_generics #path::single_res::Resource for #struct_name #ty_generics #where_clause {
+            #init
+        }
+        #check
+    }
+    .into()
+}
+
+/// Derive `EventData`
+///
+/// `#[event(retain = N)]` drops the events after N settles of the world, even if they are not read.
+/// `#[event(retain = "read")]` is the default, events are kept until all readers have read them.
+pub fn derive_event(input: syn::DeriveInput) -> TokenStream {
+    let path = ecs_path();
+    let mut retain = quote! { #path::event::EventRetain::Read };
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("event")) {
+        let r = attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("retain") {
+                return Err(meta.error("unsupported `event` attribute, expected `retain`"));
+            }
+            match meta.value()?.parse::<Lit>()? {
+                Lit::Int(n) => {
+                    if n.base10_parse::<usize>()? == 0 {
+                        return Err(Error::new(n.span(), "`retain` must be greater than 0"));
+                    }
+                    retain = quote! { #path::event::EventRetain::Settles(#n) };
+                    Ok(())
+                }
+                Lit::Str(s) if s.value() == "read" => {
+                    retain = quote! { #path::event::EventRetain::Read };
+                    Ok(())
+                }
+                lit => Err(Error::new(
+                    lit.span(),
+                    "expected `retain = \"read\"` or `retain = N`",
+                )),
+            }
+        });
+        if let Err(e) = r {
+            return e.into_compile_error().into();
+        }
+    }
+
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics #path::event::EventData for #struct_name #ty_generics #where_clause {
+            const RETAIN: #path::event::EventRetain = #retain;
+        }
+    }
+    .into()
+}
+
+// 组件类型(实现了Bundle)不能作为资源，实现了Bundle时两个impl都匹配，类型推断失败
+fn not_bundle(path: &syn::Path, ty: TokenStream2) -> TokenStream2 {
+    quote! {
+        const _: fn() = || {
+            trait ComponentUsedAsResource<A> {
+                fn check() {}
+            }
+            impl<T: ?Sized> ComponentUsedAsResource<()> for T {}
+            impl<T: ?Sized + #path::insert::Bundle> ComponentUsedAsResource<u8> for T {}
+            let _ = <#ty as ComponentUsedAsResource<_>>::check;
+        };
+    }
+}
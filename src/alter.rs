@@ -28,7 +28,7 @@ use crate::fetch::FetchComponents;
 use crate::filter::FilterComponents;
 use crate::insert::Bundle;
 use crate::query::{LocalIndex, Query, QueryError, QueryIter, QueryState};
-use crate::system::SystemMeta;
+use crate::system::{Relation, SystemMeta};
 use crate::system_params::SystemParam;
 use crate::utils::VecExt;
 use crate::world::*;
@@ -250,9 +250,19 @@ impl<
 
     fn init_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::State {
         let q = Query::init_state(world, system_meta);
+        let add = A::components(Vec::with_capacity(256));
+        let remove = D::components(Vec::with_capacity(256));
+        // 稀疏存储的组件不在原型上，增删时需要单独声明写，用于冲突检测
+        let sparse: Vec<ComponentInfo> = add.iter().chain(remove.iter()).filter(|c| c.is_sparse()).cloned().collect();
+        if !sparse.is_empty() {
+            for info in sparse {
+                system_meta.component_relate(world, info, Relation::Write(0usize.into()));
+            }
+            system_meta.related_ok();
+        }
         QueryAlterState(
             q,
-            AlterState::make(world, A::components(Vec::with_capacity(256)), D::components(Vec::with_capacity(256))),
+            AlterState::make(world, add, remove),
             Ptr::new(world),
             PhantomData,
         )
@@ -391,7 +401,9 @@ impl<A: Bundle> AlterState<A> {
                     .assume_init_ref()
             };
             // 目标原型和源原型相同，直接写入
+            self.state.remove_sparse(e);
             A::insert(item, components, e, addr.row, tick);
+            self.state.insert_required(mapping, addr.row, e, tick);
             return Ok(false);
         }
         // 判断地址是否已经标记移动了，不允许一个system内修改一个entity原型2次
@@ -401,6 +413,7 @@ impl<A: Bundle> AlterState<A> {
         } else {
             addr.mark();
         }
+        self.state.remove_sparse(e);
         let (_, dst_row) = mapping.dst.alloc();
         // println!("alter: {:?}", (e, src_row, dst_row, &mapping.dst));
         let item = unsafe {
@@ -409,6 +422,7 @@ impl<A: Bundle> AlterState<A> {
                 .assume_init_ref()
         };
         A::insert(item, components, e, dst_row.into(), tick);
+        self.state.insert_required(mapping, dst_row.into(), e, tick);
         // 记录移除行
         mapping.push(
             addr.row,
@@ -434,18 +448,41 @@ pub struct AState {
     pub(crate) adding: Vec<Share<Column>>, // 所有映射添加的列
     moving: Vec<Share<Column>>,            // 所有映射移动的列
     removing: Vec<Share<Column>>,          // 所有映射移除的列
+    sparse_removing: Vec<Share<Column>>,   // 要移除的稀疏存储的列
+    required: Vec<(Share<Column>, fn(*mut u8))>, // 添加的组件所必需的组件，实体上没有时用默认值初始化
 }
 impl AState {
     pub(crate) fn make(
         world: &mut World,
-        add: Vec<ComponentInfo>,
+        mut add: Vec<ComponentInfo>,
         remove: Vec<ComponentInfo>,
     ) -> Self {
+        // 必需的组件不放入sorted_add_removes，源原型上没有时才添加
+        let len = add.len();
+        let required = ComponentInfo::add_required(&mut add);
+        let required_infos = add.split_off(len);
         let mut sorted_add_removes = Vec::with_capacity(256);
         world.add_component_indexs(add, &mut sorted_add_removes, true);
         world.add_component_indexs(remove, &mut sorted_add_removes, false);
         sorted_add_removes.sort_unstable();
-        Self::new(sorted_add_removes)
+        let mut state = Self::new(sorted_add_removes);
+        state.sparse_removing = state
+            .sorted_add_removes
+            .iter()
+            .filter(|(_, add)| !*add)
+            .map(|(index, _)| world.get_column(*index).unwrap())
+            .filter(|c| c.info().is_sparse())
+            .cloned()
+            .collect();
+        for (info, (_, init)) in required_infos.into_iter().zip(required) {
+            let (index, c) = world.add_component_info(info);
+            // 同时要移除的组件，不再添加
+            if state.sorted_add_removes.iter().any(|(i, _)| *i == index) {
+                continue;
+            }
+            state.required.push((c, init));
+        }
+        state
     }
 
     pub(crate) fn new(sorted_add_removes: Vec<(ComponentIndex, bool)>) -> Self {
@@ -456,9 +493,37 @@ impl AState {
             adding: Default::default(),
             moving: Default::default(),
             removing: Default::default(),
+            sparse_removing: Default::default(),
+            required: Default::default(),
             // removed_columns: Default::default(),
         }
     }
+    // 移除实体上稀疏存储的组件
+    pub(crate) fn remove_sparse(&self, e: Entity) {
+        for c in self.sparse_removing.iter() {
+            c.sparse_blob_ref().sparse_remove(e);
+        }
+    }
+    // 初始化实体上没有的必需组件，需要在写入添加的组件后调用
+    pub(crate) fn insert_required(&self, am: &ArchetypeMapping, dst_row: Row, e: Entity, tick: Tick) {
+        for (c, init) in self.required.iter() {
+            let (c, row) = if c.sparse.is_some() {
+                let c = c.sparse_blob_ref();
+                if !c.sparse_row(e).is_null() {
+                    continue;
+                }
+                let (row, _) = c.sparse_alloc(e);
+                (c, row)
+            } else {
+                if am.src.contains(c.info().index) {
+                    continue;
+                }
+                (c.blob_ref_unchecked(am.dst.index()), dst_row)
+            };
+            init(c.load(row, e));
+            c.added_tick(e, row, tick);
+        }
+    }
     // 放入本地映射
     pub(crate) fn push_map(&mut self, index: ArchetypeIndex, len: usize) {
         if len == 0 {
@@ -560,6 +625,11 @@ impl AState {
                     record.record(*e);
                 }
             }
+            if c.info.on_remove.is_some() {
+                for (_src_row, _dst_row, e) in am.moves.iter() {
+                    c.info.hook_removed(*e);
+                }
+            }
         }
     }
     // 计算源和目标原型，哪些组件是一样，一样就需要获得列位置映射。哪些组件是新增或移除的
@@ -576,10 +646,24 @@ impl AState {
         let add_start: usize = self.adding.len();
         let move_start = self.moving.len();
         let removing_start = self.removing.len();
+        // 源原型上没有的必需组件，也需要添加
+        let mut sorted = Vec::new();
+        let sorted_add_removes = if self.required.is_empty() {
+            &self.sorted_add_removes
+        } else {
+            sorted.extend_from_slice(&self.sorted_add_removes);
+            for (c, _) in self.required.iter() {
+                if !mapping.src.contains(c.info().index) {
+                    sorted.push((c.info().index, true));
+                }
+            }
+            sorted.sort_unstable();
+            &sorted
+        };
         // 如果本地没有找到，则创建components，去world上查找或创建
         let info = mapping.src.alter(
             world,
-            &mut self.sorted_add_removes,
+            sorted_add_removes,
             &mut self.adding,
             &mut self.moving,
             &mut self.removing,
@@ -624,6 +708,7 @@ impl AState {
         let (addr, _local_index) = self.check(world, e)?;
         if addr.row.is_null() {
             world.entities.remove(e).unwrap();
+            world.remove_sparse(e);
             return Ok(true);
         }
         let ar = unsafe { world.get_archetype_unchecked(addr.archetype_index()) };
@@ -636,6 +721,7 @@ impl AState {
             return Err(QueryError::NoSuchRow(row));
         }
         world.entities.remove(e).unwrap();
+        world.remove_sparse(e);
         Ok(true)
     }
     // // 检查entity是否正确，包括对应的原型是否在本查询内，并将查询到的原型本地位置记到cache_mapping上
@@ -760,6 +846,7 @@ impl ArchetypeMapping {
             if let Some(record) = &c.info.removed {
                 record.record(e);
             }
+            c.info.hook_removed(e);
         }
     }
 }
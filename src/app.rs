@@ -1,9 +1,11 @@
 //! App包含一个world，一个调度器，及一个运行时
 //!
 //!
+use std::collections::HashSet;
+
 use pi_async_rt::prelude::{AsyncRuntime, AsyncRuntimeExt};
 
-use crate::{schedule::{MainSchedule, Schedule}, schedule_config::{IntoSystemConfigs, IntoSystemSetConfigs, ScheduleLabel, StageLabel}, world::World};
+use crate::{plugin::{PlaceholderPlugin, Plugin}, schedule::{MainSchedule, Schedule, ScheduleBuildError}, schedule_config::{IntoSystemConfigs, IntoSystemSetConfigs, ScheduleLabel, StageLabel}, world::World};
 
 
 pub type App = AppInner<runtime::Runtime>;
@@ -14,7 +16,59 @@ pub struct AppInner<A: AsyncRuntime + AsyncRuntimeExt> {
     pub startup_schedule: Schedule,
     pub rt: A,
     pub is_first_run: bool,
+    // 已添加的插件，按添加顺序
+    pub(crate) plugin_registry: Vec<Box<dyn Plugin>>,
+    // 已添加的唯一插件的名字，用于检查重复
+    pub(crate) plugin_name_added: HashSet<String>,
+    // 依赖未满足，延迟build的插件
+    pub(crate) pending_plugins: Vec<Box<dyn Plugin>>,
+    // 正在build的插件的深度
+    pub(crate) building_plugin_depth: usize,
+    // 已调用finish的插件数量
+    finished_len: usize,
+    plugins_state: PluginsState,
+}
+
+/// 插件的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginsState {
+    /// 正在添加插件
+    Adding,
+    /// 所有的插件都已准备好
+    Ready,
+    /// 所有的插件都已调用finish
+    Finished,
+    /// 所有的插件都已调用cleanup
+    Cleaned,
+}
+
+/// App的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// 重复添加唯一插件
+    DuplicatePlugin { plugin_name: String },
+    /// 插件依赖的插件未添加
+    MissingDependency { plugin_name: String, dependency: String },
+    /// 插件之间循环依赖
+    DependencyCycle { plugins: Vec<String> },
+}
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::DuplicatePlugin { plugin_name } => {
+                write!(f, "duplicate plugin {}", plugin_name)
+            }
+            AppError::MissingDependency { plugin_name, dependency } => {
+                write!(f, "plugin {} depends on {}, which was not added", plugin_name, dependency)
+            }
+            AppError::DependencyCycle { plugins } => {
+                write!(f, "plugin dependency cycle {:?}", plugins)
+            }
+        }
+    }
 }
+impl std::error::Error for AppError {}
+
 impl AppInner<runtime::Runtime> {
     pub fn new() -> Self {
         App {
@@ -23,7 +77,131 @@ impl AppInner<runtime::Runtime> {
             startup_schedule: Schedule::new(false),
             rt: runtime::create_runtime(),
             is_first_run: true,
+            plugin_registry: Vec::new(),
+            plugin_name_added: HashSet::new(),
+            pending_plugins: Vec::new(),
+            building_plugin_depth: 0,
+            finished_len: 0,
+            plugins_state: PluginsState::Adding,
+        }
+    }
+
+    /// 插件的状态，如果在添加中，会检查所有插件是否已准备好
+    pub fn plugins_state(&mut self) -> PluginsState {
+        if self.plugins_state == PluginsState::Adding
+            && self.building_plugin_depth == 0
+            && self.pending_plugins.is_empty()
+            && self.plugin_registry.iter().all(|p| p.ready(self))
+        {
+            self.plugins_state = PluginsState::Ready;
+        }
+        self.plugins_state
+    }
+
+    /// 所有插件准备好后，依次调用插件的finish
+    pub fn finish(&mut self) {
+        if self.plugins_state() != PluginsState::Ready {
+            return;
+        }
+        // finish中添加的插件，放在后面，同样会调用finish
+        while self.finished_len < self.plugin_registry.len() {
+            let plugin = self.take_plugin(self.finished_len);
+            plugin.finish(self);
+            self.plugin_registry[self.finished_len] = plugin;
+            self.finished_len += 1;
+        }
+        self.plugins_state = PluginsState::Finished;
+    }
+
+    /// 所有插件finish后，依次调用插件的cleanup
+    pub fn cleanup(&mut self) {
+        if self.plugins_state != PluginsState::Finished {
+            return;
+        }
+        let mut i = 0;
+        while i < self.plugin_registry.len() {
+            let plugin = self.take_plugin(i);
+            // cleanup中添加的插件，先调用finish
+            if i >= self.finished_len {
+                plugin.finish(self);
+                self.finished_len = i + 1;
+            }
+            plugin.cleanup(self);
+            self.plugin_registry[i] = plugin;
+            i += 1;
+        }
+        self.plugins_state = PluginsState::Cleaned;
+    }
+
+    // 取出插件，用占位插件替代，插件的回调中可以继续添加插件
+    fn take_plugin(&mut self, i: usize) -> Box<dyn Plugin> {
+        std::mem::replace(&mut self.plugin_registry[i], Box::new(PlaceholderPlugin))
+    }
+
+    /// 检查延迟build的插件，返回缺少的依赖或循环依赖
+    pub fn check_plugin_dependencies(&self) -> Result<(), AppError> {
+        for plugin in self.pending_plugins.iter() {
+            for dependency in plugin.dependencies() {
+                if !self.is_plugin_added(dependency)
+                    && !self.pending_plugins.iter().any(|p| p.name() == dependency)
+                {
+                    return Err(AppError::MissingDependency {
+                        plugin_name: plugin.name().to_string(),
+                        dependency: dependency.to_string(),
+                    });
+                }
+            }
+        }
+        // 依赖都存在，但仍未build，说明循环依赖
+        if !self.pending_plugins.is_empty() {
+            return Err(AppError::DependencyCycle {
+                plugins: self.pending_plugins.iter().map(|p| p.name().to_string()).collect(),
+            });
+        }
+        Ok(())
+    }
+
+    // 插件是否已build
+    pub(crate) fn is_plugin_added(&self, name: &str) -> bool {
+        self.plugin_registry.iter().any(|p| p.name() == name)
+    }
+
+    // 插件的依赖是否都已build
+    pub(crate) fn is_plugin_deps_ready(&self, plugin: &dyn Plugin) -> bool {
+        plugin.dependencies().into_iter().all(|d| self.is_plugin_added(d))
+    }
+
+    // 第一次运行前，检查所有插件是否准备好，准备好后调用finish和cleanup，返回是否已完成
+    fn finish_plugins(&mut self) -> bool {
+        if self.plugins_state == PluginsState::Cleaned {
+            return true;
+        }
+        if let Err(e) = self.check_plugin_dependencies() {
+            panic!("{}", e);
+        }
+        // 异步准备的插件需要运行时推进，不能在这里等待，由下次运行再检查
+        if self.plugins_state() == PluginsState::Adding {
+            return false;
         }
+        self.finish();
+        self.cleanup();
+        true
+    }
+}
+
+// 插件的回调需要App，其他运行时的AppInner没有插件的生命周期
+trait PluginLifecycle {
+    // 插件是否都已finish和cleanup
+    fn plugins_cleaned(&mut self) -> bool;
+}
+impl<A: AsyncRuntime + AsyncRuntimeExt> PluginLifecycle for AppInner<A> {
+    default fn plugins_cleaned(&mut self) -> bool {
+        true
+    }
+}
+impl PluginLifecycle for App {
+    fn plugins_cleaned(&mut self) -> bool {
+        self.finish_plugins()
     }
 }
 
@@ -53,6 +231,22 @@ impl<A: AsyncRuntime + AsyncRuntimeExt> AppInner<A> {
         self
     }
 
+    /// 初始化启动日程和日程，一次返回所有的构建问题（环、system内的读写冲突、歧义）
+    pub fn initialize(&mut self) -> Result<(), ScheduleBuildError> {
+        let mut error = ScheduleBuildError::default();
+        if let Err(e) = self.startup_schedule.initialize(&mut self.world) {
+            error.extend(e);
+        }
+        if let Err(e) = self.schedule.initialize(&mut self.world) {
+            error.extend(e);
+        }
+        if error.is_empty() {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
     /// 同步运行日程
     /// schedule_label为None时， 表示运行所有的system
     /// 否则运行指定日程中的system
@@ -61,6 +255,10 @@ impl<A: AsyncRuntime + AsyncRuntimeExt> AppInner<A> {
         //     println!("item: {:?}", item);
         // }
         if self.is_first_run {
+            // 插件未准备好时不运行，由外部的运行循环再次调用时检查
+            if !self.plugins_cleaned() {
+                return;
+            }
             self.startup_schedule.run(&mut self.world, &self.rt, &MainSchedule.intern());
             self.is_first_run = false;
         }
@@ -73,6 +271,10 @@ impl<A: AsyncRuntime + AsyncRuntimeExt> AppInner<A> {
     /// 否则运行指定日程中的system
     pub fn run_schedule(&mut self, schedule_label: impl ScheduleLabel) {
         if self.is_first_run {
+            // 插件未准备好时不运行，由外部的运行循环再次调用时检查
+            if !self.plugins_cleaned() {
+                return;
+            }
             self.startup_schedule.run(&mut self.world, &self.rt, &MainSchedule.intern());
             self.is_first_run = false;
         }
@@ -85,6 +287,10 @@ impl<A: AsyncRuntime + AsyncRuntimeExt> AppInner<A> {
     /// 否则运行指定日程中的system
     pub async fn async_run(&mut self, schedule_label: impl ScheduleLabel) {
         if self.is_first_run {
+            // 插件未准备好时不运行，由外部的运行循环再次调用时检查
+            if !self.plugins_cleaned() {
+                return;
+            }
             self.startup_schedule.async_run(&mut self.world, &self.rt, &MainSchedule.intern()).await;
             self.is_first_run = false;
         }
@@ -97,6 +303,10 @@ impl<A: AsyncRuntime + AsyncRuntimeExt> AppInner<A> {
     /// 否则运行指定日程中的system
     pub async fn async_run_schedule(&mut self) {
         if self.is_first_run {
+            // 插件未准备好时不运行，由外部的运行循环再次调用时检查
+            if !self.plugins_cleaned() {
+                return;
+            }
             self.startup_schedule.async_run(&mut self.world, &self.rt, &MainSchedule.intern()).await;
             self.is_first_run = false;
         }
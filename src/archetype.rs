@@ -26,7 +26,7 @@ use pi_share::{Share, ShareBool};
 use crate::column::Column;
 use crate::system::TypeInfo;
 use crate::table::Table;
-use crate::world::{ComponentIndex, SetFromWorld, World};
+use crate::world::{ComponentIndex, Entity, SetFromWorld, World};
 
 pub type ShareArchetype = Share<Archetype>;
 
@@ -238,6 +238,10 @@ impl Archetype {
     pub fn ready(&self) -> bool {
         self.ready.load(Ordering::Relaxed)
     }
+    /// 释放未使用的内存，需要在World整理后调用，如果还有未整理的移除行，则返回false
+    pub fn shrink_to_fit(&mut self) -> bool {
+        self.table.shrink_to_fit()
+    }
     // 从本原型上计算改变后了原型信息， 在该原型下添加一些组件，删除一些组件，得到新原型信息，及移动的组件
     pub(crate) fn alter(
         &self,
@@ -259,6 +263,10 @@ impl Archetype {
             } else {
                 pre_index = *index;
             }
+            // 稀疏存储的组件不在原型上
+            if world.get_column(*index).unwrap().info().is_sparse() {
+                continue;
+            }
             loop {
                 if column_index >= len {
                     if *add {
@@ -403,6 +411,87 @@ impl ArchetypeInfo {
 }
 
 pub const COMPONENT_TICK: u8 = 1;
+
+/// 组件的存储方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StorageKind {
+    /// 存放在原型的表中
+    #[default]
+    Table,
+    /// 以实体为键存放在原型表外，增删组件不改变实体的原型，适合频繁增删的标记组件。
+    /// 查询只支持`&T`、`&mut T`、`With`、`Without`及`Changed`
+    Sparse,
+}
+
+/// 组件的钩子，在组件添加或移除后，由`World::run_hooks`统一调用
+pub type ComponentHook = fn(&mut World, Entity);
+
+/// 必需的组件，插入实体时如果没有该组件，则用默认值插入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequiredComponent {
+    pub info: fn() -> ComponentInfo,
+    pub init: fn(*mut u8),
+}
+impl RequiredComponent {
+    pub const fn of<T: Default + 'static>() -> Self {
+        RequiredComponent {
+            info: required_info::<T>,
+            init: write_default::<T>,
+        }
+    }
+}
+fn required_info<T: 'static>() -> ComponentInfo {
+    ComponentInfo::of::<T>(0)
+}
+fn write_default<T: Default>(ptr: *mut u8) {
+    unsafe { std::ptr::write(ptr as *mut T, T::default()) }
+}
+
+/// 组件的配置，一般用`#[derive(Component)]`及`#[component(...)]`属性实现，
+/// 没有实现的类型使用默认配置
+pub trait Component: 'static {
+    /// 存储方式
+    const STORAGE: StorageKind = StorageKind::Table;
+    /// 是否总是记录tick
+    const TICK: bool = false;
+    /// 组件添加后的钩子
+    const ON_ADD: Option<ComponentHook> = None;
+    /// 组件移除后的钩子
+    const ON_REMOVE: Option<ComponentHook> = None;
+    /// 必需的组件
+    const REQUIRED: &'static [RequiredComponent] = &[];
+}
+
+// 根据Component的配置设置组件信息
+trait ComponentConfig {
+    fn config(info: &mut ComponentInfo);
+    fn storage() -> StorageKind;
+}
+impl<T> ComponentConfig for T {
+    default fn config(_info: &mut ComponentInfo) {}
+    default fn storage() -> StorageKind {
+        StorageKind::Table
+    }
+}
+impl<T: Component> ComponentConfig for T {
+    fn storage() -> StorageKind {
+        T::STORAGE
+    }
+    fn config(info: &mut ComponentInfo) {
+        info.storage = T::STORAGE;
+        if T::TICK {
+            info.tick_info |= COMPONENT_TICK;
+        }
+        info.on_add = T::ON_ADD;
+        info.on_remove = T::ON_REMOVE;
+        info.required = T::REQUIRED;
+    }
+}
+/// 组件类型是否为稀疏存储
+#[inline(always)]
+pub fn is_sparse<T: 'static>() -> bool {
+    <T as ComponentConfig>::storage() == StorageKind::Sparse
+}
 // pub const COMPONENT_CHANGED: u8 = 2;
 // pub const COMPONENT_ADDED: u8 = 4;
 // pub const COMPONENT_REMOVED: u8 = 8;
@@ -415,17 +504,23 @@ pub struct ComponentInfo {
     pub index: ComponentIndex, // 在world上的索引
     pub mem_size: u32,             // 内存大小
     pub tick_info: u8,            // tick信息 tick = 1 changed = 2 added = 4 removed = 8
+    pub storage: StorageKind,      // 存储方式
+    pub on_add: Option<ComponentHook>,
+    pub on_remove: Option<ComponentHook>,
+    pub required: &'static [RequiredComponent], // 必需的组件
 }
 impl ComponentInfo {
     pub fn of<T: 'static>(tick_info: u8) -> ComponentInfo {
-        ComponentInfo::create(
+        let mut info = ComponentInfo::create(
             TypeId::of::<T>(),
             std::any::type_name::<T>().into(),
             get_drop::<T>(),
             <T as SetFromWorld>::set_fn(),
             size_of::<T>() as u32,
             tick_info,
-        )
+        );
+        <T as ComponentConfig>::config(&mut info);
+        info
     }
     pub fn create(
         type_id: TypeId,
@@ -443,7 +538,35 @@ impl ComponentInfo {
             mem_size,
             index: ComponentIndex::null(),
             tick_info,
+            storage: StorageKind::Table,
+            on_add: None,
+            on_remove: None,
+            required: &[],
+        }
+    }
+    pub fn is_sparse(&self) -> bool {
+        self.storage == StorageKind::Sparse
+    }
+    pub fn has_hooks(&self) -> bool {
+        self.on_add.is_some() || self.on_remove.is_some()
+    }
+    /// 将列表中组件必需的组件加入列表，返回加入的组件类型及初始化函数
+    pub fn add_required(components: &mut Vec<ComponentInfo>) -> Vec<(TypeId, fn(*mut u8))> {
+        let mut result = Vec::new();
+        let mut i = 0;
+        while i < components.len() {
+            let required = components[i].required;
+            for r in required {
+                let info = (r.info)();
+                if components.iter().any(|c| c.type_id() == info.type_id()) {
+                    continue;
+                }
+                result.push((*info.type_id(), r.init));
+                components.push(info);
+            }
+            i += 1;
         }
+        result
     }
     pub fn type_id(&self) -> &TypeId {
         &self.type_info.type_id
@@ -98,6 +98,14 @@ where
             self.param.archetype_align_len = world.archetype_arr.len();
         }
     }
+    #[inline]
+    fn system_meta(&self) -> Option<&SystemMeta> {
+        Some(&self.param.system_meta)
+    }
+    #[inline]
+    fn system_meta_mut(&mut self) -> Option<&mut SystemMeta> {
+        Some(&mut self.param.system_meta)
+    }
 }
 impl<Marker: 'static, Out: 'static + Send + Sync, F> AsyncRunSystem for AsyncFunctionSystem<Marker, Out, F>
 where
@@ -109,11 +109,37 @@ impl BucketsBlob {
         self.buckets().load_alloc(&loc)
     }
 
+    /// 从start开始内存连续的结束位置，不超过end。vec上的数据是连续的，每个bucket内的数据是连续的
+    pub fn contiguous_end(&self, start: usize, end: usize) -> usize {
+        if start < self.vec_capacity() {
+            return end.min(self.vec_capacity());
+        }
+        let loc = Location::of(start - self.capacity);
+        end.min(start - loc.entry + loc.len)
+    }
+
     /// 整理内存，将bucket_arr的数据移到vec上，并将当前vec_capacity容量扩容len+additional
     pub fn settle(&mut self, len: usize, additional: usize, multiple: usize) {
         self.remain_settle(0..len, len, additional, multiple);
     }
 
+    /// 整理内存，并释放len之后未使用的容量
+    pub fn shrink_to_fit(&mut self, len: usize, multiple: usize) {
+        self.settle(len, 0, multiple);
+        // 未分配或零大小类型的容量不需要释放
+        if self.capacity.is_null() || self.capacity >= usize::MAX - 1 || multiple == 0 {
+            return;
+        }
+        if self.capacity <= len {
+            return;
+        }
+        let mut vec = to_vec(self.ptr, self.capacity * multiple);
+        vec.truncate(len * multiple);
+        vec.shrink_to_fit();
+        self.capacity = vec.capacity() / multiple;
+        self.ptr = vec.into_raw_parts().0;
+    }
+
     fn reset_vec(buckets: [Vec<u8>; BUCKETS], multiple: usize) -> [Vec<u8>; BUCKETS] {
         buckets.map(|vec| {
             let len = vec.len() * multiple;
@@ -290,9 +316,26 @@ impl VecBlob {
         }
         return unsafe { &mut *(*self.ptr.get()).add(index * multiple) };
     }
+    /// 从start开始内存连续的结束位置，vec上的数据总是连续的
+    pub fn contiguous_end(&self, _start: usize, end: usize) -> usize {
+        end
+    }
     /// 整理内存，将bucket_arr的数据移到vec上，并将当前vec_capacity容量扩容len+additional
     pub fn settle(&mut self, _len: usize, _additional: usize, _multiple: usize) {}
 
+    /// 释放len之后未使用的容量
+    pub fn shrink_to_fit(&mut self, len: usize, multiple: usize) {
+        let capacity = self.vec_capacity();
+        if capacity.is_null() || capacity <= len || multiple == 0 {
+            return;
+        }
+        let mut vec = to_vec(unsafe { *self.ptr.get() }, capacity * multiple);
+        vec.truncate(len * multiple);
+        vec.shrink_to_fit();
+        unsafe { self.capacity.replace(vec.capacity() / multiple) };
+        unsafe { self.ptr.replace(vec.into_raw_parts().0) };
+    }
+
     fn reserve(&self, mut vec: Vec<u8>, len: usize, mut additional: usize, multiple: usize) {
         additional = (len + additional).saturating_sub(self.vec_capacity());
         if additional > 0 {
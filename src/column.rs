@@ -12,8 +12,9 @@ use pi_null::Null;
 use pi_share::Share;
 
 use crate::{
-    archetype::{Archetype, ArchetypeIndex, ComponentInfo, Row, ShareArchetype},
+    archetype::{Archetype, ArchetypeIndex, ComponentHook, ComponentInfo, Row, ShareArchetype},
     event::ComponentEventVec,
+    sparse::SparseSet,
     world::{Entity, Tick},
 };
 
@@ -23,10 +24,14 @@ pub static COMPONENT_INDEX: AtomicUsize = AtomicUsize::new(usize::MAX);
 #[cfg(debug_assertions)]
 pub static ARCHETYPE_INDEX: AtomicUsize = AtomicUsize::new(usize::MAX);
 
+/// 等待运行的组件钩子
+pub(crate) type HookQueue = SafeVec<(ComponentHook, Entity)>;
+
 pub struct Column {
     pub(crate) info: ColumnInfo,
     pub(crate) arr: Arr<BlobTicks>,
     pub(crate) last_len: SyncUnsafeCell<usize>,
+    pub(crate) sparse: Option<SparseSet>, // 稀疏存储的组件，数据放在该集合中，不在原型上
 }
 impl Column {
     pub fn memsize(&self) -> usize {
@@ -40,21 +45,31 @@ impl Column {
         // self.arr.slice(Range{ start: 0, end: self.arr.capacity(0) }).for_each(|item| {
         //     result += item.memsize();
         // });
+        if let Some(sparse) = &self.sparse {
+            result += sparse.memsize();
+        }
         result += self.info.memsize();
         result
     }
     // #[inline(always)]
     pub fn new(info: ComponentInfo) -> Self {
         // log::warn!("New Column");
+        let sparse = if info.is_sparse() {
+            Some(SparseSet::new(info.size(), info.drop_fn))
+        } else {
+            None
+        };
         Self {
             info: ColumnInfo {
                 changed: None,
                 added: None,
                 removed: None,
+                hook_queue: None,
                 info,
             },
             arr: Arr::default(),
             last_len: SyncUnsafeCell::new(0usize.into()),
+            sparse,
         }
     }
     #[inline(always)]
@@ -65,6 +80,13 @@ impl Column {
     pub fn info_mut(&mut self) -> &mut ComponentInfo {
         &mut self.info.info
     }
+    /// 获得该列在指定原型上的内存大小，返回(blob, ticks)
+    pub(crate) fn archetype_memsize(&self, index: ArchetypeIndex) -> (usize, usize) {
+        match self.arr.load(index.index()) {
+            Some(b) if !b.blob.vec_capacity().is_null() => (b.blob_memsize(), b.ticks_memsize()),
+            _ => (0, 0),
+        }
+    }
     // 初始化原型对应列的blob
     pub fn init_blob(&self, index: ArchetypeIndex) {
         *unsafe { &mut *self.last_len.get() } = index.index() + 1;
@@ -82,6 +104,16 @@ impl Column {
             None => false,
         }
     }
+    // 检查列在指定原型上的blob是否能容纳len行
+    pub(crate) fn check_len(&self, index: ArchetypeIndex, len: usize) -> bool {
+        if self.info.size() == 0 || len == 0 {
+            return true;
+        }
+        match self.arr.load(index.index()) {
+            Some(b) => b.blob.get_multiple(len - 1, self.info.size()).is_some(),
+            None => false,
+        }
+    }
     // #[inline(always)]
     pub fn blob_ref_unchecked(&self, index: ArchetypeIndex) -> BlobRef<'_> {
         #[cfg(debug_assertions)]
@@ -135,6 +167,34 @@ impl Column {
             index,
         ))
     }
+    /// 稀疏存储组件的BlobRef，行需要通过sparse_row由实体获得
+    pub(crate) fn sparse_blob_ref(&self) -> BlobRef<'_> {
+        let sparse = self.sparse.as_ref().unwrap();
+        let mut r = BlobRef::new(
+            &sparse.blob,
+            &self.info,
+            #[cfg(debug_assertions)]
+            ArchetypeIndex::null(),
+        );
+        r.sparse = Some(sparse);
+        r
+    }
+    /// 查询时获得原型上的BlobRef，稀疏存储的组件返回稀疏集合的BlobRef
+    pub(crate) fn fetch_blob_ref(&self, index: ArchetypeIndex) -> Option<BlobRef<'_>> {
+        if self.sparse.is_some() {
+            return Some(self.sparse_blob_ref());
+        }
+        self.blob_ref(index)
+    }
+    /// 获得实体所在原型上的BlobRef及行，稀疏存储的组件从稀疏集合中获取
+    pub(crate) fn entity_ref(&self, index: ArchetypeIndex, row: Row, e: Entity) -> Option<(BlobRef<'_>, Row)> {
+        if self.sparse.is_some() {
+            let r = self.sparse_blob_ref();
+            let row = r.sparse_row(e);
+            return if row.is_null() { None } else { Some((r, row)) };
+        }
+        self.blob_ref(index).map(|r| (r, row))
+    }
     /// 扫描当前的所有原型，设置已有的实体，主要是解决不同的Plugin，依次添加时，Changed监听和tick被后设置的问题
     pub(crate) fn update<F>(&self, archetypes: &SafeVec<ShareArchetype>, set_fn: F)
     where
@@ -162,12 +222,52 @@ impl Column {
         }
         // log::warn!("Column {:?}", size);
     }
+    /// 钳制该列所有实体过旧的tick
+    pub(crate) fn check_change_ticks(&self, archetypes: &SafeVec<ShareArchetype>, now: Tick) {
+        self.update(archetypes, |r, row, _| {
+            let mut tick = r.get_tick_unchecked(row);
+            if tick.check_tick(now) {
+                r.set_tick_unchecked(row, tick);
+            }
+        });
+        // 稀疏存储的组件
+        if let Some(sparse) = &self.sparse {
+            let r = self.sparse_blob_ref();
+            for row in sparse.rows() {
+                let mut tick = r.get_tick_unchecked(row);
+                if tick.check_tick(now) {
+                    r.set_tick_unchecked(row, tick);
+                }
+            }
+        }
+    }
     /// 整理内存
     pub(crate) fn settle(&mut self) {
         let len = *self.last_len.get_mut();
         if len > self.arr.vec_capacity() {
             self.arr.settle(len, 0);
         }
+        if let Some(sparse) = &mut self.sparse {
+            sparse.settle(self.info.is_tick());
+        }
+    }
+    /// 释放稀疏集合未使用的内存，原型上的内存由原型释放
+    pub(crate) fn shrink_to_fit(&mut self) {
+        if let Some(sparse) = &mut self.sparse {
+            sparse.shrink_to_fit(self.info.is_tick());
+        }
+    }
+    /// 释放指定原型未使用的内存，需要在该原型整理后调用
+    pub(crate) fn shrink_by_index(&mut self, index: ArchetypeIndex, len: usize) {
+        if self.info.size() == 0 {
+            return;
+        }
+        let blob = unsafe { self.arr.get_unchecked_mut(index.index()) };
+        blob.blob.shrink_to_fit(len, self.info.size());
+        if self.info.is_tick() {
+            // 将ticks的bucket合并到连续内存上
+            blob.ticks.settle(len, 0);
+        }
     }
     /// 整理合并指定原型的空位
     pub(crate) fn settle_by_index(
@@ -243,6 +343,7 @@ pub(crate) struct ColumnInfo {
     pub(crate) changed: Option<Share<ComponentEventVec>>,
     pub(crate) added: Option<Share<ComponentEventVec>>,
     pub(crate) removed: Option<Share<ComponentEventVec>>,
+    pub(crate) hook_queue: Option<Share<HookQueue>>, // 组件有钩子时，指向world的钩子队列
     pub(crate) info: ComponentInfo,
 }
 impl Deref for ColumnInfo {
@@ -253,6 +354,10 @@ impl Deref for ColumnInfo {
 }
 impl ColumnInfo {
     pub fn memsize(&self) -> usize {
+        self.event_memsize() + self.info.size()
+    }
+    /// 组件的changed、added、removed事件的内存大小
+    pub fn event_memsize(&self) -> usize {
         let mut result = 0;
         if let Some(item) = &self.changed {
             result += item.capacity();
@@ -263,20 +368,38 @@ impl ColumnInfo {
         if let Some(item) = &self.removed {
             result += item.capacity();
         }
-        result += self.info.size();
         result
     }
+    /// 组件添加后，如果有钩子，则放入钩子队列
+    #[inline]
+    pub(crate) fn hook_added(&self, e: Entity) {
+        if let (Some(hook), Some(queue)) = (self.info.on_add, &self.hook_queue) {
+            queue.insert((hook, e));
+        }
+    }
+    /// 组件移除后，如果有钩子，则放入钩子队列
+    #[inline]
+    pub(crate) fn hook_removed(&self, e: Entity) {
+        if let (Some(hook), Some(queue)) = (self.info.on_remove, &self.hook_queue) {
+            queue.insert((hook, e));
+        }
+    }
 }
 
 #[derive(Default)]
 pub(crate) struct BlobTicks {
-    blob: Blob,
+    pub(crate) blob: Blob,
     pub(crate) ticks: Arr<Tick>,
 }
 impl BlobTicks {
     pub fn memsize(&self) -> usize {
-        
-        self.blob.memsize() + self.ticks.capacity(0) * 4
+        self.blob_memsize() + self.ticks_memsize()
+    }
+    pub fn blob_memsize(&self) -> usize {
+        self.blob.memsize()
+    }
+    pub fn ticks_memsize(&self) -> usize {
+        self.ticks.capacity(0) * 4
     }
 }
 
@@ -284,6 +407,7 @@ impl BlobTicks {
 pub struct BlobRef<'a> {
     pub(crate) blob: &'a BlobTicks,
     pub(crate) info: &'a ColumnInfo,
+    pub(crate) sparse: Option<&'a SparseSet>, // 稀疏存储的组件，为Some
     #[cfg(debug_assertions)]
     index: ArchetypeIndex,
 }
@@ -291,17 +415,54 @@ pub struct BlobRef<'a> {
 impl<'a> BlobRef<'a> {
     // #[inline(always)]
     pub(crate) fn new(
-        blob: &'a mut BlobTicks,
+        blob: &'a BlobTicks,
         info: &'a ColumnInfo,
         #[cfg(debug_assertions)] index: ArchetypeIndex,
     ) -> Self {
         Self {
             blob,
             info,
+            sparse: None,
             #[cfg(debug_assertions)]
             index,
         }
     }
+    /// 稀疏存储时，实体所在的行，没有该组件则返回null
+    #[inline]
+    pub fn sparse_row(&self, e: Entity) -> Row {
+        self.sparse.unwrap().row(e)
+    }
+    /// 组件所在的行，稀疏存储时由实体获得，实体没有该组件则返回null
+    #[inline]
+    pub fn entity_row(&self, row: Row, e: Entity) -> Row {
+        match self.sparse {
+            Some(s) => s.row(e),
+            None => row,
+        }
+    }
+    /// 稀疏存储时，为实体分配行，返回行及实体是否已有该组件，已有则先释放旧的组件数据
+    pub(crate) fn sparse_alloc(&self, e: Entity) -> (Row, bool) {
+        let (row, existed) = self.sparse.unwrap().alloc(e);
+        if existed {
+            self.drop_row(row, e);
+        }
+        (row, existed)
+    }
+    /// 稀疏存储时，移除实体的组件，返回是否有该组件
+    pub(crate) fn sparse_remove(&self, e: Entity) -> bool {
+        let sparse = self.sparse.unwrap();
+        let row = sparse.remove(e);
+        if row.is_null() {
+            return false;
+        }
+        self.drop_row(row, e);
+        sparse.free(row);
+        if let Some(record) = &self.info.removed {
+            record.record(e);
+        }
+        self.info.hook_removed(e);
+        true
+    }
     // #[inline(always)]
     pub fn get_tick_unchecked(&self, row: Row) -> Tick {
         self.blob
@@ -312,6 +473,7 @@ impl<'a> BlobRef<'a> {
     // #[inline]
     pub fn added_tick(&self, e: Entity, row: Row, tick: Tick) {
         // println!("added_tick===={:?}", (e, row, tick, self.info.type_name()));
+        self.info.hook_added(e);
         if !self.info.is_tick() {
             return;
         }
@@ -327,7 +489,7 @@ impl<'a> BlobRef<'a> {
             return;
         }
         let old = unsafe { self.blob.ticks.load_alloc(row.index()) };
-        if *old >= tick {
+        if !tick.is_newer_than(*old) {
             return;
         }
         *old = tick;
@@ -382,6 +544,34 @@ impl<'a> BlobRef<'a> {
         unsafe { transmute(self.load_blob(row)) }
     }
 
+    /// 从start开始内存连续的结束行，不超过end
+    pub fn contiguous_end(&self, start: Row, end: Row) -> Row {
+        Row(self.blob.blob.contiguous_end(start.index(), end.index()) as u32)
+    }
+    /// start到end的连续内存，必须保证该范围在contiguous_end内
+    pub fn slice<T>(&self, start: Row, end: Row) -> &'a [T] {
+        if start.0 >= end.0 {
+            return &[];
+        }
+        unsafe {
+            std::slice::from_raw_parts(
+                self.get_blob_unchecked(start) as *const T,
+                end.index() - start.index(),
+            )
+        }
+    }
+    /// start到end的可变连续内存，必须保证该范围在contiguous_end内
+    pub fn slice_mut<T>(&self, start: Row, end: Row) -> &'a mut [T] {
+        if start.0 >= end.0 {
+            return &mut [];
+        }
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.get_blob_unchecked(start) as *mut T,
+                end.index() - start.index(),
+            )
+        }
+    }
     #[inline(always)]
     pub fn get_unchecked_mut<T>(&self, row: Row) -> &'a mut T {
         // self.trace(row, e, "get", std::ptr::null_mut());
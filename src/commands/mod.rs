@@ -1,10 +1,11 @@
 #![allow(warnings)]
 mod command_queue;
-use crate::{insert::Bundle, prelude::{Entity, World}};
+use std::mem::transmute;
+
+use crate::{insert::Bundle, prelude::{Entity, World}, system::{SystemId, SystemMeta}, system_params::SystemParam, world_ptr::Ptr};
 use crate as pi_world;
 
 pub use command_queue::CommandQueue;
-use pi_world_macros::SystemParam;
 // // mod parallel_scope;
 
 // // use crate::{
@@ -118,9 +119,37 @@ pub trait Command: Send + 'static {
 pub struct Commands<'w> {
     // queue: Deferred<'w, CommandQueue>,
     insert: crate::insert::Insert<'w, ()>,
+    systems: SystemQueue<'w>,
     // entities: &'w Entities,
 }
 
+/// 一次性system的等待队列，只追加system id，不读写world的数据
+pub struct SystemQueue<'w>(&'w World);
+
+impl<'w> SystemQueue<'w> {
+    pub fn push(&self, id: SystemId) {
+        self.0.queue_system(id);
+    }
+}
+
+impl SystemParam for SystemQueue<'_> {
+    type State = Ptr<World>;
+    type Item<'w> = SystemQueue<'w>;
+
+    fn init_state(world: &mut World, _system_meta: &mut SystemMeta) -> Self::State {
+        Ptr::new(world)
+    }
+
+    #[inline]
+    fn get_param<'world>(state: &'world mut Self::State) -> Self::Item<'world> {
+        SystemQueue(&*state)
+    }
+    #[inline]
+    fn get_self<'world>(state: &'world mut Self::State) -> Self {
+        unsafe { transmute(Self::get_param(state)) }
+    }
+}
+
 // impl SystemBuffer for CommandQueue {
 //     #[inline]
 //     fn apply(&mut self, _system_meta: &SystemMeta, world: &mut World) {
@@ -198,6 +227,11 @@ impl<'w> Commands<'w> {
         }
     }
 
+    /// 在本次调度结束时，运行指定的一次性system
+    pub fn run_system(&mut self, id: SystemId) {
+        self.systems.push(id);
+    }
+
 //     /// Pushes a [`Command`] to the queue for creating a new [`Entity`] if the given one does not exists,
 //     /// and returns its corresponding [`EntityCommands`].
 //     ///
@@ -0,0 +1,309 @@
+//! 运行条件
+//!
+//! 条件是返回bool的system，通过`run_if`添加到system或系统集上。
+//! 提供条件的组合器（`and_then`、`or_else`、`not`），及一些常用的条件：
+//! `resource_exists`、`resource_changed`、`on_event`、`run_once`、`any_with_component`、`every_n_runs`
+
+use std::{any::TypeId, borrow::Cow};
+
+use crate::{
+    event::{Event, EventData},
+    filter::With,
+    pipe::SystemInput,
+    query::Query,
+    schedule_config::Condition,
+    single_res::{Resource, SingleRes},
+    system::{IntoSystem, RunSystem, System, SystemMeta, TypeInfo},
+    world::{Entity, Tick, World},
+};
+
+/// 组合条件的标记
+pub struct IsCombinator;
+
+// 合并被组合条件的读写关系，供冲突和歧义分析
+fn merge_meta(meta: &SystemMeta, metas: &[Option<&SystemMeta>]) -> SystemMeta {
+    let mut r = SystemMeta::new(meta.type_info.clone());
+    for m in metas.iter().flatten() {
+        r.merge(m);
+    }
+    r
+}
+
+/// 与条件，a为false时，不再运行b
+pub struct AndThen<A, B> {
+    a: A,
+    b: B,
+    system_meta: SystemMeta,
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> AndThen<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        let type_info = TypeInfo {
+            type_id: TypeId::of::<Self>(),
+            type_name: Cow::Owned(format!("{} && {}", a.name(), b.name())),
+        };
+        Self {
+            a,
+            b,
+            system_meta: SystemMeta::new(type_info),
+        }
+    }
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> System for AndThen<A, B> {
+    type Out = bool;
+    #[inline]
+    fn name(&self) -> &Cow<'static, str> {
+        &self.system_meta.type_info.type_name
+    }
+    #[inline]
+    fn id(&self) -> TypeId {
+        TypeId::of::<Self>()
+    }
+    fn initialize(&mut self, world: &mut World) {
+        self.a.initialize(world);
+        self.b.initialize(world);
+        self.system_meta = merge_meta(&self.system_meta, &[self.a.system_meta(), self.b.system_meta()]);
+    }
+    fn align(&mut self, world: &World) {
+        self.a.align(world);
+        self.b.align(world);
+    }
+    #[inline]
+    fn system_meta(&self) -> Option<&SystemMeta> {
+        Some(&self.system_meta)
+    }
+    #[inline]
+    fn system_meta_mut(&mut self) -> Option<&mut SystemMeta> {
+        Some(&mut self.system_meta)
+    }
+    fn check_change_ticks(&mut self, now: Tick) {
+        self.a.check_change_ticks(now);
+        self.b.check_change_ticks(now);
+        self.system_meta.check_change_ticks(now);
+    }
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> RunSystem for AndThen<A, B> {
+    #[inline]
+    fn run(&mut self, world: &World) -> bool {
+        self.a.run(world) && self.b.run(world)
+    }
+}
+
+impl<A: SystemInput<Input = ()>, B: SystemInput<Input = ()>> SystemInput for AndThen<A, B> {
+    type Input = ();
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> IntoSystem<IsCombinator, bool>
+    for AndThen<A, B>
+{
+    type System = Self;
+    #[inline]
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+/// 或条件，a为true时，不再运行b
+pub struct OrElse<A, B> {
+    a: A,
+    b: B,
+    system_meta: SystemMeta,
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> OrElse<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        let type_info = TypeInfo {
+            type_id: TypeId::of::<Self>(),
+            type_name: Cow::Owned(format!("{} || {}", a.name(), b.name())),
+        };
+        Self {
+            a,
+            b,
+            system_meta: SystemMeta::new(type_info),
+        }
+    }
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> System for OrElse<A, B> {
+    type Out = bool;
+    #[inline]
+    fn name(&self) -> &Cow<'static, str> {
+        &self.system_meta.type_info.type_name
+    }
+    #[inline]
+    fn id(&self) -> TypeId {
+        TypeId::of::<Self>()
+    }
+    fn initialize(&mut self, world: &mut World) {
+        self.a.initialize(world);
+        self.b.initialize(world);
+        self.system_meta = merge_meta(&self.system_meta, &[self.a.system_meta(), self.b.system_meta()]);
+    }
+    fn align(&mut self, world: &World) {
+        self.a.align(world);
+        self.b.align(world);
+    }
+    #[inline]
+    fn system_meta(&self) -> Option<&SystemMeta> {
+        Some(&self.system_meta)
+    }
+    #[inline]
+    fn system_meta_mut(&mut self) -> Option<&mut SystemMeta> {
+        Some(&mut self.system_meta)
+    }
+    fn check_change_ticks(&mut self, now: Tick) {
+        self.a.check_change_ticks(now);
+        self.b.check_change_ticks(now);
+        self.system_meta.check_change_ticks(now);
+    }
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> RunSystem for OrElse<A, B> {
+    #[inline]
+    fn run(&mut self, world: &World) -> bool {
+        self.a.run(world) || self.b.run(world)
+    }
+}
+
+impl<A: SystemInput<Input = ()>, B: SystemInput<Input = ()>> SystemInput for OrElse<A, B> {
+    type Input = ();
+}
+
+impl<A: RunSystem<Out = bool>, B: RunSystem<Out = bool>> IntoSystem<IsCombinator, bool>
+    for OrElse<A, B>
+{
+    type System = Self;
+    #[inline]
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+/// 非条件
+pub struct NotSystem<A> {
+    a: A,
+    system_meta: SystemMeta,
+}
+
+impl<A: RunSystem<Out = bool>> NotSystem<A> {
+    pub fn new(a: A) -> Self {
+        let type_info = TypeInfo {
+            type_id: TypeId::of::<Self>(),
+            type_name: Cow::Owned(format!("!{}", a.name())),
+        };
+        Self {
+            a,
+            system_meta: SystemMeta::new(type_info),
+        }
+    }
+}
+
+impl<A: RunSystem<Out = bool>> System for NotSystem<A> {
+    type Out = bool;
+    #[inline]
+    fn name(&self) -> &Cow<'static, str> {
+        &self.system_meta.type_info.type_name
+    }
+    #[inline]
+    fn id(&self) -> TypeId {
+        TypeId::of::<Self>()
+    }
+    fn initialize(&mut self, world: &mut World) {
+        self.a.initialize(world);
+        self.system_meta = merge_meta(&self.system_meta, &[self.a.system_meta()]);
+    }
+    fn align(&mut self, world: &World) {
+        self.a.align(world);
+    }
+    #[inline]
+    fn system_meta(&self) -> Option<&SystemMeta> {
+        Some(&self.system_meta)
+    }
+    #[inline]
+    fn system_meta_mut(&mut self) -> Option<&mut SystemMeta> {
+        Some(&mut self.system_meta)
+    }
+    fn check_change_ticks(&mut self, now: Tick) {
+        self.a.check_change_ticks(now);
+        self.system_meta.check_change_ticks(now);
+    }
+}
+
+impl<A: RunSystem<Out = bool>> RunSystem for NotSystem<A> {
+    #[inline]
+    fn run(&mut self, world: &World) -> bool {
+        !self.a.run(world)
+    }
+}
+
+impl<A: SystemInput<Input = ()>> SystemInput for NotSystem<A> {
+    type Input = ();
+}
+
+impl<A: RunSystem<Out = bool>> IntoSystem<IsCombinator, bool> for NotSystem<A> {
+    type System = Self;
+    #[inline]
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+/// 对条件取反
+pub fn not<M, C: Condition<M>>(condition: C) -> NotSystem<C::System> {
+    NotSystem::new(condition.into_system())
+}
+
+/// 单例资源存在时，条件成立
+pub fn resource_exists<T: Resource>(res: Option<SingleRes<T>>) -> bool {
+    res.is_some()
+}
+
+/// 单例资源自上次运行该条件后被修改过，条件成立
+pub fn resource_changed<T: Resource>(res: Option<SingleRes<T>>) -> bool {
+    match res {
+        Some(r) => r.is_changed(),
+        None => false,
+    }
+}
+
+/// 有未读的事件时，条件成立。事件会被标记为已读
+pub fn on_event<E: EventData>(e: Event<E>) -> bool {
+    let r = e.len() > 0;
+    e.mark_read();
+    r
+}
+
+/// 仅第一次运行时，条件成立
+pub fn run_once() -> impl FnMut() -> bool + Clone + Send + Sync + 'static {
+    let mut has_run = false;
+    move || {
+        if has_run {
+            false
+        } else {
+            has_run = true;
+            true
+        }
+    }
+}
+
+/// 存在带有组件T的实体时，条件成立
+pub fn any_with_component<T: 'static>(q: Query<Entity, With<T>>) -> bool {
+    !q.is_empty()
+}
+
+/// 条件每被运行n次，成立1次（第一次运行时成立）
+/// 计数的是条件的运行次数，而不是world的tick
+pub fn every_n_runs(n: u32) -> impl FnMut() -> bool + Clone + Send + Sync + 'static {
+    assert!(n > 0, "every_n_runs, n must be greater than 0");
+    let mut count = 0u32;
+    move || {
+        let r = count == 0;
+        count += 1;
+        if count >= n {
+            count = 0;
+        }
+        r
+    }
+}
@@ -1,3 +1,7 @@
+use pi_null::Null;
+
+use crate::archetype::{ArchetypeIndex, Row};
+use crate::name::EntityDebug;
 use crate::prelude::World;
 
 #[derive(Debug, Clone)]
@@ -15,6 +19,14 @@ pub struct ColumnDebug {
 }
 
 impl World{
+    /// 原型中的实体，有名称时一并显示
+    pub fn archetype_entities_debug(&self, index: ArchetypeIndex) -> Vec<EntityDebug> {
+        let ar = match self.get_archetype(index) {
+            Some(ar) => ar,
+            None => return Vec::new(),
+        };
+        (0..ar.len().index()).map(|row| ar.get_unchecked(Row(row as u32))).filter(|e| !e.is_null()).map(|e| self.entity_debug(e)).collect()
+    }
     pub fn assert_archetype_arr(&self, archetypes_info: &[Option<ArchetypeDebug>]){
         if !archetypes_info.is_empty(){
             assert_eq!(archetypes_info.len(), self.archetype_arr.len(), "{:?}", self.archetype_arr);
@@ -25,7 +37,7 @@ impl World{
                 let real = &self.archetype_arr[i];
 
                 if let Some(entitys) = expect.entitys{
-                    assert_eq!(entitys, real.len().0 as usize, ":{:?}", real);
+                    assert_eq!(entitys, real.len().0 as usize, ":{:?}, entities: {:?}", real, self.archetype_entities_debug(real.index()));
                 }
     
                 if !expect.columns_info.is_empty(){
@@ -5,6 +5,7 @@ use std::fmt::{self, Display, Write};
 use bitflags::bitflags;
 
 use crate::exec_graph::{Direction, EdgeIndex, ExecGraph, NodeIndex, NodeType};
+use crate::world::World;
 
 /// `Dot` implements output to graphviz .dot format for a graph.
 ///
@@ -87,6 +88,39 @@ impl<'a> Dot<'a> {
     }
 }
 
+// 原型节点上最多显示的实体名称数量
+const MAX_ENTITY_NAMES: usize = 32;
+
+/// 原型节点的属性，用`tooltip`列出原型中有名称的实体，配合`Dot::with_attr_getters`使用
+pub fn archetype_entity_names<'w>(world: &'w World) -> impl Fn(&ExecGraph, NodeIndex) -> String + 'w {
+    move |g, index| {
+        let id = match g.node_label(index) {
+            NodeType::ArchetypeComponent(id, _) => *id as u64,
+            _ => return String::new(),
+        };
+        let ar_index = match world.archetype_map.get(&id) {
+            Some(ar) => ar.index(),
+            None => return String::new(),
+        };
+        let names = world
+            .archetype_entities_debug(ar_index)
+            .into_iter()
+            .filter(|e| e.name.is_some())
+            .map(|e| format!("{:?}", e))
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return String::new();
+        }
+        let mut s = String::from("tooltip=\"");
+        let _ = write!(Escaper(&mut s), "{}", names[..names.len().min(MAX_ENTITY_NAMES)].join(", "));
+        if names.len() > MAX_ENTITY_NAMES {
+            s.push_str(", ...");
+        }
+        s.push('"');
+        s
+    }
+}
+
 // `Dot` configuration.
 bitflags! {
     pub struct Config: u32 {
@@ -4,9 +4,10 @@ use std::{
 
 use pi_map::{hashmap::HashMap, Map};
 use pi_null::Null;
+use pi_share::Share;
 
 use crate::{
-    alter::{AState, ArchetypeMapping, QueryAlterState}, archetype::{ArchetypeIndex, ArchetypeInfo, Row}, fetch::FetchComponents, filter::FilterComponents, insert::{Bundle, InsertState}, prelude::{Entity, Mut, QueryError, Tick, World}, query::{LocalIndex, QueryState}, system::SystemMeta, system_params::SystemParam, world::ComponentIndex, world_ptr::Ptr
+    alter::{AState, ArchetypeMapping, QueryAlterState}, archetype::{ArchetypeIndex, ArchetypeInfo, Row}, column::Column, fetch::FetchComponents, filter::FilterComponents, insert::{Bundle, InsertState}, prelude::{Entity, Mut, QueryError, Tick, World}, query::{LocalIndex, QueryState}, system::SystemMeta, system_params::SystemParam, world::ComponentIndex, world_ptr::Ptr
 };
 
 impl AState {
@@ -50,7 +51,6 @@ impl<'w> EntityEditor<'w> {
         e: Entity,
         components: &[ComponentIndex],
     ) -> Result<(), QueryError> {
-        println!("add_components_by_index: {:?}", e);
         self.state().tmp.clear();
         for item in components.iter().rev() {
             self.state().tmp.push((*item, true));
@@ -64,7 +64,6 @@ impl<'w> EntityEditor<'w> {
         e: Entity,
         components: &[ComponentIndex],
     ) -> Result<(), QueryError> {
-        println!("remove_components_by_index: {:?}", e);
         self.state().tmp.clear();
         for item in components.iter().rev() {
             self.state().tmp.push((*item, false));
@@ -87,19 +86,36 @@ impl<'w> EntityEditor<'w> {
     }
 
     fn alter_components_impl(&mut self, e: Entity) -> Result<(), QueryError> {
+        let addr = match self.world.entities.get(e) {
+            Some(v) => *v,
+            None => return Err(QueryError::NoSuchEntity(e)),
+        };
+        let mut tmp = std::mem::take(&mut self.state().tmp);
+        Self::add_required(self.world, &mut tmp);
+        tmp.sort_by(|a, b| a.cmp(b)); // 只比较ComponentIndex，并且保持原始顺序的排序
+        // 稀疏存储的组件不在原型上，直接在稀疏集合中增删
+        let tick = self.world.tick();
+        let mut pre_index = ComponentIndex::null();
+        tmp.retain(|(index, add)| {
+            let c = self.world.get_column(*index).unwrap().clone();
+            if !c.info().is_sparse() {
+                return true;
+            }
+            // 去重
+            if pre_index != *index {
+                pre_index = *index;
+                Self::alter_sparse(self.world, &c, e, *add, tick);
+            }
+            false
+        });
+        self.state().tmp = tmp;
         let ptr: *const EditorState = &self.world.entity_editor_state;
         let editor_state = unsafe { &mut *(ptr as *mut EditorState) };
-        editor_state.tmp.sort_by(|a, b| a.cmp(b)); // 只比较ComponentIndex，并且保持原始顺序的排序
 
         let mut hasher = DefaultHasher::new();
         editor_state.tmp.hash(&mut hasher);
         let hash = hasher.finish();
 
-        let addr = match self.world.entities.get(e) {
-            Some(v) => *v,
-            None => return Err(QueryError::NoSuchEntity(e)),
-        };
-
         let ar_index = addr.archetype_index();
         let ar = unsafe { self.world.archetype_arr.get_unchecked(ar_index.index()) };
 
@@ -146,13 +162,55 @@ impl<'w> EntityEditor<'w> {
         Ok(())
     }
 
+    // 增删实体上稀疏存储的组件，实体已有要添加的组件时保留原有的值
+    fn alter_sparse(world: &mut World, c: &Share<Column>, e: Entity, add: bool, tick: Tick) {
+        let r = c.sparse_blob_ref();
+        if !add {
+            r.sparse_remove(e);
+            return;
+        }
+        if !r.sparse_row(e).is_null() {
+            return;
+        }
+        let (row, _) = r.sparse_alloc(e);
+        let dst_data: *mut u8 = r.load(row, e);
+        match c.info().set_fn {
+            Some(fun) => fun(world, dst_data),
+            None => {
+                log::error!("{:?} is not set_fn!!!", c);
+                panic!("{:?} is not set_fn!!!", c)
+            },
+        };
+        r.added_tick(e, row, tick)
+    }
+
+    // 将添加的组件所必需的组件加入列表，列表中已有的(包括要删除的)不再加入
+    fn add_required(world: &mut World, tmp: &mut Vec<(ComponentIndex, bool)>) {
+        let mut i = 0;
+        while i < tmp.len() {
+            let (index, add) = tmp[i];
+            i += 1;
+            if !add {
+                continue;
+            }
+            let required = world.get_column(index).unwrap().info().required;
+            for r in required {
+                let index = world.add_component_info((r.info)()).0;
+                if tmp.iter().all(|(c, _)| *c != index) {
+                    tmp.push((index, true));
+                }
+            }
+        }
+    }
+
     /// 根据组件id列表一次插入多个相应组件
     // todo 参数components改为sort_components或&mut自己排序
     pub fn insert_entity_by_index(&mut self, components: &[ComponentIndex]) -> Result<Entity, QueryError> {
-        let components = components
+        // 稀疏存储的组件不在原型上
+        let (sparse, components): (Vec<Share<Column>>, Vec<Share<Column>>) = components
             .iter()
             .map(|index| self.world.get_column(*index).unwrap().clone())
-            .collect();
+            .partition(|c| c.info().is_sparse());
         let info = ArchetypeInfo::sort(components);
         // todo 将Archetype的id改为[ComponentIndex]的hash值，这样尝试获取原型
         let ar = self.world.find_archtype(info);
@@ -162,6 +220,9 @@ impl<'w> EntityEditor<'w> {
         // println!("mapping: {}")
         ar.init_row(self.world, row.into(), e, tick);
         *r = e;
+        for c in sparse.iter() {
+            Self::alter_sparse(self.world, c, e, true, tick);
+        }
         Ok(e)
     }
     // todo editer 应该支持Insert的Bundle
@@ -174,6 +235,7 @@ impl<'w> EntityEditor<'w> {
         };
         if addr.row.is_null() {
             self.world.entities.remove(e).unwrap();
+            self.world.remove_sparse(e);
             return Ok(());
         }
         let ar_index = addr.archetype_index();
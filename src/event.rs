@@ -1,7 +1,10 @@
 //! 事件，及组件移除
 //!
+//! 事件类型需要实现`EventData`，一般用`#[derive(Event)]`，
+//! 默认所有监听器读取后清理，`#[event(retain = N)]`表示最多保留N次整理，到期后未读取的事件也会被丢弃
 use std::any::{Any, TypeId};
 use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::marker::PhantomData;
 use std::mem::{size_of, transmute};
 use std::ops::Deref;
@@ -17,13 +20,32 @@ use crate::system::{SystemMeta, TypeInfo};
 use crate::system_params::SystemParam;
 use crate::world::*;
 
+pub use pi_world_macros::Event;
+
 pub type ComponentEventVec = EventVec<Entity>;
 
+/// 事件的保留策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventRetain {
+    /// 所有监听器都读取后清理
+    #[default]
+    Read,
+    /// 最多保留n次整理，到期后未读取的事件也会被丢弃
+    Settles(usize),
+}
+
+/// 事件，`Event`和`EventSender`要求事件实现该trait，一般用`#[derive(Event)]`实现
+pub trait EventData: 'static {
+    const RETAIN: EventRetain = EventRetain::Read;
+}
+
 #[derive(Debug, Default)]
 pub struct EventVec<E> {
     name: Cow<'static, str>,
     listeners: Vec<ShareUsize>, // 每个监听器的已读取的长度
     vec: SafeVec<E>,            // 记录的事件
+    retain: EventRetain,        // 保留策略
+    marks: VecDeque<usize>,     // 最近几次整理时的事件列表长度
 }
 unsafe impl<E> Send for EventVec<E> {}
 unsafe impl<E> Sync for EventVec<E> {}
@@ -34,8 +56,18 @@ impl<E> EventVec<E> {
             name: name.into(),
             listeners: Vec::new(),
             vec: SafeVec::default(),
+            retain: EventRetain::Read,
+            marks: VecDeque::new(),
         }
     }
+    /// 设置保留策略
+    pub fn with_retain(mut self, retain: EventRetain) -> Self {
+        self.retain = retain;
+        self
+    }
+    pub fn retain(&self) -> EventRetain {
+        self.retain
+    }
     pub fn capacity(&self) -> usize {
         self.listeners.capacity() * 8 + self.vec.capacity() * size_of::<E>()
     }
@@ -59,6 +91,17 @@ impl<E> EventVec<E> {
         self.vec.len() - read_len.load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// 检查监听器的读取位置，返回第一个超出事件列表长度的监听器(index, 读取位置, 事件列表长度)
+    pub(crate) fn check_listeners(&self) -> Option<(usize, usize, usize)> {
+        let len = self.vec.len();
+        for (i, read_len) in self.listeners.iter().enumerate() {
+            let read = read_len.load(Ordering::Relaxed);
+            if read > len {
+                return Some((i, read, len));
+            }
+        }
+        None
+    }
     /// 标记为已读
     pub(crate) fn mark_read(&self, listener_index: usize) {
         let len = self.vec.len();
@@ -97,6 +140,7 @@ impl<E> EventVec<E> {
         for read_len in self.listeners.iter_mut() {
             *read_len.get_mut() = 0;
         }
+        self.marks.clear();
     }
     /// 清理部分已读的事件列表
     pub(crate) fn clear_part(&mut self, index: usize) {
@@ -107,9 +151,55 @@ impl<E> EventVec<E> {
         for read_len in self.listeners.iter_mut() {
             *read_len.get_mut() -= index;
         }
+        for mark in self.marks.iter_mut() {
+            *mark = mark.saturating_sub(index);
+        }
+        while self.marks.front() == Some(&0) {
+            self.marks.pop_front();
+        }
+    }
+    /// 丢弃超过保留次数的事件，未读取的也会被丢弃
+    fn expire(&mut self, settles: usize) {
+        let settles = settles.max(1);
+        let len = self.vec.len();
+        if len == 0 {
+            self.marks.clear();
+            return;
+        }
+        self.marks.push_back(len);
+        if self.marks.len() < settles {
+            return;
+        }
+        let index = self.marks[self.marks.len() - settles];
+        if index >= len {
+            self.vec.clear(0);
+        } else {
+            self.vec.remain_settle(index..usize::MAX, 0);
+        }
+        for read_len in self.listeners.iter_mut() {
+            let read_len = read_len.get_mut();
+            *read_len = read_len.saturating_sub(index);
+        }
+        while let Some(mark) = self.marks.front() {
+            if *mark > index {
+                break;
+            }
+            self.marks.pop_front();
+        }
+        for mark in self.marks.iter_mut() {
+            *mark -= index;
+        }
     }
     // 整理方法， 返回是否已经将事件列表清空，只有所有的监听器都读取了全部的事件列表，才可以清空事件列表
     pub(crate) fn settle(&mut self) -> bool {
+        let r = self.settle_read();
+        if let EventRetain::Settles(n) = self.retain {
+            self.expire(n);
+            return self.vec.len() == 0;
+        }
+        r
+    }
+    fn settle_read(&mut self) -> bool {
         match self.can_clear() {
             Ok(len) => {
                 if len > 0 {
@@ -132,6 +222,12 @@ impl<E: 'static> Settle for EventVec<E> {
     fn settle(&mut self) {
         self.settle();
     }
+    fn mem_info(&self) -> (&str, usize) {
+        (&self.name, self.capacity())
+    }
+    fn check_listeners(&self) -> Option<(usize, usize, usize)> {
+        EventVec::check_listeners(self)
+    }
 }
 impl<E: 'static> Downcast for EventVec<E> {
     fn into_any(self: Share<Self>) -> Share<dyn Any + Send + Sync> {
@@ -175,7 +271,7 @@ impl<'w, E: 'static> Event<'w, E> {
     }
 }
 
-impl<E: 'static> SystemParam for Event<'_, E> {
+impl<E: EventData> SystemParam for Event<'_, E> {
     type State = (Share<EventVec<E>>, usize);
     type Item<'w> = Event<'w, E>;
 
@@ -213,7 +309,7 @@ impl<'w, E: 'static> EventSender<'w, E> {
     }
 }
 
-impl<E: 'static> SystemParam for EventSender<'_, E> {
+impl<E: EventData> SystemParam for EventSender<'_, E> {
     type State = Share<EventVec<E>>;
     type Item<'w> = EventSender<'w, E>;
 
@@ -375,7 +471,7 @@ impl<'w, T: 'static> ComponentEvent<'w, T> {
 }
 
 #[inline]
-fn init_state<E: 'static>(world: &mut World) -> Share<EventVec<E>> {
+fn init_state<E: EventData>(world: &mut World) -> Share<EventVec<E>> {
     let info = TypeInfo::of::<Event<E>>();
     // let r = world.get_event_record(&info.type_id);
     // if let Some(er) = r {
@@ -388,7 +484,7 @@ fn init_state<E: 'static>(world: &mut World) -> Share<EventVec<E>> {
     match _init_state(world, &info) {
         Ok(r) => Share::downcast::<EventVec<E>>(r).unwrap(),
         Err(r) => {
-            let r = Share::new(EventVec::<E>::new(r));
+            let r = Share::new(EventVec::<E>::new(r).with_retain(E::RETAIN));
             world.init_event_record(info.type_id, r.clone());
             r
         },
@@ -404,7 +500,7 @@ fn _init_state(world: &mut World, info: &TypeInfo) -> Result<Share<dyn Any + Sen
     }
 }
 
-fn init_changed_state(world: &mut World, typeid: TypeId, info: ComponentInfo) -> (Share<ComponentEventVec>, usize) {
+pub(crate) fn init_changed_state(world: &mut World, typeid: TypeId, info: ComponentInfo) -> (Share<ComponentEventVec>, usize) {
     let (r, c) = init_component_state(world, info, |info| match &info.changed {
         Some(r) => r.clone(),
         None => {
@@ -422,7 +518,7 @@ fn init_changed_state(world: &mut World, typeid: TypeId, info: ComponentInfo) ->
     }
     r
 }
-fn init_added_state(world: &mut World, typeid: TypeId, info: ComponentInfo) -> (Share<ComponentEventVec>, usize) {
+pub(crate) fn init_added_state(world: &mut World, typeid: TypeId, info: ComponentInfo) -> (Share<ComponentEventVec>, usize) {
     let r = init_component_state(world, info, |info| match &info.added {
         Some(r) => r.clone(),
         None => {
@@ -436,7 +532,7 @@ fn init_added_state(world: &mut World, typeid: TypeId, info: ComponentInfo) -> (
     r
 }
 
-fn init_removed_state(world: &mut World, typeid: TypeId, info: ComponentInfo) -> (Share<ComponentEventVec>, usize) {
+pub(crate) fn init_removed_state(world: &mut World, typeid: TypeId, info: ComponentInfo) -> (Share<ComponentEventVec>, usize) {
     let r = init_component_state(world, info, |info| match &info.removed {
         Some(r) => r.clone(),
         None => {
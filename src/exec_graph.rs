@@ -38,9 +38,9 @@ use crate::archetype::{Archetype, ArchetypeDependResult, Flags};
 use crate::column::ARCHETYPE_INDEX;
 #[cfg(debug_assertions)]
 use crate::column::COMPONENT_INDEX;
-use crate::dot::{Config, Dot};
+use crate::dot::{archetype_entity_names, Config, Dot};
 use crate::listener::Listener;
-use crate::system::BoxedSystem;
+use crate::system::{BoxedSystem, SystemMeta};
 use crate::world::{ArchetypeInit, ComponentIndex, World};
 
 const NODE_STATUS_STEP: u32 = 0x1000_0000;
@@ -133,6 +133,16 @@ pub struct ExecSystem {
     pub(crate) set_conditions: FixedBitSet,
 }
 
+impl ExecSystem {
+    /// system及其运行条件的元数据
+    pub(crate) fn metas(&self) -> impl Iterator<Item = &SystemMeta> {
+        self.system
+            .system_meta()
+            .into_iter()
+            .chain(self.conditions.iter().filter_map(|c| c.system_meta()))
+    }
+}
+
 #[derive(Clone)]
 pub struct ExecGraph(Share<GraphInner>, pub String, pub Vec<usize>/*toop排序*/);
 
@@ -141,7 +151,8 @@ impl ExecGraph {
         Self(Default::default(), name, Vec::with_capacity(256))
     }
 
-    pub fn check(&self) -> Vec<usize> {
+    /// 拓扑排序，图中有环时，返回环的路径
+    pub fn check(&self) -> std::result::Result<Vec<usize>, CycleError> {
         let graph = self.0.as_ref();
         let mut ngraph = NGraph::default();
         for n in graph.nodes.iter().enumerate() {
@@ -156,12 +167,18 @@ impl ExecGraph {
 
         let cycle_keys = ngraph.build();
         match cycle_keys {
-            Ok(r) => r,
+            Ok(r) => Ok(r),
             Err(cycle_keys) => if cycle_keys.len() > 0 {
-                let cycle: Vec<(usize, &Node)> = cycle_keys.iter().map(|k| {(k.clone(), graph.nodes.get(*k).unwrap())}).collect();
-                panic!("cycle=========={:?}", cycle);
+                let path: Vec<Cow<'static, str>> = cycle_keys.iter().map(|k| graph.nodes.get(*k).unwrap().label().type_name().clone()).collect();
+                // 环上的边，最后一个节点连回第一个节点，带上添加边时记录的来源
+                let edges = (0..cycle_keys.len()).map(|i| {
+                    let j = (i + 1) % cycle_keys.len();
+                    let origin = graph.origins.get(&(NodeIndex::new(cycle_keys[i]), NodeIndex::new(cycle_keys[j]))).map(|r| r.value().clone());
+                    CycleEdge { before: path[i].clone(), after: path[j].clone(), origin }
+                }).collect();
+                Err(CycleError { graph: self.1.clone(), path, edges })
             } else {
-                Vec::default()
+                Ok(Vec::default())
             },
         }
     }
@@ -183,10 +200,20 @@ impl ExecGraph {
         let index = inner.nodes.insert(Node::new(NodeType::Set(start, end, set_name)));
         NodeIndex(index as u32)
     }
-    pub fn add_edge(&self, from: NodeIndex, to: NodeIndex) {
+    /// 添加边，并记录边的来源（第一次声明该边的system或集）
+    pub fn add_edge(&self, from: NodeIndex, to: NodeIndex, origin: EdgeOrigin) {
         let inner = self.0.as_ref();
+        inner.origins.entry((from, to)).or_insert(origin);
         inner.add_edge(from, to);
     }
+    /// 节点的名字
+    pub fn node_name(&self, index: NodeIndex) -> Cow<'static, str> {
+        self.node_label(index).type_name().clone()
+    }
+    /// 节点的类型
+    pub fn node_label(&self, index: NodeIndex) -> &NodeType {
+        self.0.as_ref().nodes.get(index.index()).unwrap().label()
+    }
     pub fn node_references<'a>(&'a self) -> Iter<'a, Node> {
         self.0.as_ref().nodes.iter()
     }
@@ -207,7 +234,8 @@ impl ExecGraph {
     }
     /// 初始化方法，每个图可被执行多次， 已经初始化的system和world上的资源和原型不会再次生成图节点
     /// 将system, res, archetype, 添加成图节点，并维护边
-    pub fn initialize(&mut self, systems: Share<SafeVec<ExecSystem>>, set_conditions: Share<SafeVec<BoxedSystem<bool>>>, world: &mut World, init_notify: bool) {
+    /// 图中有环时，返回环的路径
+    pub fn initialize(&mut self, systems: Share<SafeVec<ExecSystem>>, set_conditions: Share<SafeVec<BoxedSystem<bool>>>, world: &mut World, init_notify: bool) -> std::result::Result<(), CycleError> {
         let inner = self.0.as_ref();
         let old_sys_len = inner.sys_len.load(Ordering::Relaxed);
         let new_sys_len = systems.len();
@@ -240,18 +268,16 @@ impl ExecGraph {
         // for r in world.archetype_arr.iter() {
         //     self.add_archetype_node(&systems, range.clone(), r, world);
         // }
-        log::trace!("res & archtypes initialized, {:?}", Dot::with_config(&self, Config::empty()));
-        let _ = std::fs::write("system_graph".to_string() + self.1.as_str() + ".dot", Dot::with_config(&self, Config::empty()).to_string());
+        {
+            // 原型节点上显示有名称的实体
+            let entity_names = archetype_entity_names(world);
+            let dot = Dot::with_attr_getters(&self, Config::empty(), &|_, _| String::new(), &entity_names);
+            log::trace!("res & archtypes initialized, {:?}", dot);
+            let _ = std::fs::write("system_graph".to_string() + self.1.as_str() + ".dot", dot.to_string());
+        }
 
-        let sort = self.check().into_iter().filter(|i| {
-            match &inner.nodes[*i].label {
-                NodeType::System(_, _) => true,
-                NodeType::Set(start, end, _) => end - start > 0,
-                _ => false,
-            } 
-        }).collect::<Vec<usize>>();
-        // toop 排序
-        self.2 = sort;
+        // 有环时，也要完成图的整理和监听器的注册，保持图的状态完整
+        let sort = self.check();
         // nodes和edges整理AppendVec
         let inner = Share::<GraphInner>::get_mut(&mut self.0).unwrap();
         inner.nodes.settle(0);
@@ -275,14 +301,22 @@ impl ExecGraph {
             // 整理world的监听器，合并内存
             world.listener_mgr.settle();
         }
+        let inner = self.0.as_ref();
+        let sort = sort?.into_iter().filter(|i| {
+            match &inner.nodes[*i].label {
+                NodeType::System(_, _) => true,
+                NodeType::Set(start, end, _) => end - start > 0,
+                _ => false,
+            } 
+        }).collect::<Vec<usize>>();
+        // toop 排序
+        self.2 = sort;
         // println!(
         //     "graph initialized, froms: {:?},  to_len:{}",
         //     self.froms(),
         //     self.to_len()
         // );
-        
-        
-
+        Ok(())
     }
     // 添加单例和多例节点，添加单例多例和system的依赖关系产生的边。
     // 只会在初始化时调用一次。
@@ -665,12 +699,145 @@ impl ExecGraph {
         inner.nodes.settle(0);
         inner.edges.settle(0);
     }
+    /// 检查图中没有先后顺序，但读写冲突的system对
+    pub fn check_ambiguity(&self, systems: &SafeVec<ExecSystem>, world: &World) -> Vec<Ambiguity> {
+        let inner = self.0.as_ref();
+        let len = inner.nodes.iter().count();
+        // 计算每个system节点可到达的节点
+        let mut sys_nodes = Vec::new();
+        for (i, node) in inner.nodes.iter().enumerate() {
+            if let NodeType::System(sys_index, _) = node.label() {
+                let mut reach = FixedBitSet::with_capacity(len);
+                let mut stack = vec![NodeIndex::new(i)];
+                while let Some(n) = stack.pop() {
+                    for to in inner.neighbors(n, Direction::To) {
+                        if !reach.put(to.index()) {
+                            stack.push(to);
+                        }
+                    }
+                }
+                sys_nodes.push((i, *sys_index, reach));
+            }
+        }
+        let mut result = Vec::new();
+        for (k, (i, sys_i, reach_i)) in sys_nodes.iter().enumerate() {
+            for (j, sys_j, reach_j) in sys_nodes[k + 1..].iter() {
+                // 有先后顺序
+                if reach_i.contains(*j) || reach_j.contains(*i) {
+                    continue;
+                }
+                let a = unsafe { systems.load_unchecked(*sys_i) };
+                let b = unsafe { systems.load_unchecked(*sys_j) };
+                // system的运行条件和system一起运行，也参与检查
+                let mut conflicts = Vec::new();
+                for ma in a.metas() {
+                    for mb in b.metas() {
+                        for c in ma.conflicts(mb, world) {
+                            if !conflicts.contains(&c) {
+                                conflicts.push(c);
+                            }
+                        }
+                    }
+                }
+                if conflicts.len() > 0 {
+                    result.push(Ambiguity {
+                        graph: self.1.clone(),
+                        systems: [a.system.name().clone(), b.system.name().clone()],
+                        conflicts,
+                    });
+                }
+            }
+        }
+        result
+    }
+}
+
+/// 图中的环
+#[derive(Debug, Clone)]
+pub struct CycleError {
+    /// 所在的图
+    pub graph: String,
+    /// 环上的节点（system或集）的名字
+    pub path: Vec<Cow<'static, str>>,
+    /// 环上的边
+    pub edges: Vec<CycleEdge>,
+}
+impl Display for CycleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}: cycle ", self.graph)?;
+        for edge in self.edges.iter() {
+            match &edge.origin {
+                Some(origin) => write!(f, "[{} -> {} ({:?}, declared by {})]", edge.before, edge.after, origin.kind, origin.declared_by)?,
+                None => write!(f, "[{} -> {}]", edge.before, edge.after)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 环上的边
+#[derive(Debug, Clone)]
+pub struct CycleEdge {
+    /// 先执行的节点的名字
+    pub before: Cow<'static, str>,
+    /// 后执行的节点的名字
+    pub after: Cow<'static, str>,
+    /// 边的来源，system和原型或资源之间的边没有来源
+    pub origin: Option<EdgeOrigin>,
+}
+
+/// 边的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// 通过before声明的顺序
+    Before,
+    /// 通过after声明的顺序
+    After,
+    /// 集的开始节点到成员，成员到集的结束节点
+    InSet,
+    /// 集的开始节点到结束节点
+    SetBounds,
+}
+
+/// 边的来源
+#[derive(Debug, Clone)]
+pub struct EdgeOrigin {
+    /// 边的种类
+    pub kind: EdgeKind,
+    /// 声明该边的system或集的名字
+    pub declared_by: Cow<'static, str>,
+}
+impl EdgeOrigin {
+    pub fn new(kind: EdgeKind, declared_by: Cow<'static, str>) -> Self {
+        Self { kind, declared_by }
+    }
+}
+
+/// 歧义，同一个图中两个system读写冲突，但没有指定先后顺序
+#[derive(Debug, Clone)]
+pub struct Ambiguity {
+    /// 所在的图
+    pub graph: String,
+    /// 冲突的两个system的名字
+    pub systems: [Cow<'static, str>; 2],
+    /// 冲突的组件或资源的名字
+    pub conflicts: Vec<Cow<'static, str>>,
+}
+impl Display for Ambiguity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "{}: {} and {} conflict on {:?}",
+            self.graph, self.systems[0], self.systems[1], self.conflicts
+        )
+    }
 }
 
 pub struct GraphInner {
     nodes: AppendVec<Node>,
     edges: AppendVec<Edge>,
     map: DashMap<(u128, ComponentIndex), NodeIndex>,
+    origins: DashMap<(NodeIndex, NodeIndex), EdgeOrigin>, // 边的来源
     to_len: ShareU32, // 图中的end节点的数量
     to_count: ShareU32, // 多线程运行时数据， 表示还剩多少结束节点未执行（派发开始， to_count设置为to_len， to_count为0时， 表示本次派发完成）
     froms: Vec<NodeIndex>,
@@ -949,6 +1116,7 @@ impl Default for GraphInner {
             nodes: Default::default(),
             edges: Default::default(),
             map: Default::default(),
+            origins: Default::default(),
             to_len: ShareU32::new(0),
             froms: Default::default(),
             lock: ShareMutex::new(()),
@@ -2,14 +2,18 @@ use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 
+use pi_null::Null;
 use pi_proc_macros::all_tuples;
+pub use pi_world_macros::QueryData;
 use pi_share::Share;
 
-use crate::archetype::{ArchetypeIndex, ComponentInfo, Row, COMPONENT_TICK};
+use crate::archetype::{Archetype, ArchetypeIndex, ComponentInfo, Row, COMPONENT_TICK};
 use crate::column::{BlobRef, Column};
+use crate::name::Name;
 use crate::prelude::FromWorld;
 use crate::single_res::TickRes;
-use crate::system::{Relation, SystemMeta};
+use crate::query::QueryError;
+use crate::system::{Related, Relation, SystemMeta};
 use crate::world::{ComponentIndex, Entity, Tick, World};
 
 pub trait FetchComponents {
@@ -28,6 +32,11 @@ pub trait FetchComponents {
     /// initializes ReadWrite for this [`FetchComponents`] type.
     fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State;
 
+    /// 不修改world，用已注册的组件创建状态，读写关系记录到related上，用于从已有的查询派生查询
+    fn get_state(_world: &World, _related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        Err(QueryError::LensUnsupported(Cow::Borrowed(std::any::type_name::<Self>())))
+    }
+
     /// Creates a new instance of this fetch.
     ///
     /// # Safety
@@ -65,6 +74,22 @@ pub trait FetchComponents {
     /// that `fetch` is called no more than once for each `entity`/`table_row` in each archetype.
     /// If `Self` implements [`ReadOnlyFetchComponents`], then this can safely be called multiple times.
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w>;
+
+    /// 是否跳过该行，稀疏存储的组件在实体没有该组件时跳过
+    fn skip<'w>(_fetch: &Self::Fetch<'w>, _row: Row, _e: Entity) -> bool {
+        false
+    }
+}
+
+// 组件所在的行，稀疏存储时由实体获得，实体没有该组件时返回None
+#[inline]
+fn opt_row(c: &BlobRef, row: Row, e: Entity) -> Option<Row> {
+    let row = c.entity_row(row, e);
+    if row.is_null() {
+        None
+    } else {
+        Some(row)
+    }
 }
 
 impl FetchComponents for Entity {
@@ -74,6 +99,9 @@ impl FetchComponents for Entity {
     type State = ();
 
     fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {}
+    fn get_state(_world: &World, _related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        Ok(())
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -116,6 +144,13 @@ impl<T: 'static> FetchComponents for &T {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(0),
+            Relation::Read(0usize.into()),
+        )
+    }
     //#[inline(always)]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -124,6 +159,9 @@ impl<T: 'static> FetchComponents for &T {
         _tick: Tick,
         _last_run: Tick,
     ) -> Self::Fetch<'w>{
+        if state.sparse.is_some() {
+            return state.sparse_blob_ref();
+        }
         state.blob_ref_unchecked(index)
     }
 
@@ -135,12 +173,22 @@ impl<T: 'static> FetchComponents for &T {
         _tick: Tick,
         _last_run: Tick,
     ) -> Option<Self::Fetch<'w>>{
+        if state.sparse.is_some() {
+            return Some(state.sparse_blob_ref());
+        }
         state.blob_ref(index)
     }
 
     //#[inline(always)]
-    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, _e: Entity) -> Self::Item<'w> {
-        fetch.get_unchecked::<T>(row)
+    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
+        match fetch.sparse {
+            Some(s) => fetch.get_unchecked::<T>(s.row(e)),
+            None => fetch.get_unchecked::<T>(row),
+        }
+    }
+
+    fn skip<'w>(fetch: &Self::Fetch<'w>, _row: Row, e: Entity) -> bool {
+        fetch.sparse.map_or(false, |s| s.row(e).is_null())
     }
 }
 
@@ -158,6 +206,13 @@ impl<T: 'static> FetchComponents for &mut T {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(0),
+            Relation::Write(0usize.into()),
+        )
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -166,6 +221,9 @@ impl<T: 'static> FetchComponents for &mut T {
         tick: Tick,
         last_run: Tick,
     )  -> Self::Fetch<'w>{
+        if state.sparse.is_some() {
+            return ColumnTick::new(state.sparse_blob_ref(), tick, last_run);
+        }
         ColumnTick::new(state.blob_ref_unchecked(index), tick, last_run)
     }
 
@@ -177,6 +235,9 @@ impl<T: 'static> FetchComponents for &mut T {
         tick: Tick,
         last_run: Tick,
     )  -> Option<Self::Fetch<'w>>{
+        if state.sparse.is_some() {
+            return Some(ColumnTick::new(state.sparse_blob_ref(), tick, last_run));
+        }
         state.blob_ref(index).map(|r| {
             ColumnTick::new(r, tick, last_run)
         }) 
@@ -184,7 +245,14 @@ impl<T: 'static> FetchComponents for &mut T {
 
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        Mut::new(fetch, e, row)
+        match fetch.column.sparse {
+            Some(s) => Mut::new(fetch, e, s.row(e)),
+            None => Mut::new(fetch, e, row),
+        }
+    }
+
+    fn skip<'w>(fetch: &Self::Fetch<'w>, _row: Row, e: Entity) -> bool {
+        fetch.column.sparse.map_or(false, |s| s.row(e).is_null())
     }
 }
 
@@ -196,12 +264,22 @@ impl<T: 'static> FetchComponents for Ref<T> {
     type State = Share<Column>;
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
-        meta.component_relate(
+        let column = meta
+            .component_relate(
+                world,
+                ComponentInfo::of::<T>(COMPONENT_TICK),
+                Relation::Read(0usize.into()),
+            )
+            .1;
+        column
+    }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        let column = related.component_relate_ref(
             world,
             ComponentInfo::of::<T>(COMPONENT_TICK),
             Relation::Read(0usize.into()),
-        )
-        .1
+        )?;
+        Ok(column)
     }
     //#[inline]
     fn init_fetch<'w>(
@@ -211,6 +289,9 @@ impl<T: 'static> FetchComponents for Ref<T> {
         tick: Tick,
         last_run: Tick,
     )  -> Self::Fetch<'w>{
+        if state.sparse.is_some() {
+            return ColumnTick::new(state.sparse_blob_ref(), tick, last_run);
+        }
         ColumnTick::new(state.blob_ref_unchecked(index), tick, last_run)
     }
 
@@ -222,14 +303,18 @@ impl<T: 'static> FetchComponents for Ref<T> {
         tick: Tick,
         last_run: Tick,
     )  -> Option<Self::Fetch<'w>>{
-        state.blob_ref(index).map(|r| {
+        state.fetch_blob_ref(index).map(|r| {
             ColumnTick::new(r, tick, last_run)
         }) 
     }
 
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        TickRef::new(fetch, row, e)
+        TickRef::new(fetch, fetch.column.entity_row(row, e), e)
+    }
+
+    fn skip<'w>(fetch: &Self::Fetch<'w>, _row: Row, e: Entity) -> bool {
+        fetch.column.sparse.map_or(false, |s| s.row(e).is_null())
     }
 }
 
@@ -247,6 +332,13 @@ impl<T: 'static> FetchComponents for Option<Ref<T>> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(COMPONENT_TICK),
+            Relation::OptRead(0usize.into()),
+        )
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -255,7 +347,7 @@ impl<T: 'static> FetchComponents for Option<Ref<T>> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
-        if let Some(column) = state.blob_ref(index) {
+        if let Some(column) = state.fetch_blob_ref(index) {
             Some(ColumnTick::new(column, tick, last_run))
         } else {
             None
@@ -275,10 +367,8 @@ impl<T: 'static> FetchComponents for Option<Ref<T>> {
 
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        match fetch {
-            Some(c) => Some(TickRef::new(c, row, e)),
-            None => None,
-        }
+        let c = fetch.as_ref()?;
+        opt_row(&c.column, row, e).map(|row| TickRef::new(c, row, e))
     }
 }
 
@@ -289,12 +379,22 @@ impl<T: 'static> FetchComponents for Ticker<'_, &'_ T> {
     type State = Share<Column>;
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
-        meta.component_relate(
+        let column = meta
+            .component_relate(
+                world,
+                ComponentInfo::of::<T>(COMPONENT_TICK),
+                Relation::Read(0usize.into()),
+            )
+            .1;
+        column
+    }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        let column = related.component_relate_ref(
             world,
             ComponentInfo::of::<T>(COMPONENT_TICK),
             Relation::Read(0usize.into()),
-        )
-        .1
+        )?;
+        Ok(column)
     }
     //#[inline]
     fn init_fetch<'w>(
@@ -304,6 +404,9 @@ impl<T: 'static> FetchComponents for Ticker<'_, &'_ T> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
+        if state.sparse.is_some() {
+            return ColumnTick::new(state.sparse_blob_ref(), tick, last_run);
+        }
         ColumnTick::new(state.blob_ref_unchecked(index), tick, last_run)
     }
     //#[inline]
@@ -314,13 +417,17 @@ impl<T: 'static> FetchComponents for Ticker<'_, &'_ T> {
         tick: Tick,
         last_run: Tick,
     )  -> Option<Self::Fetch<'w>>{
-        state.blob_ref(index).map(|r| {
+        state.fetch_blob_ref(index).map(|r| {
             ColumnTick::new(r, tick, last_run)
         }) 
     }
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        Ticker::new(fetch, e, row)
+        Ticker::new(fetch, e, fetch.column.entity_row(row, e))
+    }
+
+    fn skip<'w>(fetch: &Self::Fetch<'w>, _row: Row, e: Entity) -> bool {
+        fetch.column.sparse.map_or(false, |s| s.row(e).is_null())
     }
 }
 
@@ -331,12 +438,22 @@ impl<T: 'static> FetchComponents for Ticker<'_, &'_ mut T> {
     type State = Share<Column>;
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
-        meta.component_relate(
+        let column = meta
+            .component_relate(
+                world,
+                ComponentInfo::of::<T>(COMPONENT_TICK),
+                Relation::Write(0usize.into()),
+            )
+            .1;
+        column
+    }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        let column = related.component_relate_ref(
             world,
             ComponentInfo::of::<T>(COMPONENT_TICK),
             Relation::Write(0usize.into()),
-        )
-        .1
+        )?;
+        Ok(column)
     }
     //#[inline]
     fn init_fetch<'w>(
@@ -346,6 +463,9 @@ impl<T: 'static> FetchComponents for Ticker<'_, &'_ mut T> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
+        if state.sparse.is_some() {
+            return ColumnTick::new(state.sparse_blob_ref(), tick, last_run);
+        }
         ColumnTick::new(state.blob_ref_unchecked(index), tick, last_run)
     }
     //#[inline]
@@ -356,13 +476,17 @@ impl<T: 'static> FetchComponents for Ticker<'_, &'_ mut T> {
         tick: Tick,
         last_run: Tick,
     )  -> Option<Self::Fetch<'w>>{
-        state.blob_ref(index).map(|r| {
+        state.fetch_blob_ref(index).map(|r| {
             ColumnTick::new(r, tick, last_run)
         }) 
     }
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        Ticker::new(fetch, e, row)
+        Ticker::new(fetch, e, fetch.column.entity_row(row, e))
+    }
+
+    fn skip<'w>(fetch: &Self::Fetch<'w>, _row: Row, e: Entity) -> bool {
+        fetch.column.sparse.map_or(false, |s| s.row(e).is_null())
     }
 }
 
@@ -380,6 +504,13 @@ impl<T: 'static> FetchComponents for Option<Ticker<'_, &'_ T>> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(COMPONENT_TICK),
+            Relation::OptRead(0usize.into()),
+        )
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -388,7 +519,7 @@ impl<T: 'static> FetchComponents for Option<Ticker<'_, &'_ T>> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
-        if let Some(column) = state.blob_ref(index) {
+        if let Some(column) = state.fetch_blob_ref(index) {
             Some(ColumnTick::new(column, tick, last_run))
         } else {
             None
@@ -407,10 +538,8 @@ impl<T: 'static> FetchComponents for Option<Ticker<'_, &'_ T>> {
     }
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        match fetch {
-            Some(f) => Some(Ticker::new(f, e, row)),
-            None => None,
-        }
+        let f = fetch.as_ref()?;
+        opt_row(&f.column, row, e).map(|row| Ticker::new(f, e, row))
     }
 }
 
@@ -428,6 +557,13 @@ impl<T: 'static> FetchComponents for Option<Ticker<'_, &'_ mut T>> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(COMPONENT_TICK),
+            Relation::OptWrite(0usize.into()),
+        )
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -436,7 +572,7 @@ impl<T: 'static> FetchComponents for Option<Ticker<'_, &'_ mut T>> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
-        if let Some(column) = state.blob_ref(index) {
+        if let Some(column) = state.fetch_blob_ref(index) {
             Some(ColumnTick::new(column, tick, last_run))
         } else {
             None
@@ -454,10 +590,8 @@ impl<T: 'static> FetchComponents for Option<Ticker<'_, &'_ mut T>> {
     }
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        match fetch {
-            Some(f) => Some(Ticker::new(f, e, row)),
-            None => None,
-        }
+        let f = fetch.as_ref()?;
+        opt_row(&f.column, row, e).map(|row| Ticker::new(f, e, row))
     }
 }
 
@@ -475,6 +609,13 @@ impl<T: 'static> FetchComponents for Option<&T> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(0),
+            Relation::OptRead(0usize.into()),
+        )
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -483,7 +624,7 @@ impl<T: 'static> FetchComponents for Option<&T> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
-        if let Some(column) = state.blob_ref(index) {
+        if let Some(column) = state.fetch_blob_ref(index) {
             Some(ColumnTick::new(column, tick, last_run))
         } else {
             None
@@ -500,11 +641,9 @@ impl<T: 'static> FetchComponents for Option<&T> {
         Some(Self::init_fetch(world, state, index, tick, last_run))
     }
     //#[inline(always)]
-    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, _e: Entity) -> Self::Item<'w> {
-        match fetch {
-            Some(c) => Some(c.column.get_unchecked::<T>(row)),
-            None => None,
-        }
+    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
+        let c = fetch.as_ref()?;
+        opt_row(&c.column, row, e).map(|row| c.column.get_unchecked::<T>(row))
     }
 }
 
@@ -522,6 +661,13 @@ impl<T: 'static> FetchComponents for Option<&mut T> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(0),
+            Relation::OptWrite(0usize.into()),
+        )
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -530,7 +676,7 @@ impl<T: 'static> FetchComponents for Option<&mut T> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
-        if let Some(column) = state.blob_ref(index) {
+        if let Some(column) = state.fetch_blob_ref(index) {
             Some(ColumnTick::new(column, tick, last_run))
         } else {
             None
@@ -548,10 +694,8 @@ impl<T: 'static> FetchComponents for Option<&mut T> {
     }
     //#[inline(always)]
     fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
-        match fetch {
-            Some(f) => Some(Mut::new(f, e, row)),
-            None => None,
-        }
+        let f = fetch.as_ref()?;
+        opt_row(&f.column, row, e).map(|row| Mut::new(f, e, row))
     }
 }
 
@@ -568,7 +712,7 @@ fn _init_state_ordefault(world: &mut World, meta: &mut SystemMeta, info: Compone
 /// DefaultValue<T>默认为DefaultValue::from_world的返回值，也可被应用程序覆盖
 pub struct OrDefault<T: 'static + FromWorld>(PhantomData<T>);
 impl<T: 'static + FromWorld> FetchComponents for OrDefault<T> {
-    type Fetch<'w> = Result<BlobRef<'w>, &'w T>;
+    type Fetch<'w> = (Option<BlobRef<'w>>, &'w T);
     type Item<'w> = &'w T;
     type ReadOnly = Self;
     type State = (Share<Column>, Share<TickRes<T>>);
@@ -591,11 +735,7 @@ impl<T: 'static + FromWorld> FetchComponents for OrDefault<T> {
         _tick: Tick,
         _last_run: Tick,
     ) -> Self::Fetch<'w> {
-        if let Some(column) = state.0.blob_ref(index) {
-            Ok(column)
-        } else {
-            Err(&state.1)
-        }
+        (state.0.fetch_blob_ref(index), &state.1)
     }
     //#[inline]
     fn init_fetch_opt<'w>(
@@ -608,10 +748,10 @@ impl<T: 'static + FromWorld> FetchComponents for OrDefault<T> {
         Some(Self::init_fetch(world, state, index, tick, last_run))
     } 
     //#[inline(always)]
-    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, _e: Entity) -> Self::Item<'w> {
-        match fetch {
-            Ok(c) => c.get_unchecked::<T>(row),
-            Err(r) => *r,
+    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
+        match fetch.0.as_ref().and_then(|c| opt_row(c, row, e).map(|row| (c, row))) {
+            Some((c, row)) => c.get_unchecked::<T>(row),
+            None => fetch.1,
         }
     }
 }
@@ -620,7 +760,7 @@ impl<T: 'static + FromWorld> FetchComponents for OrDefault<T> {
 /// DefaultValue<T>默认为DefaultValue::from_world的返回值，也可被应用程序覆盖
 pub struct OrDefaultRef<T: 'static + FromWorld>(PhantomData<T>);
 impl<T: 'static + FromWorld> FetchComponents for OrDefaultRef<T> {
-    type Fetch<'w> = Result<ColumnTick<'w>, (&'w T, Tick)>;
+    type Fetch<'w> = (Option<ColumnTick<'w>>, &'w T, Tick);
     type Item<'w> = ValueRef<'w, T>;
     type ReadOnly = Self;
     type State = (Share<Column>, Share<TickRes<T>>);
@@ -643,11 +783,11 @@ impl<T: 'static + FromWorld> FetchComponents for OrDefaultRef<T> {
         tick: Tick,
         last_run: Tick,
     ) -> Self::Fetch<'w> {
-        if let Some(column) = state.0.blob_ref(index) {
-            Ok(ColumnTick::new(column, tick, last_run))
-        } else {
-            Err((&state.1, last_run))
-        }
+        (
+            state.0.fetch_blob_ref(index).map(|column| ColumnTick::new(column, tick, last_run)),
+            &state.1,
+            last_run,
+        )
     }
     //#[inline]
     fn init_fetch_opt<'w>(
@@ -660,20 +800,20 @@ impl<T: 'static + FromWorld> FetchComponents for OrDefaultRef<T> {
         Some(Self::init_fetch(world, state, index, tick, last_run))
     }
     //#[inline(always)]
-    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, _e: Entity) -> Self::Item<'w> {
-        match fetch {
-            Ok(c) => {
+    fn fetch<'w>(fetch: &Self::Fetch<'w>, row: Row, e: Entity) -> Self::Item<'w> {
+        match fetch.0.as_ref().and_then(|c| opt_row(&c.column, row, e).map(|row| (c, row))) {
+            Some((c, row)) => {
                 let tick = c.column.get_tick_unchecked(row);
                 ValueRef::new(c.column.get_unchecked::<T>(row), tick, c.last_run)
             }
-            Err(r) => ValueRef::new(r.0, 0usize.into(), r.1),
+            None => ValueRef::new(fetch.1, 0usize.into(), fetch.2),
         }
     }
 }
 
 pub struct Has<T: 'static>(PhantomData<T>);
 impl<T: 'static> FetchComponents for Has<T> {
-    type Fetch<'w> = bool;
+    type Fetch<'w> = Result<bool, BlobRef<'w>>; // 稀疏存储时，逐个实体判断
     type Item<'w> = bool;
     type ReadOnly = Self;
     type State = Share<Column>;
@@ -686,6 +826,13 @@ impl<T: 'static> FetchComponents for Has<T> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(0),
+            Relation::OptRead(0usize.into()),
+        )
+    }
     //#[inline]
     fn init_fetch<'w>(
         _world: &'w World,
@@ -694,7 +841,10 @@ impl<T: 'static> FetchComponents for Has<T> {
         _tick: Tick,
         _last_run: Tick,
     ) -> Self::Fetch<'w> {
-        state.contains(index)
+        if state.sparse.is_some() {
+            return Err(state.sparse_blob_ref());
+        }
+        Ok(state.contains(index))
     }
     //#[inline]
     fn init_fetch_opt<'w>(
@@ -707,8 +857,11 @@ impl<T: 'static> FetchComponents for Has<T> {
         Some(Self::init_fetch(world, state, index, tick, last_run))
     }
     //#[inline(always)]
-    fn fetch<'w>(fetch: &Self::Fetch<'w>, _row: Row, _e: Entity) -> Self::Item<'w> {
-        *fetch
+    fn fetch<'w>(fetch: &Self::Fetch<'w>, _row: Row, e: Entity) -> Self::Item<'w> {
+        match fetch {
+            Ok(r) => *r,
+            Err(c) => !c.sparse_row(e).is_null(),
+        }
     }
 }
 
@@ -751,6 +904,13 @@ impl<T: 'static> FetchComponents for ComponentId<T> {
 
 #[derive(Debug)]
 pub struct ArchetypeName<'a>(pub &'a Cow<'static, str>, pub ArchetypeIndex, pub Row);
+impl ArchetypeName<'_> {
+    /// 该行实体的名称（如果有Name组件）
+    pub fn entity_name<'w>(&self, world: &'w World) -> Option<&'w Name> {
+        let e = world.get_archetype(self.1)?.get_unchecked(self.2);
+        world.entity_name(e)
+    }
+}
 impl FetchComponents for ArchetypeName<'_> {
     type Fetch<'w> = (&'w Cow<'static, str>, ArchetypeIndex);
     type Item<'w> = ArchetypeName<'w>;
@@ -758,6 +918,9 @@ impl FetchComponents for ArchetypeName<'_> {
     type State = ();
 
     fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {}
+    fn get_state(_world: &World, _related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        Ok(())
+    }
 
     fn init_fetch<'w>(
         world: &'w World,
@@ -834,7 +997,7 @@ impl<'a, T> TickRef<'a, T> {
     }
     //#[inline(always)]
     pub fn is_changed(&self) -> bool {
-        self.c.column.get_tick_unchecked(self.row) > self.c.last_run
+        self.c.column.get_tick_unchecked(self.row).is_newer_than(self.c.last_run)
     }
 }
 impl<'a, T: 'static> Deref for TickRef<'a, T> {
@@ -871,7 +1034,7 @@ impl<'a, T> ValueRef<'a, T> {
     }
     //#[inline(always)]
     pub fn is_changed(&self) -> bool {
-        self.tick > self.last_run
+        self.tick.is_newer_than(self.last_run)
     }
 }
 impl<'a, T: 'static> Deref for ValueRef<'a, T> {
@@ -913,7 +1076,7 @@ impl<'a, T> Ticker<'a, T> {
     }
 
     pub fn is_changed(&self) -> bool {
-        self.c.column.get_tick_unchecked(self.row) > self.c.last_run
+        self.c.column.get_tick_unchecked(self.row).is_newer_than(self.c.last_run)
     }
 }
 impl<'a, T: 'static> Deref for Ticker<'a, &'_ T> {
@@ -980,7 +1143,7 @@ impl<'a, T: Sized> Mut<'a, T> {
     }
     //#[inline(always)]
     pub fn is_changed(&self) -> bool {
-        self.c.column.get_tick_unchecked(self.row) > self.c.last_run
+        self.c.column.get_tick_unchecked(self.row).is_newer_than(self.c.last_run)
     }
     pub fn bypass_change_detection(&mut self) -> &mut T {
         self.c.column.get_mut::<T>(self.row, self.e)
@@ -1025,6 +1188,9 @@ macro_rules! impl_tuple_fetch {
             fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {
                 ($($name::init_state(_world, _meta),)*)
             }
+            fn get_state(_world: &World, _related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+                Ok(($($name::get_state(_world, _related)?,)*))
+            }
             #[allow(clippy::unused_unit)]
             //#[inline(always)]
             fn init_fetch<'w>(
@@ -1064,8 +1230,165 @@ macro_rules! impl_tuple_fetch {
                 let ($($name,)*) = _fetch;
                 ($($name::fetch($name, _row, _e),)*)
             }
+
+            //#[inline(always)]
+            fn skip<'w>(_fetch: &Self::Fetch<'w>, _row: Row, _e: Entity) -> bool {
+                let ($($name,)*) = _fetch;
+                $(
+                    if $name::skip($name, _row, _e){return true};
+                )*
+                false
+            }
         }
 
     };
 }
 all_tuples!(impl_tuple_fetch, 0, 15, F, S);
+
+/// 按块获取组件，块内的组件在内存上是连续的，用于Query::iter_chunks
+pub trait ChunkFetch: FetchComponents {
+    type Chunk<'a>;
+    /// 从start开始内存连续的结束行，不超过end
+    fn chunk_end<'w>(fetch: &Self::Fetch<'w>, ar: &'w Archetype, start: Row, end: Row) -> Row;
+    /// 获取start到end的块，必须保证该范围在chunk_end内
+    fn fetch_chunk<'w>(
+        fetch: &Self::Fetch<'w>,
+        ar: &'w Archetype,
+        start: Row,
+        end: Row,
+    ) -> Self::Chunk<'w>;
+}
+
+impl<T: 'static> ChunkFetch for &T {
+    type Chunk<'a> = &'a [T];
+
+    fn chunk_end<'w>(fetch: &Self::Fetch<'w>, ar: &'w Archetype, start: Row, end: Row) -> Row {
+        blob_chunk_end(fetch, ar, start, end)
+    }
+    fn fetch_chunk<'w>(
+        fetch: &Self::Fetch<'w>,
+        ar: &'w Archetype,
+        start: Row,
+        end: Row,
+    ) -> Self::Chunk<'w> {
+        let data = blob_chunk_start(fetch, ar, start);
+        fetch.slice(data, Row(data.0 + end.0 - start.0))
+    }
+}
+
+impl<T: 'static> ChunkFetch for &mut T {
+    type Chunk<'a> = ChunkMut<'a, T>;
+
+    fn chunk_end<'w>(fetch: &Self::Fetch<'w>, ar: &'w Archetype, start: Row, end: Row) -> Row {
+        blob_chunk_end(&fetch.column, ar, start, end)
+    }
+    fn fetch_chunk<'w>(
+        fetch: &Self::Fetch<'w>,
+        ar: &'w Archetype,
+        start: Row,
+        end: Row,
+    ) -> Self::Chunk<'w> {
+        ChunkMut {
+            c: fetch.clone(),
+            ar,
+            start,
+            end,
+            data: blob_chunk_start(&fetch.column, ar, start),
+            changed: false,
+            _p: PhantomData,
+        }
+    }
+}
+
+/// 块内组件的可变借用，第一次可变解引用时，将块内所有组件标记为修改
+pub struct ChunkMut<'a, T: 'static> {
+    c: ColumnTick<'a>,
+    ar: &'a Archetype,
+    start: Row,
+    end: Row,
+    data: Row, // 块在列上的起始行，稀疏存储时与原型上的行不同
+    changed: bool,
+    _p: PhantomData<T>,
+}
+impl<'a, T: 'static> ChunkMut<'a, T> {
+    fn data_end(&self) -> Row {
+        Row(self.data.0 + self.end.0 - self.start.0)
+    }
+    pub fn bypass_change_detection(&mut self) -> &mut [T] {
+        self.c.column.slice_mut::<T>(self.data, self.data_end())
+    }
+    pub fn set_changed(&mut self) {
+        for row in self.start.0..self.end.0 {
+            let data = Row(self.data.0 + row - self.start.0);
+            self.c
+                .column
+                .changed_tick(self.ar.get_unchecked(Row(row)), data, self.c.tick);
+        }
+        self.changed = true;
+    }
+}
+impl<'a, T: 'static> Deref for ChunkMut<'a, T> {
+    type Target = [T];
+    fn deref(&self) -> &Self::Target {
+        self.c.column.slice::<T>(self.data, self.data_end())
+    }
+}
+impl<'a, T: 'static> DerefMut for ChunkMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if !self.changed {
+            self.set_changed();
+        }
+        self.c.column.slice_mut::<T>(self.data, self.data_end())
+    }
+}
+
+// 块在列上的起始行，稀疏存储时由起始实体获得
+fn blob_chunk_start(c: &BlobRef, ar: &Archetype, start: Row) -> Row {
+    c.entity_row(start, ar.get_unchecked(start))
+}
+
+// 从start开始内存连续的结束行，稀疏存储时，还要求相邻实体在稀疏集合中的行也相邻
+fn blob_chunk_end(c: &BlobRef, ar: &Archetype, start: Row, end: Row) -> Row {
+    let s = match c.sparse {
+        Some(s) => s,
+        None => return c.contiguous_end(start, end),
+    };
+    let data = s.row(ar.get_unchecked(start));
+    let mut row = Row(start.0 + 1);
+    while row.0 < end.0 {
+        let e = ar.get_unchecked(row);
+        if e.is_null() || s.row(e).0 != data.0 + row.0 - start.0 {
+            break;
+        }
+        row.0 += 1;
+    }
+    let data_end = c.contiguous_end(data, Row(data.0 + row.0 - start.0));
+    Row(start.0 + data_end.0 - data.0)
+}
+
+macro_rules! impl_tuple_chunk {
+    ($(($name: ident, $state: ident)),*) => {
+        #[allow(non_snake_case)]
+        #[allow(clippy::unused_unit)]
+        impl<$($name: ChunkFetch),*> ChunkFetch for ($($name,)*) {
+            type Chunk<'w> = ($($name::Chunk<'w>,)*);
+
+            fn chunk_end<'w>(_fetch: &Self::Fetch<'w>, _ar: &'w Archetype, _start: Row, end: Row) -> Row {
+                let ($($name,)*) = _fetch;
+                let mut _end = end;
+                $(_end = $name::chunk_end($name, _ar, _start, _end);)*
+                _end
+            }
+            fn fetch_chunk<'w>(
+                _fetch: &Self::Fetch<'w>,
+                _ar: &'w Archetype,
+                _start: Row,
+                _end: Row,
+            ) -> Self::Chunk<'w> {
+                let ($($name,)*) = _fetch;
+                ($($name::fetch_chunk($name, _ar, _start, _end),)*)
+            }
+        }
+    };
+}
+all_tuples!(impl_tuple_chunk, 0, 15, F, S);
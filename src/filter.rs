@@ -6,14 +6,18 @@
 //! Query<(&T, &mut C8>), (Without<C1>,With<C2>,With<C3>,Or<(With<C4>, With<C5>)>, Changed<C6>, Destroyed, Removed<C8>)>
 //!
 
+use pi_null::Null;
 use pi_proc_macros::all_tuples;
+pub use pi_world_macros::QueryFilter;
 use pi_share::Share;
+use std::borrow::Cow;
 use std::marker::PhantomData;
 
 use crate::archetype::{Archetype, ArchetypeIndex, ComponentInfo, Row, COMPONENT_TICK};
 use crate::column::{BlobRef, Column};
 use crate::prelude::{Entity, Tick};
-use crate::system::SystemMeta;
+use crate::query::QueryError;
+use crate::system::{Related, Relation, SystemMeta};
 use crate::world::{ComponentIndex, World};
 
 pub trait FilterComponents {
@@ -23,6 +27,11 @@ pub trait FilterComponents {
     /// initializes ReadWrite for this [`FilterComponents`] type.
     fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State;
 
+    /// 不修改world，用已注册的组件创建状态，读写关系记录到related上，用于从已有的查询派生查询
+    fn get_state(_world: &World, _related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        Err(QueryError::LensUnsupported(Cow::Borrowed(std::any::type_name::<Self>())))
+    }
+
     fn init_filter<'w>(
         world: &'w World,
         state: &'w Self::State,
@@ -55,7 +64,7 @@ pub trait FilterComponents {
 pub struct Without<T: 'static>(PhantomData<T>);
 impl<T: 'static> FilterComponents for Without<T> {
 
-    type Filter<'w> = ();
+    type Filter<'w> = Option<BlobRef<'w>>; // 稀疏存储的组件需要逐行判断
     type State = Share<Column>;
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
         meta.component_relate(
@@ -65,16 +74,23 @@ impl<T: 'static> FilterComponents for Without<T> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(0),
+            Relation::Without(0usize.into()),
+        )
+    }
 
     #[inline]
     fn init_filter<'w>(
         _world: &'w World,
-        _state: &'w Self::State,
+        state: &'w Self::State,
         _index: ArchetypeIndex,
         _tick: Tick,
         _last_run: Tick,
     ) -> Self::Filter<'w> {
-        ()
+        state.sparse.as_ref().map(|_| state.sparse_blob_ref())
     }
 
     // #[inline]
@@ -85,12 +101,19 @@ impl<T: 'static> FilterComponents for Without<T> {
         _tick: Tick,
         _last_run: Tick,
     ) -> (Self::Filter<'w>, bool) {
+        if state.sparse.is_some() {
+            return (Some(state.sparse_blob_ref()), true);
+        }
         match state.blob_ref(index) {
-            Some(_) => ((), false),
-            None => ((), true),
+            Some(_) => (None, false),
+            None => (None, true),
         }
     }
 
+    fn filter<'w>(filter: &Self::Filter<'w>, _row: Row, e: Entity) -> bool {
+        filter.as_ref().map_or(false, |r| !r.sparse_row(e).is_null())
+    }
+
     // fn is_match<'w>(
     //     state: &'w Self::State,
     //     archetype: &'w Archetype,
@@ -103,7 +126,7 @@ impl<T: 'static> FilterComponents for Without<T> {
 pub struct With<T: 'static>(PhantomData<T>);
 impl<T: 'static> FilterComponents for With<T> {
 
-    type Filter<'w> = ();
+    type Filter<'w> = Option<BlobRef<'w>>; // 稀疏存储的组件需要逐行判断
     type State = Share<Column>;
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
         meta.component_relate(
@@ -113,15 +136,22 @@ impl<T: 'static> FilterComponents for With<T> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(0),
+            Relation::With(0usize.into()),
+        )
+    }
     #[inline]
     fn init_filter<'w>(
         _world: &'w World,
         state: &'w Self::State,
-        index: ArchetypeIndex,
+        _index: ArchetypeIndex,
         _tick: Tick,
         _last_run: Tick,
     ) -> Self::Filter<'w>{
-        ()
+        state.sparse.as_ref().map(|_| state.sparse_blob_ref())
     }
 
     // #[inline]
@@ -132,12 +162,19 @@ impl<T: 'static> FilterComponents for With<T> {
         _tick: Tick,
         _last_run: Tick,
     ) -> (Self::Filter<'w>, bool) {
+        if state.sparse.is_some() {
+            return (Some(state.sparse_blob_ref()), true);
+        }
         match state.blob_ref(index) {
-            Some(_) => ((), true),
-            None => ((), false),
+            Some(_) => (None, true),
+            None => (None, false),
         }
     }
 
+    fn filter<'w>(filter: &Self::Filter<'w>, _row: Row, e: Entity) -> bool {
+        filter.as_ref().map_or(false, |r| r.sparse_row(e).is_null())
+    }
+
     // fn is_match<'w>(
     //     state: &'w Self::State,
     //     archetype: &'w Archetype,
@@ -160,6 +197,13 @@ impl<T: 'static> FilterComponents for Changed<T> {
         )
         .1
     }
+    fn get_state(world: &World, related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+        related.component_relate_ref(
+            world,
+            ComponentInfo::of::<T>(COMPONENT_TICK),
+            Relation::Read(0usize.into()),
+        )
+    }
 
     // #[inline(always)]
     fn init_filter<'w>(
@@ -169,6 +213,9 @@ impl<T: 'static> FilterComponents for Changed<T> {
         _tick: Tick,
         last_run: Tick,
     ) -> Self::Filter<'w> {
+        if state.sparse.is_some() {
+            return (Some(state.sparse_blob_ref()), last_run);
+        }
         (state.blob_ref(index), last_run)
     }
 
@@ -180,15 +227,23 @@ impl<T: 'static> FilterComponents for Changed<T> {
         _tick: Tick,
         last_run: Tick,
     ) -> (Self::Filter<'w>, bool) {
+        if state.sparse.is_some() {
+            return ((Some(state.sparse_blob_ref()), last_run), true);
+        }
         let r = state.blob_ref(index);
         let is_match = r.is_some();
         ((state.blob_ref(index), last_run), is_match)
     }
 
     // #[inline(always)]
-    fn filter<'w>(filter: &Self::Filter<'w>, row: Row, _e: Entity) -> bool {
+    fn filter<'w>(filter: &Self::Filter<'w>, row: Row, e: Entity) -> bool {
         if let Some(r) = &filter.0 {
-            r.get_tick_unchecked(row) <= filter.1
+            let row = match r.sparse {
+                // 稀疏存储的组件，实体没有该组件也跳过
+                Some(s) => s.row(e),
+                None => row,
+            };
+            row.is_null() || !r.get_tick_unchecked(row).is_newer_than(filter.1)
         } else {
             true
         }
@@ -210,6 +265,9 @@ macro_rules! impl_tuple_filter {
 	        fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {
                 ($($name::init_state(_world, _meta),)*)
             }
+            fn get_state(_world: &World, _related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+                Ok(($($name::get_state(_world, _related)?,)*))
+            }
 
             #[allow(clippy::unused_unit)]
             // #[inline]
@@ -271,6 +329,12 @@ macro_rules! impl_tuple_filter {
                 _meta.relate(crate::system::Relation::End);
                 s
             }
+            fn get_state(_world: &World, _related: &mut Related<ComponentIndex>) -> Result<Self::State, QueryError> {
+                _related.vec.push(Relation::Or);
+                let s = ($($name::get_state(_world, _related)?,)*);
+                _related.vec.push(Relation::End);
+                Ok(s)
+            }
 
             #[allow(clippy::unused_unit)]
             // #[inline]
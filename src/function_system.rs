@@ -1,6 +1,7 @@
-use std::{any::TypeId, borrow::Cow};
+use std::{any::{Any, TypeId}, borrow::Cow};
 
 use crate::{
+    pipe::{take_input, In, SystemInput},
     system::{IntoSystem, RunSystem, System, SystemMeta, TypeInfo},
     system_params::SystemParam,
     world::*,
@@ -17,11 +18,16 @@ pub type SystemParamItem<'w, P> = <P as SystemParam>::Item<'w>;
 pub trait SystemParamFunction<Marker, Out>: Send + Sync + 'static {
     /// The [`SystemParam`]/s used by this system to access the [`World`].
     type Param: SystemParam;
+    /// 管道输入的类型，第一个参数为[`In<T>`]时为T
+    type Input: Send + Sync + 'static = ();
 
     /// Executes this system once. See [`System::run`] or [`System::run_unsafe`].
-    fn run(&mut self, _param_value: SystemParamItem<Self::Param>) -> Out;
+    fn run(&mut self, input: Option<Box<dyn Any + Send + Sync>>, _param_value: SystemParamItem<Self::Param>) -> Out;
 }
 
+/// 第一个参数为[`In<T>`]的函数system的标记
+pub struct HasSystemInput;
+
 /// The [`System`] counter part of an ordinary function.
 ///
 /// You get this by calling [`IntoSystem::into_system`]  on a function that only accepts
@@ -99,6 +105,14 @@ where
             self.param.archetype_align_len = world.archetype_arr.len();
         }
     }
+    #[inline]
+    fn system_meta(&self) -> Option<&SystemMeta> {
+        Some(&self.param.system_meta)
+    }
+    #[inline]
+    fn system_meta_mut(&mut self) -> Option<&mut SystemMeta> {
+        Some(&mut self.param.system_meta)
+    }
 }
 impl<Marker, Out: 'static + Send + Sync, F> RunSystem for FunctionSystem<Marker, Out, F>
 where
@@ -106,10 +120,18 @@ where
 {
     #[inline]
     fn run(&mut self, world: &World) -> Out {
+        let input = self.param.system_meta.input.take();
         let params = self.param.get_param(world);
-        self.func.run(params)
+        self.func.run(input, params)
     }
 }
+
+impl<Marker, Out: 'static + Send + Sync, F> SystemInput for FunctionSystem<Marker, Out, F>
+where
+    F: SystemParamFunction<Marker, Out>,
+{
+    type Input = F::Input;
+}
 pub struct ParamSystem<P: SystemParam> {
     pub(crate) param_state: Option<P::State>,
     pub(crate) system_meta: SystemMeta,
@@ -225,7 +247,7 @@ macro_rules! impl_system_function {
         {
             type Param = ($($param,)*);
             // #[inline(always)]
-            fn run(&mut self, param_value: SystemParamItem< ($($param,)*)>) -> Out {
+            fn run(&mut self, _input: Option<Box<dyn Any + Send + Sync>>, param_value: SystemParamItem< ($($param,)*)>) -> Out {
                 // Yes, this is strange, but `rustc` fails to compile this impl
                 // without using this function. It fails to recognize that `func`
                 // is a function, potentially because of the multiple impls of `FnMut`
@@ -244,6 +266,35 @@ macro_rules! impl_system_function {
     };
 }
 
+macro_rules! impl_system_function_with_input {
+    ($($param: ident),*) => {
+        #[allow(non_snake_case)]
+        impl<Func: Send + Sync + 'static, Input: Send + Sync + 'static, Out, $($param: SystemParam),*> SystemParamFunction<(HasSystemInput, fn(In<Input>, $($param,)*) -> Out), Out> for Func
+        where
+        for <'a> &'a mut Func:
+                FnMut(In<Input>, $($param),*) -> Out +
+                FnMut(In<Input>, $(SystemParamItem<$param>),*) -> Out,
+        {
+            type Param = ($($param,)*);
+            type Input = Input;
+            fn run(&mut self, input: Option<Box<dyn Any + Send + Sync>>, param_value: SystemParamItem< ($($param,)*)>) -> Out {
+                #[allow(clippy::too_many_arguments)]
+                #[inline(always)]
+                fn call_inner<Input, Out, $($param,)*>(
+                    mut f: impl FnMut(In<Input>, $($param,)*) -> Out,
+                    input: In<Input>,
+                    $($param: $param,)*
+                ) -> Out {
+                    f(input, $($param,)*)
+                }
+                let ($($param,)*) = param_value;
+                call_inner(self, take_input::<Input>(input), $($param),*)
+            }
+        }
+    };
+}
+
 // Note that we rely on the highest impl to be <= the highest order of the tuple impls
 // of `SystemParam` created.
 all_tuples!(impl_system_function, 0, 32, F);
+all_tuples!(impl_system_function_with_input, 0, 31, F);
@@ -0,0 +1,386 @@
+//! 组件索引，根据组件计算出的键，快速查找实体
+//!
+//! 通过`World::register_index`注册，如`world.register_index(|c: &NetId| c.0)`。
+//! 多个实体可能有相同键的，通过`World::register_multi_index`注册。
+//! 索引通过组件的added、changed、removed事件维护，调度在每个阶段运行前同步一次，
+//! 在system中通过只读的`Indexed<K, T>`参数查询。
+//! 查询时会检查实体是否存活及键是否一致，所以被销毁的实体不会被查到。
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::RwLock;
+
+use pi_null::Null;
+use pi_share::Share;
+
+use crate::archetype::{ComponentInfo, Row, COMPONENT_TICK};
+use crate::event::{
+    init_added_state, init_changed_state, init_removed_state, ComponentAdded, ComponentChanged,
+    ComponentEventVec, ComponentRemoved,
+};
+use crate::query::{Query, QueryState};
+use crate::single_res::{Resource, SingleRes};
+use crate::system::SystemMeta;
+use crate::system_params::SystemParam;
+use crate::world::{Entity, World};
+use crate::world_ptr::Ptr;
+
+/// 键到实体的映射
+pub(crate) trait IndexMap<K, T>: Send + Sync {
+    /// 插入或更新实体的键
+    fn insert(&mut self, e: Entity, value: &T);
+    /// 移除实体的键
+    fn remove(&mut self, e: Entity);
+    /// 查找键对应的实体
+    fn get(&self, key: &K, world: &World) -> Option<Entity>;
+    /// 查找键对应的所有实体
+    fn get_all(&self, key: &K, world: &World) -> Vec<Entity>;
+    /// 移除已销毁的实体
+    fn retain_alive(&mut self, world: &World);
+    fn len(&self) -> usize;
+}
+
+struct KeyMap<T, K> {
+    key_fn: Box<dyn Fn(&T) -> K + Send + Sync>,
+    map: HashMap<K, Entity>,
+    keys: HashMap<Entity, K>, // 实体当前的键，用于移除旧键
+}
+
+impl<T: 'static, K: Hash + Eq + Clone + Send + Sync + 'static> IndexMap<K, T> for KeyMap<T, K> {
+    fn insert(&mut self, e: Entity, value: &T) {
+        let key = (self.key_fn)(value);
+        if let Some(old) = self.keys.insert(e, key.clone()) {
+            if old == key {
+                return;
+            }
+            if self.map.get(&old) == Some(&e) {
+                self.map.remove(&old);
+            }
+        }
+        self.map.insert(key, e);
+    }
+    fn remove(&mut self, e: Entity) {
+        if let Some(old) = self.keys.remove(&e) {
+            if self.map.get(&old) == Some(&e) {
+                self.map.remove(&old);
+            }
+        }
+    }
+    fn get(&self, key: &K, world: &World) -> Option<Entity> {
+        let e = *self.map.get(key)?;
+        // 检查实体是否存活，及键是否一致
+        match world.get_component::<T>(e) {
+            Ok(value) if (self.key_fn)(value) == *key => Some(e),
+            _ => None,
+        }
+    }
+    fn get_all(&self, key: &K, world: &World) -> Vec<Entity> {
+        self.get(key, world).into_iter().collect()
+    }
+    fn retain_alive(&mut self, world: &World) {
+        let map = &mut self.map;
+        self.keys.retain(|e, key| {
+            if world.get_component::<T>(*e).is_ok() {
+                return true;
+            }
+            if map.get(key) == Some(e) {
+                map.remove(key);
+            }
+            false
+        });
+    }
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+/// 一个键对应多个实体
+pub(crate) struct MultiKeyMap<T, K> {
+    key_fn: Box<dyn Fn(&T) -> K + Send + Sync>,
+    map: HashMap<K, Vec<Entity>>,
+    pub(crate) keys: HashMap<Entity, K>,
+}
+
+impl<T, K: Hash + Eq> MultiKeyMap<T, K> {
+    pub(crate) fn new(key_fn: impl Fn(&T) -> K + Send + Sync + 'static) -> Self {
+        Self {
+            key_fn: Box::new(key_fn),
+            map: HashMap::new(),
+            keys: HashMap::new(),
+        }
+    }
+    fn remove_key(&mut self, key: &K, e: Entity) {
+        if let Some(list) = self.map.get_mut(key) {
+            if let Some(i) = list.iter().position(|r| *r == e) {
+                list.swap_remove(i);
+            }
+            if list.is_empty() {
+                self.map.remove(key);
+            }
+        }
+    }
+}
+
+impl<T: 'static, K: Hash + Eq + Clone + Send + Sync + 'static> IndexMap<K, T> for MultiKeyMap<T, K> {
+    fn insert(&mut self, e: Entity, value: &T) {
+        let key = (self.key_fn)(value);
+        if let Some(old) = self.keys.insert(e, key.clone()) {
+            if old == key {
+                return;
+            }
+            self.remove_key(&old, e);
+        }
+        self.map.entry(key).or_default().push(e);
+    }
+    fn remove(&mut self, e: Entity) {
+        if let Some(old) = self.keys.remove(&e) {
+            self.remove_key(&old, e);
+        }
+    }
+    fn get(&self, key: &K, world: &World) -> Option<Entity> {
+        self.map.get(key)?.iter().copied().find(|e| match world.get_component::<T>(*e) {
+            Ok(value) => (self.key_fn)(value) == *key,
+            Err(_) => false,
+        })
+    }
+    fn get_all(&self, key: &K, world: &World) -> Vec<Entity> {
+        let list = match self.map.get(key) {
+            Some(r) => r,
+            None => return Vec::new(),
+        };
+        list.iter()
+            .copied()
+            .filter(|e| match world.get_component::<T>(*e) {
+                Ok(value) => (self.key_fn)(value) == *key,
+                Err(_) => false,
+            })
+            .collect()
+    }
+    fn retain_alive(&mut self, world: &World) {
+        let mut dead = Vec::new();
+        self.keys.retain(|e, key| {
+            if world.get_component::<T>(*e).is_ok() {
+                return true;
+            }
+            dead.push((*e, key.clone()));
+            false
+        });
+        for (e, key) in dead {
+            self.remove_key(&key, e);
+        }
+    }
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+/// 组件T以K为键的索引，作为单例资源存放在World上
+pub struct Index<K: 'static, T: 'static> {
+    map: RwLock<Box<dyn IndexMap<K, T>>>, // 同步时写，查找时读，可通过共享引用同步
+    added: (Share<ComponentEventVec>, usize),
+    changed: (Share<ComponentEventVec>, usize),
+    removed: (Share<ComponentEventVec>, usize),
+}
+unsafe impl<K, T> Send for Index<K, T> {}
+unsafe impl<K, T> Sync for Index<K, T> {}
+impl<K: 'static, T: 'static> Resource for Index<K, T> {}
+
+impl<K: 'static, T: 'static> Index<K, T> {
+    /// 根据组件的事件，更新索引
+    pub(crate) fn sync(&self, world: &World) {
+        let mut map = self.map.write().unwrap();
+        for e in self
+            .added
+            .0
+            .get_iter(self.added.1)
+            .chain(self.changed.0.get_iter(self.changed.1))
+            .chain(self.removed.0.get_iter(self.removed.1))
+        {
+            // 事件的先后顺序无法保证，所以以实体当前的组件为准
+            match world.get_component::<T>(*e) {
+                Ok(value) => map.insert(*e, value),
+                Err(_) => map.remove(*e),
+            }
+        }
+        // 实体销毁没有事件，如果索引的数量超过了组件的数量，则清理已销毁的实体
+        if map.len() > component_len::<T>(world) {
+            map.retain_alive(world);
+        }
+    }
+    /// 查找键对应的实体
+    pub fn get(&self, key: &K, world: &World) -> Option<Entity> {
+        self.map.read().unwrap().get(key, world)
+    }
+    /// 查找键对应的所有实体
+    pub fn get_all(&self, key: &K, world: &World) -> Vec<Entity> {
+        self.map.read().unwrap().get_all(key, world)
+    }
+    /// 索引的数量
+    pub fn len(&self) -> usize {
+        self.map.read().unwrap().len()
+    }
+}
+
+// 包含组件T的实体数量（包括未整理的被移除行）
+fn component_len<T: 'static>(world: &World) -> usize {
+    let c = match world.get_column_by_id(&TypeId::of::<T>()) {
+        Some(c) => c,
+        None => return 0,
+    };
+    world
+        .archetype_arr
+        .iter()
+        .filter(|ar| c.contains(ar.index()))
+        .map(|ar| ar.len().index())
+        .sum()
+}
+
+impl World {
+    /// 注册组件T的索引，key_fn根据组件计算键，注册后可通过`Indexed<K, T>`查找实体。
+    /// 如：`world.register_index(|c: &NetId| c.0)`
+    pub fn register_index<T: 'static, K: Hash + Eq + Clone + Send + Sync + 'static>(
+        &mut self,
+        key_fn: impl Fn(&T) -> K + Send + Sync + 'static,
+    ) {
+        self.register_index_map(Box::new(KeyMap {
+            key_fn: Box::new(key_fn),
+            map: HashMap::new(),
+            keys: HashMap::new(),
+        }));
+    }
+    /// 注册组件T的索引，允许多个实体有相同的键，通过`Indexed::get_all`查找
+    pub fn register_multi_index<T: 'static, K: Hash + Eq + Clone + Send + Sync + 'static>(
+        &mut self,
+        key_fn: impl Fn(&T) -> K + Send + Sync + 'static,
+    ) {
+        self.register_index_map(Box::new(MultiKeyMap::new(key_fn)));
+    }
+    pub(crate) fn register_index_map<K: 'static, T: 'static>(&mut self, mut map: Box<dyn IndexMap<K, T>>) {
+        let changed = init_changed_state(
+            self,
+            TypeId::of::<ComponentChanged<'static, T>>(),
+            ComponentInfo::of::<T>(COMPONENT_TICK),
+        );
+        let added = init_added_state(
+            self,
+            TypeId::of::<ComponentAdded<'static, T>>(),
+            ComponentInfo::of::<T>(0),
+        );
+        let removed = init_removed_state(
+            self,
+            TypeId::of::<ComponentRemoved<'static, T>>(),
+            ComponentInfo::of::<T>(0),
+        );
+        // 将已有的实体放入索引
+        if let Some(c) = self.get_column_by_id(&TypeId::of::<T>()) {
+            for ar in self.archetype_arr.iter() {
+                let r = match c.blob_ref(ar.index()) {
+                    Some(r) => r,
+                    None => continue,
+                };
+                for row in 0..ar.len().index() {
+                    let row = Row(row as u32);
+                    let e = ar.get_unchecked(row);
+                    if !e.is_null() {
+                        map.insert(e, r.get::<T>(row, e));
+                    }
+                }
+            }
+        }
+        self.insert_single_res(Index {
+            map: RwLock::new(map),
+            added,
+            changed,
+            removed,
+        });
+        self.index_syncs.push(sync_index::<K, T>);
+    }
+    /// 根据组件的事件同步所有索引，调度在每个阶段运行前调用
+    pub fn sync_indexes(&self) {
+        for i in 0..self.index_syncs.len() {
+            (self.index_syncs[i])(self);
+        }
+    }
+}
+
+fn sync_index<K: 'static, T: 'static>(world: &World) {
+    world.get_single_res::<Index<K, T>>().unwrap().sync(world);
+}
+
+/// 组件索引参数，按键查找实体，只读
+///
+/// 键的类型不匹配时编译失败：
+/// ```compile_fail
+/// use pi_world::prelude::*;
+/// #[derive(Component)]
+/// pub struct NetId(pub u32);
+/// fn lookup(index: Indexed<u32, NetId>) {
+///     index.get(&1u64);
+/// }
+/// ```
+pub struct Indexed<'w, K: 'static, T: 'static> {
+    index: SingleRes<'w, Index<K, T>>,
+    world: &'w World,
+}
+unsafe impl<K, T> Send for Indexed<'_, K, T> {}
+unsafe impl<K, T> Sync for Indexed<'_, K, T> {}
+
+impl<'w, K: 'static, T: 'static> Indexed<'w, K, T> {
+    /// 查找键对应的实体
+    pub fn get(&self, key: &K) -> Option<Entity> {
+        self.index.get(key, self.world)
+    }
+    /// 查找键对应的所有实体
+    pub fn get_all(&self, key: &K) -> Vec<Entity> {
+        self.index.get_all(key, self.world)
+    }
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+}
+
+pub struct IndexedState<K: 'static, T: 'static> {
+    query: QueryState<&'static T, ()>, // 声明对组件T的读
+    res: <SingleRes<'static, Index<K, T>> as SystemParam>::State,
+    world: Ptr<World>,
+}
+
+impl<K: 'static, T: 'static> SystemParam for Indexed<'_, K, T> {
+    type State = IndexedState<K, T>;
+    type Item<'w> = Indexed<'w, K, T>;
+
+    fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
+        assert!(
+            world.get_single_res::<Index<K, T>>().is_some(),
+            "Indexed<{}, {}> is used before World::register_index",
+            std::any::type_name::<K>(),
+            std::any::type_name::<T>()
+        );
+        IndexedState {
+            query: <Query<'static, &'static T> as SystemParam>::init_state(world, meta),
+            res: <SingleRes<'static, Index<K, T>> as SystemParam>::init_state(world, meta),
+            world: Ptr::new(world),
+        }
+    }
+    fn align(state: &mut Self::State) {
+        state.query.align();
+    }
+    fn init(state: &mut Self::State) {
+        <SingleRes<'static, Index<K, T>> as SystemParam>::init(&mut state.res);
+    }
+    fn get_param<'world>(state: &'world mut Self::State) -> Self::Item<'world> {
+        Indexed {
+            index: <SingleRes<'static, Index<K, T>> as SystemParam>::get_param(&mut state.res),
+            world: &state.world,
+        }
+    }
+    fn get_self<'world>(state: &'world mut Self::State) -> Self {
+        // 安全：world不可移动，且在system运行期间存活
+        let world = unsafe { &*(&*state.world as *const World) };
+        Indexed {
+            index: <SingleRes<'_, Index<K, T>> as SystemParam>::get_self(&mut state.res),
+            world,
+        }
+    }
+}
@@ -15,6 +15,7 @@ use crate::world::*;
 use crate::world_ptr::Ptr;
 pub use pi_world_macros::Bundle;
 pub use pi_world_macros::Component;
+pub use crate::archetype::Component;
 
 // pub type Insert<'w, B> = &'w mut Insert<'w, B>;
 pub struct Insert<'world, B: Bundle> {
@@ -50,10 +51,13 @@ impl<B: Bundle + 'static> SystemParam for Insert<'_, B> {
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
         // 加meta 如果world上没有找到对应的原型，则创建并放入world中
-        let components = B::components(Vec::with_capacity(256));
+        let mut components = B::components(Vec::with_capacity(256));
+        let required = ComponentInfo::add_required(&mut components);
         let ar = meta.insert(world, components);
         let s = B::init_item(world, &ar);
-        InsertState::new(ar, s, Ptr::new(meta), Ptr::new(world))
+        let mut state = InsertState::new(ar, s, Ptr::new(meta), Ptr::new(world));
+        state.set_required(world, required);
+        state
     }
     #[inline]
     fn get_param<'world>(
@@ -74,12 +78,20 @@ pub struct InsertState<B: Bundle> {
     pub(crate) item: B::Item,
     pub(crate) system_meta: Ptr<SystemMeta>,
     pub(crate) world: Ptr<World>,
+    pub(crate) required: Vec<(Share<Column>, fn(*mut u8))>, // Bundle中没有的必需组件，插入时用默认值初始化
 }
 
 impl<B: Bundle> InsertState<B> {
     #[inline(always)]
     pub fn new(archetype: ShareArchetype, item: B::Item, system_meta: Ptr<SystemMeta>, world: Ptr<World>) -> Self {
-        Self { archetype, item, system_meta, world }
+        Self { archetype, item, system_meta, world, required: Vec::new() }
+    }
+    // 设置必需组件的列及初始化函数
+    pub(crate) fn set_required(&mut self, world: &World, required: Vec<(TypeId, fn(*mut u8))>) {
+        self.required = required
+            .into_iter()
+            .map(|(id, init)| (world.get_column_by_id(&id).unwrap().clone(), init))
+            .collect();
     }
     #[inline(always)]
     pub fn insert(&self, _world: &World, components: B) -> Entity {
@@ -90,6 +102,17 @@ impl<B: Bundle> InsertState<B> {
         let (r, row) = self.archetype.alloc();
         let e = self.world.insert_addr(self.archetype.index(), row.into());
         B::insert(&self.item, components, e, row.into(), self.system_meta.this_run);
+        for (c, init) in self.required.iter() {
+            let (c, row) = if c.sparse.is_some() {
+                let c = c.sparse_blob_ref();
+                let (row, _) = c.sparse_alloc(e);
+                (c, row)
+            } else {
+                (c.blob_ref_unchecked(self.archetype.index()), row.into())
+            };
+            init(c.load(row, e));
+            c.added_tick(e, row, self.system_meta.this_run);
+        }
         *r = e;
         e
     }
@@ -184,6 +207,27 @@ pub trait Bundle {
     fn init_item(world: &World, archetype: &Archetype) -> Self::Item;
 
     fn insert(item: &Self::Item, components: Self, e: Entity, row: Row, tick: Tick);
+
+    /// 收集组件及其在Bundle中的字段路径，用于检查重复的组件
+    fn component_paths(path: String, paths: &mut Vec<(ComponentInfo, String)>) {
+        for info in Self::components(Vec::new()) {
+            paths.push((info, path.clone()));
+        }
+    }
+}
+
+/// 检查Bundle中是否有重复的组件，有则panic并给出两个字段的路径
+pub fn check_duplicate(paths: &[(ComponentInfo, String)]) {
+    for (i, (info, path)) in paths.iter().enumerate() {
+        if let Some((_, other)) = paths[..i].iter().find(|(c, _)| c.type_id() == info.type_id()) {
+            panic!(
+                "duplicate component `{}` in bundle: `{}` and `{}`",
+                info.type_name(),
+                other,
+                path
+            );
+        }
+    }
 }
 
 pub struct TypeItem<T: 'static>(Share<Column>, ArchetypeIndex, PhantomData<T>);
@@ -197,6 +241,19 @@ impl<T: 'static> TypeItem<T> {
     }
     #[inline(always)]
     pub fn write(&self, val: T, e: Entity, row: Row, tick: Tick) {
+        if self.0.sparse.is_some() {
+            // 稀疏存储的组件，按实体分配行
+            let c = self.0.sparse_blob_ref();
+            let (row, existed) = c.sparse_alloc(e);
+            c.write(row, e, val);
+            // 实体已有该组件，则是替换组件
+            if existed {
+                c.changed_tick(e, row, tick);
+            } else {
+                c.added_tick(e, row, tick);
+            }
+            return;
+        }
         let c = self.0.blob_ref_unchecked(self.1);
         c.write(row, e, val);
         c.added_tick(e, row, tick);
@@ -26,33 +26,41 @@ pub mod prelude {
     #[doc(hidden)]
     pub use crate::{
         param_unready::ParamUnReady,
-        app::App,
-        query::{Query, QueryUnReady, QueryError, EntryQuery},
+        app::{App, AppError, PluginsState},
+        query::{Query, QueryUnReady, QueryError, EntryQuery, QueryLens},
         insert::{Insert, Bundle, Component},
         alter::Alter,
         editor::EntityEditor,
-        event:: {Event, EventReader, EventWriter, ComponentChanged, ComponentAdded, ComponentRemoved},
+        event:: {Event, EventData, EventRetain, EventReader, EventWriter, ComponentChanged, ComponentAdded, ComponentRemoved},
         param_set::{ParamSet, ParamSetElement},
-        single_res::{SingleRes, SingleResMut},
+        single_res::{SingleRes, SingleResMut, Resource},
         // multi_res::{MultiRes, MultiResMut},
-        filter::{Changed, With, Without, Or, FilterComponents},
-        fetch::{Has, Ref, Mut, OrDefault, OrDefaultRef, Ticker, ComponentId, ArchetypeName},
-        system::{BoxedSystem, IntoSystem, IntoAsyncSystem, SystemMeta},
+        filter::{Changed, With, Without, Or, FilterComponents, QueryFilter},
+        fetch::{Has, Ref, Mut, OrDefault, OrDefaultRef, Ticker, ComponentId, ArchetypeName, FetchComponents, QueryData},
+        system::{BoxedSystem, IntoSystem, IntoAsyncSystem, SystemMeta, SystemId},
         system_params::{SystemParam, Local, ComponentDebugIndex},
         world::{Entity, World, FromWorld, Tick},
+        memory::{MemSize, MemoryReport},
+        validate::ValidationError,
+        index::Indexed,
+        name::{Name, Names},
+        sorted_query::SortedQuery,
         listener::Listener,
-        plugin::{Plugin, Plugins},
-        plugin_group::WorldPluginExtent,
-        schedule::{Schedule, Update, PreUpdate, Startup, PostUpdate, Last, First, End},
-        schedule_config::{ScheduleLabel, StageLabel, SystemSet, IntoSystemSetConfigs, IntoSystemConfigs},
+        plugin::{Plugin, Plugins, plugin_name},
+        plugin_group::{WorldPluginExtent, PluginGroup, PluginGroupBuilder},
+        schedule::{Schedule, Update, PreUpdate, Startup, PostUpdate, Last, First, End, AmbiguityDetection, AmbiguityReport, ScheduleBuildError},
+        schedule_config::{ScheduleLabel, StageLabel, SystemSet, IntoSystemSetConfigs, IntoSystemConfigs, Condition},
+        pipe::In,
+        condition::{not, resource_exists, resource_changed, on_event, run_once, any_with_component, every_n_runs},
         exec_graph::ExecGraph,
         dot::{Dot, Config},
-        commands::{Command, CommandQueue},
+        commands::{Command, CommandQueue, Commands},
     };
 }
 
 pub mod param_unready;
 pub mod column;
+pub mod sparse;
 pub mod table;
 pub mod archetype;
 pub mod query;
@@ -83,7 +91,14 @@ pub mod blob;
 
 mod test;
 pub mod schedule_config;
+pub mod condition;
+pub mod pipe;
 mod plugin;
 mod plugin_group;
 pub mod utils;
 pub mod debug;
+pub mod memory;
+pub mod validate;
+pub mod index;
+pub mod name;
+pub mod sorted_query;
@@ -0,0 +1,301 @@
+//! 内存报告
+//!
+//! 统计世界中每个原型、每个组件、事件、监听器、单例资源及实体表的内存大小。
+//! 报告可以表格形式打印（Display），也可以输出为json（`to_json`）。
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter, Result, Write};
+
+use crate::world::World;
+
+/// 资源可实现该trait，报告自身的内存大小（包括堆上分配的内存）。
+/// 未实现的资源，只统计类型本身的大小
+pub trait MemSize {
+    fn mem_size(&self) -> usize;
+}
+
+pub(crate) trait ResMemSize {
+    fn res_mem_size(&self) -> Option<usize>;
+}
+impl<T> ResMemSize for T {
+    default fn res_mem_size(&self) -> Option<usize> {
+        None
+    }
+}
+impl<T: MemSize> ResMemSize for T {
+    fn res_mem_size(&self) -> Option<usize> {
+        Some(self.mem_size())
+    }
+}
+
+/// 原型上一列的内存
+#[derive(Debug, Clone)]
+pub struct ColumnMemory {
+    pub name: Cow<'static, str>,
+    pub blob: usize,  // 组件数据的内存大小
+    pub ticks: usize, // tick的内存大小
+}
+impl ColumnMemory {
+    pub fn size(&self) -> usize {
+        self.blob + self.ticks
+    }
+}
+
+/// 原型的内存
+#[derive(Debug, Clone)]
+pub struct ArchetypeMemory {
+    pub index: usize,
+    pub name: Cow<'static, str>,
+    pub entities: usize,     // 实体数量
+    pub capacity: usize,     // 容量
+    pub entities_size: usize, // 实体表及移除表的内存大小
+    pub columns: Vec<ColumnMemory>,
+}
+impl ArchetypeMemory {
+    pub fn size(&self) -> usize {
+        self.entities_size + self.columns.iter().map(|c| c.size()).sum::<usize>()
+    }
+}
+
+/// 组件在所有原型上的内存合计
+#[derive(Debug, Clone)]
+pub struct ComponentMemory {
+    pub name: Cow<'static, str>,
+    pub archetypes: usize, // 包含该组件的原型数量
+    pub blob: usize,
+    pub ticks: usize,
+    pub events: usize, // changed、added、removed事件的内存大小
+}
+impl ComponentMemory {
+    pub fn size(&self) -> usize {
+        self.blob + self.ticks + self.events
+    }
+}
+
+/// 事件的内存
+#[derive(Debug, Clone)]
+pub struct EventMemory {
+    pub name: Cow<'static, str>,
+    pub size: usize,
+}
+
+/// 单例资源的内存
+#[derive(Debug, Clone)]
+pub struct ResourceMemory {
+    pub name: Cow<'static, str>,
+    pub size: usize,
+    pub deep: bool, // 是否由MemSize报告，否则只是类型本身的大小
+}
+
+/// 世界的内存报告
+#[derive(Debug, Clone, Default)]
+pub struct MemoryReport {
+    pub archetypes: Vec<ArchetypeMemory>,
+    pub components: Vec<ComponentMemory>,
+    pub events: Vec<EventMemory>,
+    pub listeners: usize,
+    pub resources: Vec<ResourceMemory>,
+    pub entities: usize,      // 实体数量
+    pub entities_size: usize, // 实体SlotMap的内存大小
+}
+
+impl MemoryReport {
+    pub(crate) fn new(world: &World) -> Self {
+        let mut components: Vec<ComponentMemory> = world
+            .component_arr
+            .iter()
+            .map(|c| ComponentMemory {
+                name: c.info().type_name().clone(),
+                archetypes: 0,
+                blob: 0,
+                ticks: 0,
+                events: c.info.event_memsize(),
+            })
+            .collect();
+        let mut archetypes = Vec::new();
+        for ar in world.archetype_arr.iter() {
+            let mut columns = Vec::with_capacity(ar.column_len());
+            for c in ar.get_columns().iter() {
+                let (blob, ticks) = c.archetype_memsize(ar.index());
+                if let Some(m) = components.get_mut(c.info().index.index()) {
+                    m.archetypes += 1;
+                    m.blob += blob;
+                    m.ticks += ticks;
+                }
+                columns.push(ColumnMemory {
+                    name: c.info().type_name().clone(),
+                    blob,
+                    ticks,
+                });
+            }
+            archetypes.push(ArchetypeMemory {
+                index: ar.index().index(),
+                name: ar.name().clone(),
+                entities: ar.len().index(),
+                capacity: ar.capacity(),
+                entities_size: ar.entities_mem_size(),
+                columns,
+            });
+        }
+        let mut events: Vec<EventMemory> = world
+            .event_map
+            .values()
+            .map(|e| {
+                let (name, size) = e.mem_info();
+                EventMemory {
+                    name: Cow::Owned(name.to_string()),
+                    size,
+                }
+            })
+            .collect();
+        events.sort_by(|a, b| a.name.cmp(&b.name));
+        let resources = world
+            .single_res_arr
+            .iter()
+            .flatten()
+            .map(|r| r.mem_info())
+            .collect();
+        MemoryReport {
+            archetypes,
+            components,
+            events,
+            listeners: world.listener_mgr.memsize(),
+            resources,
+            entities: world.entities.len(),
+            entities_size: world.entities.mem_size(),
+        }
+    }
+    /// 总的内存大小
+    pub fn total(&self) -> usize {
+        self.archetypes.iter().map(|a| a.size()).sum::<usize>()
+            + self.components.iter().map(|c| c.events).sum::<usize>()
+            + self.events.iter().map(|e| e.size).sum::<usize>()
+            + self.listeners
+            + self.resources.iter().map(|r| r.size).sum::<usize>()
+            + self.entities_size
+    }
+    /// 输出为json
+    pub fn to_json(&self) -> String {
+        let mut s = String::new();
+        s.push_str("{\"total\":");
+        let _ = write!(s, "{}", self.total());
+        s.push_str(",\"entities\":{\"len\":");
+        let _ = write!(s, "{},\"size\":{}}}", self.entities, self.entities_size);
+        let _ = write!(s, ",\"listeners\":{}", self.listeners);
+        s.push_str(",\"archetypes\":[");
+        for (i, a) in self.archetypes.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            let _ = write!(
+                s,
+                "{{\"index\":{},\"name\":{},\"entities\":{},\"capacity\":{},\"entities_size\":{},\"size\":{},\"columns\":[",
+                a.index,
+                json_str(&a.name),
+                a.entities,
+                a.capacity,
+                a.entities_size,
+                a.size()
+            );
+            for (j, c) in a.columns.iter().enumerate() {
+                if j > 0 {
+                    s.push(',');
+                }
+                let _ = write!(
+                    s,
+                    "{{\"name\":{},\"blob\":{},\"ticks\":{}}}",
+                    json_str(&c.name),
+                    c.blob,
+                    c.ticks
+                );
+            }
+            s.push_str("]}");
+        }
+        s.push_str("],\"components\":[");
+        for (i, c) in self.components.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            let _ = write!(
+                s,
+                "{{\"name\":{},\"archetypes\":{},\"blob\":{},\"ticks\":{},\"events\":{}}}",
+                json_str(&c.name),
+                c.archetypes,
+                c.blob,
+                c.ticks,
+                c.events
+            );
+        }
+        s.push_str("],\"events\":[");
+        for (i, e) in self.events.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            let _ = write!(s, "{{\"name\":{},\"size\":{}}}", json_str(&e.name), e.size);
+        }
+        s.push_str("],\"resources\":[");
+        for (i, r) in self.resources.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            let _ = write!(
+                s,
+                "{{\"name\":{},\"size\":{},\"deep\":{}}}",
+                json_str(&r.name),
+                r.size,
+                r.deep
+            );
+        }
+        s.push_str("]}");
+        s
+    }
+}
+
+impl Display for MemoryReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "total: {}", self.total())?;
+        writeln!(f, "entities: {}, size: {}", self.entities, self.entities_size)?;
+        writeln!(f, "listeners: {}", self.listeners)?;
+        writeln!(f, "{:<6} {:>10} {:>10} {:>12}  {}", "index", "entities", "capacity", "size", "archetype")?;
+        for a in self.archetypes.iter() {
+            writeln!(f, "{:<6} {:>10} {:>10} {:>12}  {}", a.index, a.entities, a.capacity, a.size(), a.name)?;
+            for c in a.columns.iter() {
+                writeln!(f, "{:<6} {:>10} {:>10} {:>12}    {}", "", "", "", c.size(), c.name)?;
+            }
+        }
+        writeln!(f, "{:>10} {:>12} {:>12} {:>12}  {}", "archetypes", "blob", "ticks", "events", "component")?;
+        for c in self.components.iter() {
+            writeln!(f, "{:>10} {:>12} {:>12} {:>12}  {}", c.archetypes, c.blob, c.ticks, c.events, c.name)?;
+        }
+        writeln!(f, "{:>12}  {}", "size", "event")?;
+        for e in self.events.iter() {
+            writeln!(f, "{:>12}  {}", e.size, e.name)?;
+        }
+        writeln!(f, "{:>12}  {}", "size", "resource")?;
+        for r in self.resources.iter() {
+            writeln!(f, "{:>12}{} {}", r.size, if r.deep { " " } else { "*" }, r.name)?;
+        }
+        Ok(())
+    }
+}
+
+// 转为json字符串
+fn json_str(s: &str) -> String {
+    let mut r = String::with_capacity(s.len() + 2);
+    r.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            '\n' => r.push_str("\\n"),
+            '\r' => r.push_str("\\r"),
+            '\t' => r.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(r, "\\u{:04x}", c as u32);
+            }
+            c => r.push(c),
+        }
+    }
+    r.push('"');
+    r
+}
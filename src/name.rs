@@ -0,0 +1,257 @@
+//! 实体名称
+//!
+//! `Name`是内置的组件，给实体命名，方便调试。
+//! 通过`World::find_by_name`或system参数`Names`，按名称查找实体，名称可以重复。
+//! 注册名称索引后（`World::register_name_index`，使用`Names`或第一次运行`Name`的添加钩子时自动注册），按名称查找不再遍历实体。
+//! 名称索引同步时，实体的名称也同步到全局的名称表，`Entity`的`Debug`有名称时显示为`Entity(1v1 "name")`。
+//! 多个World的实体键可能相同，此时显示最后同步的名称。
+//! 需要立即读取World上的名称时，可以用`World::entity_debug`。
+
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter, Result};
+use std::ops::Deref;
+use std::sync::LazyLock;
+
+use dashmap::DashMap;
+use pi_key_alloter::Key;
+use pi_null::Null;
+
+use crate::archetype::{Component, ComponentHook, Row};
+use crate::index::{Index, IndexMap, Indexed, MultiKeyMap};
+use crate::system::SystemMeta;
+use crate::system_params::SystemParam;
+use crate::world::{Entity, World};
+
+/// 实体的名称
+#[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name(Cow<'static, str>);
+
+impl Name {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Name(name.into())
+    }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    pub fn set(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.0 = name.into();
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl Debug for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(&self.0)
+    }
+}
+impl From<&'static str> for Name {
+    fn from(name: &'static str) -> Self {
+        Name(Cow::Borrowed(name))
+    }
+}
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Name(Cow::Owned(name))
+    }
+}
+
+impl Component for Name {
+    // 第一次添加名称时注册名称索引，之后由索引维护名称表
+    const ON_ADD: Option<ComponentHook> = Some(name_added);
+}
+fn name_added(world: &mut World, _e: Entity) {
+    world.register_name_index();
+}
+
+// 实体的名称表，由名称索引同步，用于打印实体
+static ENTITY_NAMES: LazyLock<DashMap<Entity, Name>> = LazyLock::new(DashMap::default);
+
+fn fmt_entity(e: Entity, name: Option<&Name>, f: &mut Formatter<'_>) -> Result {
+    match name {
+        Some(name) => write!(f, "Entity({:?} {:?})", e.data(), name),
+        None => write!(f, "Entity({:?})", e.data()),
+    }
+}
+
+impl Debug for Entity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match ENTITY_NAMES.get(self) {
+            Some(name) => fmt_entity(*self, Some(name.value()), f),
+            None => fmt_entity(*self, None, f),
+        }
+    }
+}
+
+// 名称索引，同时将实体的名称同步到名称表
+struct NameMap(MultiKeyMap<Name, Name>);
+
+impl IndexMap<Name, Name> for NameMap {
+    fn insert(&mut self, e: Entity, value: &Name) {
+        self.0.insert(e, value);
+        ENTITY_NAMES.insert(e, value.clone());
+    }
+    fn remove(&mut self, e: Entity) {
+        if let Some(name) = self.0.keys.get(&e) {
+            ENTITY_NAMES.remove_if(&e, |_, r| r == name);
+        }
+        self.0.remove(e);
+    }
+    fn get(&self, key: &Name, world: &World) -> Option<Entity> {
+        self.0.get(key, world)
+    }
+    fn get_all(&self, key: &Name, world: &World) -> Vec<Entity> {
+        self.0.get_all(key, world)
+    }
+    fn retain_alive(&mut self, world: &World) {
+        for (e, name) in self.0.keys.iter() {
+            if world.get_component::<Name>(*e).is_err() {
+                ENTITY_NAMES.remove_if(e, |_, r| r == name);
+            }
+        }
+        self.0.retain_alive(world);
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+impl Drop for NameMap {
+    fn drop(&mut self) {
+        for (e, name) in self.0.keys.iter() {
+            ENTITY_NAMES.remove_if(e, |_, r| r == name);
+        }
+    }
+}
+
+/// 打印实体，直接读取World上的名称，有名称时显示为`Entity(1v1 "name")`
+pub struct EntityDebug<'w> {
+    pub entity: Entity,
+    pub name: Option<&'w Name>,
+}
+impl Debug for EntityDebug<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        fmt_entity(self.entity, self.name, f)
+    }
+}
+impl Display for EntityDebug<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl World {
+    /// 实体的名称
+    pub fn entity_name(&self, e: Entity) -> Option<&Name> {
+        self.get_component::<Name>(e).ok()
+    }
+    /// 用于日志打印实体，有名称时一并显示
+    pub fn entity_debug(&self, e: Entity) -> EntityDebug {
+        EntityDebug {
+            entity: e,
+            name: self.entity_name(e),
+        }
+    }
+    /// 按名称查找实体，有多个同名实体时返回其中一个
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        let key = Name::new(name.to_string());
+        match self.name_index() {
+            Some(index) => index.get(&key, self),
+            None => self.scan_names(&key, false).pop(),
+        }
+    }
+    /// 按名称查找所有实体
+    pub fn find_all_by_name(&self, name: &str) -> Vec<Entity> {
+        let key = Name::new(name.to_string());
+        match self.name_index() {
+            Some(index) => index.get_all(&key, self),
+            None => self.scan_names(&key, true),
+        }
+    }
+    /// 注册名称索引，之后按名称查找不再遍历实体
+    pub fn register_name_index(&mut self) {
+        if self.get_single_res::<Index<Name, Name>>().is_none() {
+            self.register_index_map::<Name, Name>(Box::new(NameMap(MultiKeyMap::new(|n: &Name| n.clone()))));
+        }
+    }
+    // 获得同步后的名称索引，索引内部加锁，可通过共享引用同步
+    fn name_index(&self) -> Option<&Index<Name, Name>> {
+        let index: &Index<Name, Name> = self.get_single_res::<Index<Name, Name>>()?;
+        index.sync(self);
+        Some(index)
+    }
+    // 没有名称索引时，遍历有名称的实体
+    fn scan_names(&self, name: &Name, all: bool) -> Vec<Entity> {
+        let mut r = Vec::new();
+        let c = match self.get_column_by_id(&TypeId::of::<Name>()) {
+            Some(c) => c,
+            None => return r,
+        };
+        for ar in self.archetype_arr.iter() {
+            let blob = match c.blob_ref(ar.index()) {
+                Some(blob) => blob,
+                None => continue,
+            };
+            for row in 0..ar.len().index() {
+                let row = Row(row as u32);
+                let e = ar.get_unchecked(row);
+                if !e.is_null() && blob.get::<Name>(row, e) == name {
+                    r.push(e);
+                    if !all {
+                        return r;
+                    }
+                }
+            }
+        }
+        r
+    }
+}
+
+/// 按名称查找实体的参数，名称索引在第一次使用时注册
+pub struct Names<'w>(Indexed<'w, Name, Name>);
+
+impl<'w> Names<'w> {
+    /// 按名称查找实体，有多个同名实体时返回其中一个
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.0.get(&Name::new(name.to_string()))
+    }
+    /// 按名称查找所有实体
+    pub fn get_all(&self, name: &str) -> Vec<Entity> {
+        self.0.get_all(&Name::new(name.to_string()))
+    }
+    /// 有名称的实体数量
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl SystemParam for Names<'_> {
+    type State = <Indexed<'static, Name, Name> as SystemParam>::State;
+    type Item<'w> = Names<'w>;
+
+    fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
+        world.register_name_index();
+        <Indexed<'static, Name, Name> as SystemParam>::init_state(world, meta)
+    }
+    fn align(state: &mut Self::State) {
+        <Indexed<'static, Name, Name> as SystemParam>::align(state);
+    }
+    fn init(state: &mut Self::State) {
+        <Indexed<'static, Name, Name> as SystemParam>::init(state);
+    }
+    fn get_param<'world>(state: &'world mut Self::State) -> Self::Item<'world> {
+        Names(<Indexed<'static, Name, Name> as SystemParam>::get_param(state))
+    }
+    fn get_self<'world>(state: &'world mut Self::State) -> Self {
+        Names(<Indexed<'_, Name, Name> as SystemParam>::get_self(state))
+    }
+}
@@ -0,0 +1,168 @@
+//! 管道，将一个system的输出作为另一个system的输入
+//!
+//! `a.pipe(b)`，b的第一个参数为`In<T>`，T为a的输出类型，类型不匹配时编译失败。
+//! 组合后的system合并a和b的读写关系，a和b依次执行。
+
+use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    system::{IntoSystem, RunSystem, System, SystemMeta, TypeInfo},
+    world::{Tick, World},
+};
+
+/// 管道system的输入参数
+#[derive(Debug)]
+pub struct In<T>(pub T);
+
+impl<T> In<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for In<T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl<T> DerefMut for In<T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// system的输入类型，函数system的第一个参数为[`In<T>`]时，输入类型为T，否则为()
+pub trait SystemInput {
+    type Input: Send + Sync + 'static;
+}
+
+// 取出管道的输入
+pub(crate) fn take_input<T: 'static>(input: Option<Box<dyn Any + Send + Sync>>) -> In<T> {
+    let input = input.expect("In<T> input, no input, the system must be used by pipe");
+    match input.downcast::<T>() {
+        Ok(r) => In(*r),
+        Err(_) => panic!(
+            "In<T> input, input type mismatch, expect: {}",
+            std::any::type_name::<T>()
+        ),
+    }
+}
+
+/// 管道system的标记
+pub struct IsPipe;
+
+/// 管道system，先运行a，再将a的输出作为b的输入，运行b
+pub struct PipeSystem<A, B> {
+    a: A,
+    b: B,
+    system_meta: SystemMeta,
+}
+
+impl<A: RunSystem, B: RunSystem> PipeSystem<A, B>
+where
+    A::Out: Send + Sync + 'static,
+{
+    pub fn new(a: A, b: B) -> Self {
+        let type_info = TypeInfo {
+            type_id: TypeId::of::<Self>(),
+            type_name: Cow::Owned(format!("{} | {}", a.name(), b.name())),
+        };
+        Self {
+            a,
+            b,
+            system_meta: SystemMeta::new(type_info),
+        }
+    }
+}
+
+impl<A: RunSystem, B: RunSystem> System for PipeSystem<A, B>
+where
+    A::Out: Send + Sync + 'static,
+{
+    type Out = B::Out;
+    #[inline]
+    fn name(&self) -> &Cow<'static, str> {
+        &self.system_meta.type_info.type_name
+    }
+    #[inline]
+    fn id(&self) -> TypeId {
+        self.system_meta.type_info.type_id
+    }
+    fn initialize(&mut self, world: &mut World) {
+        self.a.initialize(world);
+        self.b.initialize(world);
+        // 合并a和b的读写关系，供执行图分析依赖
+        let mut meta = SystemMeta::new(self.system_meta.type_info.clone());
+        if let Some(m) = self.a.system_meta() {
+            meta.merge(m);
+        }
+        if let Some(m) = self.b.system_meta() {
+            meta.merge(m);
+        }
+        self.system_meta = meta;
+    }
+    fn align(&mut self, world: &World) {
+        self.a.align(world);
+        self.b.align(world);
+    }
+    #[inline]
+    fn system_meta(&self) -> Option<&SystemMeta> {
+        Some(&self.system_meta)
+    }
+    #[inline]
+    fn system_meta_mut(&mut self) -> Option<&mut SystemMeta> {
+        Some(&mut self.system_meta)
+    }
+    fn check_change_ticks(&mut self, now: Tick) {
+        self.a.check_change_ticks(now);
+        self.b.check_change_ticks(now);
+        self.system_meta.check_change_ticks(now);
+    }
+}
+
+impl<A: RunSystem, B: RunSystem> RunSystem for PipeSystem<A, B>
+where
+    A::Out: Send + Sync + 'static,
+{
+    #[inline]
+    fn run(&mut self, world: &World) -> B::Out {
+        // 管道自身的输入，交给a
+        if let Some(input) = self.system_meta.input.take() {
+            self.a
+                .system_meta_mut()
+                .expect("pipe, the source system has no SystemMeta")
+                .input = Some(input);
+        }
+        let out = self.a.run(world);
+        self.b
+            .system_meta_mut()
+            .expect("pipe, the target system has no SystemMeta")
+            .input = Some(Box::new(out));
+        self.b.run(world)
+    }
+}
+
+impl<A: RunSystem + SystemInput, B: RunSystem> SystemInput for PipeSystem<A, B>
+where
+    A::Out: Send + Sync + 'static,
+{
+    type Input = A::Input;
+}
+
+impl<A: RunSystem, B: RunSystem> IntoSystem<IsPipe, B::Out> for PipeSystem<A, B>
+where
+    A::Out: Send + Sync + 'static,
+{
+    type System = Self;
+    #[inline]
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
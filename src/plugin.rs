@@ -56,6 +56,22 @@ pub trait Plugin: Downcast + Any + Send + Sync {
     fn is_unique(&self) -> bool {
         true
     }
+
+    /// 依赖的插件的名字（见[`plugin_name`]），依赖的插件build后，才会build该插件。
+    /// 第一次运行时，依赖的插件仍未添加，会报告缺少依赖
+    fn dependencies(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// 同一个插件组内，需要在这些插件之后build，插件不存在时忽略
+    fn after(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+}
+
+/// 插件的默认名字，用于声明依赖
+pub fn plugin_name<P: Plugin>() -> &'static str {
+    std::any::type_name::<P>()
 }
 
 // impl_downcast!(Plugin);
@@ -80,6 +96,7 @@ mod sealed {
 
     // use crate::{App, AppError, Plugin, PluginGroup};
     // use pi_world_extend_macro::all_tuples;
+    use crate::app::AppError;
     use crate::plugin_group::WorldPluginExtent;
     use crate::prelude::App;
     use crate::plugin::Plugin;
@@ -92,8 +109,14 @@ mod sealed {
 
     impl<P: Plugin> Plugins<PluginMarker> for P {
         fn add_to_app(self, app: &mut App) {
-            app.add_boxed_plugin(Box::new(self));
-
+            if let Err(AppError::DuplicatePlugin { plugin_name }) =
+                app.try_add_boxed_plugin(Box::new(self))
+            {
+                panic!(
+                    "Error adding plugin {}: plugin was already added in application",
+                    plugin_name
+                );
+            }
         }
     }
 
@@ -122,7 +145,7 @@ mod sealed {
 }
 
 // Dummy plugin used to temporary hold the place in the plugin registry
-// struct PlaceholderPlugin;
-// impl Plugin for PlaceholderPlugin {
-//     fn build(&self, _app: &mut App) {}
-// }
\ No newline at end of file
+pub(crate) struct PlaceholderPlugin;
+impl Plugin for PlaceholderPlugin {
+    fn build(&self, _app: &mut App) {}
+}
\ No newline at end of file
@@ -7,7 +7,8 @@ use std::{
     panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
 };
 
-use crate::plugin::{Plugin, Plugins};
+use crate::app::AppError;
+use crate::plugin::{Plugin, Plugins, PlaceholderPlugin};
 use crate::prelude::App;
 
 /// Combines multiple [`Plugin`]s into a single unit.
@@ -179,31 +180,81 @@ impl PluginGroupBuilder {
     ///
     /// # Panics
     ///
-    /// Panics if one of the plugin in the group was already added to the application.
-    pub fn finish(mut self, app: &mut App) {
+    /// Panics if one of the plugin in the group was already added to the application,
+    /// or if the plugins in the group have a dependency cycle.
+    pub fn finish(self, app: &mut App) {
+        let group_name = self.group_name.clone();
+        if let Err(e) = self.try_finish(app) {
+            panic!("Error adding plugin group {}: {}", group_name, e);
+        }
+    }
+
+    /// 按依赖（[`Plugin::dependencies`]和[`Plugin::after`]）拓扑排序后，依次添加插件。
+    /// 没有依赖关系的插件，保持指定的顺序
+    pub fn try_finish(mut self, app: &mut App) -> Result<(), AppError> {
+        let mut plugins = Vec::with_capacity(self.order.len());
         for ty in &self.order {
             if let Some(entry) = self.plugins.remove(ty) {
                 if entry.enabled {
-                    log::debug!("added plugin: {}", entry.plugin.name());
-                    // if let Err(AppError::DuplicatePlugin { plugin_name }) =
-                    app.add_boxed_plugin(entry.plugin);
-                    // {
-                    //     panic!(
-                    //         "Error adding plugin {} in group {}: plugin was already added in application",
-                    //         plugin_name,
-                    //         self.group_name
-                    //     );
-                    // }
+                    plugins.push(entry.plugin);
                 }
             }
         }
+        for plugin in sort_plugins(plugins)? {
+            log::debug!("added plugin: {}", plugin.name());
+            app.try_add_boxed_plugin(plugin)?;
+        }
+        Ok(())
     }
 }
 
+// 按组内的依赖拓扑排序，组外的依赖由App处理
+fn sort_plugins(plugins: Vec<Box<dyn Plugin>>) -> Result<Vec<Box<dyn Plugin>>, AppError> {
+    let names = plugins.iter().map(|p| p.name().to_string()).collect::<Vec<_>>();
+    // 每个插件在组内的前置插件
+    let befores = plugins
+        .iter()
+        .map(|p| {
+            p.dependencies()
+                .into_iter()
+                .chain(p.after())
+                .filter_map(|d| names.iter().position(|n| n == d))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut done = vec![false; plugins.len()];
+    let mut order = Vec::with_capacity(plugins.len());
+    while order.len() < plugins.len() {
+        // 按原顺序，选择第一个前置插件都已排序的插件
+        match (0..plugins.len()).find(|i| !done[*i] && befores[*i].iter().all(|b| done[*b])) {
+            Some(i) => {
+                done[i] = true;
+                order.push(i);
+            }
+            None => {
+                return Err(AppError::DependencyCycle {
+                    plugins: (0..plugins.len())
+                        .filter(|i| !done[*i])
+                        .map(|i| names[i].clone())
+                        .collect(),
+                })
+            }
+        }
+    }
+    let mut plugins = plugins.into_iter().map(Some).collect::<Vec<_>>();
+    Ok(order
+        .into_iter()
+        .map(|i| plugins[i].take().unwrap())
+        .collect())
+}
+
 pub trait WorldPluginExtent {
     fn add_plugins<M>(&mut self, plugins: impl Plugins<M>) -> &mut Self;
 
     fn add_boxed_plugin(&mut self, plugin: Box<dyn Plugin>) -> &mut Self;
+
+    /// 添加插件并立即build，重复添加唯一插件时返回错误
+    fn try_add_boxed_plugin(&mut self, plugin: Box<dyn Plugin>) -> Result<&mut Self, AppError>;
 }
 
 impl WorldPluginExtent for App {
@@ -213,27 +264,51 @@ impl WorldPluginExtent for App {
     }
 
     fn add_boxed_plugin(&mut self, plugin: Box<dyn Plugin>) -> &mut Self {
-        // log::debug!("added plugin: {}", plugin.name());
-        // if plugin.is_unique() && !self.plugin_name_added.insert(plugin.name().to_string()) {
-        //     Err(AppError::DuplicatePlugin {
-        //         plugin_name: plugin.name().to_string(),
-        //     })?;
-        // }
-
-        // Reserve that position in the plugin registry. if a plugin adds plugins, they will be correctly ordered
-        // let plugin_position_in_registry = self.plugin_registry.len();
-        // self.plugin_registry.push(Box::new(PlaceholderPlugin));
-
-        // self.building_plugin_depth += 1;
-        let result = catch_unwind(AssertUnwindSafe(|| plugin.build(self)));
-        // self.building_plugin_depth -= 1;
-        if let Err(payload) = result {
-            resume_unwind(payload);
+        if let Err(e) = self.try_add_boxed_plugin(plugin) {
+            panic!("Error adding plugin: {}", e);
         }
-        // self.plugin_registry[plugin_position_in_registry] = plugin;
-        // Ok(self)
         self
     }
+
+    fn try_add_boxed_plugin(&mut self, plugin: Box<dyn Plugin>) -> Result<&mut Self, AppError> {
+        log::debug!("added plugin: {}", plugin.name());
+        if plugin.is_unique() && !self.plugin_name_added.insert(plugin.name().to_string()) {
+            return Err(AppError::DuplicatePlugin {
+                plugin_name: plugin.name().to_string(),
+            });
+        }
+
+        // 依赖的插件未build，延迟build
+        if !self.is_plugin_deps_ready(plugin.as_ref()) {
+            self.pending_plugins.push(plugin);
+            return Ok(self);
+        }
+        build_plugin(self, plugin);
+        // 依次build依赖已满足的延迟插件
+        while let Some(i) = self
+            .pending_plugins
+            .iter()
+            .position(|p| self.is_plugin_deps_ready(p.as_ref()))
+        {
+            let plugin = self.pending_plugins.remove(i);
+            build_plugin(self, plugin);
+        }
+        Ok(self)
+    }
+}
+
+fn build_plugin(app: &mut App, plugin: Box<dyn Plugin>) {
+    // Reserve that position in the plugin registry. if a plugin adds plugins, they will be correctly ordered
+    let plugin_position_in_registry = app.plugin_registry.len();
+    app.plugin_registry.push(Box::new(PlaceholderPlugin));
+
+    app.building_plugin_depth += 1;
+    let result = catch_unwind(AssertUnwindSafe(|| plugin.build(app)));
+    app.building_plugin_depth -= 1;
+    if let Err(payload) = result {
+        resume_unwind(payload);
+    }
+    app.plugin_registry[plugin_position_in_registry] = plugin;
 }
 
 /// A plugin group which doesn't do anything. Useful for examples:
@@ -2,14 +2,16 @@
 
 use core::fmt::*;
 use core::result::Result;
+use std::borrow::Cow;
 use std::cell::SyncUnsafeCell;
+use std::marker::PhantomData;
 use std::mem::{transmute, MaybeUninit};
 use std::ops::{Deref, DerefMut};
 
 use crate::archetype::{Archetype, ArchetypeIndex, Row, ShareArchetype};
-use crate::fetch::FetchComponents;
+use crate::fetch::{ChunkFetch, FetchComponents};
 use crate::filter::FilterComponents;
-use crate::system::{relate, Related, SystemMeta};
+use crate::system::{component_name, relate, Related, Relation, SystemMeta};
 use crate::system_params::SystemParam;
 use crate::world::*;
 use crate::world_ptr::Ptr;
@@ -28,6 +30,18 @@ pub enum QueryError {
     NoSuchRow(Row),
     NoSuchRes,
     RepeatAlter,
+    // get_many或get_many_mut时，实体重复
+    AliasedMutability(Entity),
+    // get_single时，没有匹配的实体
+    NoEntities,
+    // get_single时，有多个匹配的实体
+    MultipleEntities,
+    // 派生查询时，组件未注册
+    UnregisteredComponent(Cow<'static, str>),
+    // 派生查询时，父查询没有该组件的读写权限
+    LensNoAccess(Cow<'static, str>),
+    // 派生查询不支持的查询类型
+    LensUnsupported(Cow<'static, str>),
 }
 // // todo 移除
 // pub struct Queryer<'w, Q: FetchComponents + 'static, F: FilterComponents + 'static = ()> {
@@ -141,6 +155,122 @@ impl<'w, Q: FetchComponents, F: FilterComponents> Query<'w, Q, F> {
         unsafe { transmute(r) }
     }
 
+    /// 一次获取多个实体的数据，实体不能重复，否则返回AliasedMutability
+    pub fn get_many<const N: usize>(
+        &self,
+        entities: [Entity; N],
+    ) -> Result<[<<Q as FetchComponents>::ReadOnly as FetchComponents>::Item<'_>; N], QueryError> {
+        verify_distinct(&entities)?;
+        let state = self.state.as_readonly();
+        let mut items: [MaybeUninit<_>; N] = unsafe { MaybeUninit::uninit().assume_init() };
+        for (item, e) in items.iter_mut().zip(entities) {
+            *item = MaybeUninit::new(state.get_by_tick(e)?);
+        }
+        Ok(items.map(|item| unsafe { item.assume_init() }))
+    }
+
+    /// 一次可变获取多个实体的数据，实体不能重复，否则返回AliasedMutability
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> Result<[<Q as FetchComponents>::Item<'_>; N], QueryError> {
+        verify_distinct(&entities)?;
+        let mut items: [MaybeUninit<_>; N] = unsafe { MaybeUninit::uninit().assume_init() };
+        for (item, e) in items.iter_mut().zip(entities) {
+            *item = MaybeUninit::new(self.state.get_by_tick(e)?);
+        }
+        Ok(items.map(|item| unsafe { item.assume_init() }))
+    }
+
+    /// 迭代指定的实体，跳过不匹配的实体
+    pub fn iter_many<I: IntoIterator<Item = Entity>>(
+        &self,
+        entities: I,
+    ) -> QueryManyIter<'_, <Q as FetchComponents>::ReadOnly, F, I::IntoIter> {
+        QueryManyIter {
+            state: self.state.as_readonly(),
+            entities: entities.into_iter(),
+        }
+    }
+
+    /// 迭代K个实体的所有组合，组合内的实体不重复，如K为2时，迭代所有的无序实体对
+    pub fn iter_combinations<const K: usize>(
+        &self,
+    ) -> QueryCombinationIter<'_, <Q as FetchComponents>::ReadOnly, K> {
+        QueryCombinationIter::new(self.state.as_readonly())
+    }
+
+    /// 可变迭代K个实体的所有组合，通过fetch_next获取，保证同时只有一个组合被借用
+    pub fn iter_combinations_mut<const K: usize>(&mut self) -> QueryCombinationIterMut<'_, Q, K> {
+        QueryCombinationIterMut(QueryCombinationIter::new(self.state))
+    }
+
+    /// 按块迭代，每块为同一原型内连续的行，组件以切片的形式返回
+    pub fn iter_chunks(&self) -> QueryChunkIter<'_, <Q as FetchComponents>::ReadOnly, F>
+    where
+        Q::ReadOnly: ChunkFetch,
+    {
+        QueryChunkIter::new(self.state.as_readonly())
+    }
+
+    /// 按块可变迭代，每块为同一原型内连续的行，组件以切片的形式返回
+    pub fn iter_chunks_mut(&mut self) -> QueryChunkIter<'_, Q, F>
+    where
+        Q: ChunkFetch,
+    {
+        QueryChunkIter::new(self.state)
+    }
+
+    /// 从本查询派生一个更窄的查询，复用本查询的原型列表。
+    /// 新查询的读写必须是本查询读写的子集，否则panic
+    pub fn transmute_lens<NQ: FetchComponents + 'static, NF: FilterComponents + 'static>(
+        &mut self,
+    ) -> QueryLens<'_, NQ, NF> {
+        match self.try_transmute_lens() {
+            Ok(r) => r,
+            Err(e) => panic!(
+                "Query<{}, {}>::transmute_lens::<{}, {}> failed, {:?}",
+                std::any::type_name::<Q>(),
+                std::any::type_name::<F>(),
+                std::any::type_name::<NQ>(),
+                std::any::type_name::<NF>(),
+                e
+            ),
+        }
+    }
+    /// 从本查询派生一个更窄的查询，不修改world。
+    /// 组件未注册时返回UnregisteredComponent，读写不是本查询的子集时返回LensNoAccess
+    pub fn try_transmute_lens<NQ: FetchComponents + 'static, NF: FilterComponents + 'static>(
+        &mut self,
+    ) -> Result<QueryLens<'_, NQ, NF>, QueryError> {
+        let world: &World = &self.state.world;
+        let mut related = Related::new();
+        let fetch_state = NQ::get_state(world, &mut related)?;
+        let filter_state = NF::get_state(world, &mut related)?;
+        if let Some(index) = lens_access(&self.state.qstate.related, &related) {
+            return Err(QueryError::LensNoAccess(component_name(world, index)));
+        }
+        let mut qstate = QState::with_related(Share::new(related));
+        for ar in self.state.qstate.archetypes.iter() {
+            qstate.add_archetype(ar, ar.index());
+        }
+        qstate.archetypes_len = self.state.qstate.archetypes_len;
+        Ok(QueryLens {
+            state: QueryState {
+                fetch_state,
+                filter_state,
+                qstate,
+                world: Ptr::new(&mut *self.state.world),
+                system_meta: Ptr::new(&mut *self.state.system_meta),
+                cache_index: SyncUnsafeCell::new(ArchetypeIndex::null()),
+                fetch: SyncUnsafeCell::new(MaybeUninit::uninit()),
+                filter: SyncUnsafeCell::new(MaybeUninit::uninit()),
+                is_match: SyncUnsafeCell::new(false),
+            },
+            _p: PhantomData,
+        })
+    }
+
     pub fn get_by_catch(
         &self,
         e: Entity,
@@ -157,8 +287,10 @@ impl<'w, Q: FetchComponents, F: FilterComponents> Query<'w, Q, F> {
         unsafe { transmute(r) }
     }
 
+    /// 是否没有匹配的实体，会考虑过滤器及被销毁的实体
     pub fn is_empty(&self) -> bool {
-        self.state.is_empty()
+        // 原型都为空时，不需要迭代
+        self.state.is_empty() || self.iter().next().is_none()
     }
 
     pub fn len(&self) -> usize {
@@ -169,6 +301,54 @@ impl<'w, Q: FetchComponents, F: FilterComponents> Query<'w, Q, F> {
         self.state.archetypes_len()
     }
 
+    /// 获取唯一匹配的实体，没有或有多个时返回NoEntities或MultipleEntities
+    pub fn get_single(
+        &self,
+    ) -> Result<<<Q as FetchComponents>::ReadOnly as FetchComponents>::Item<'_>, QueryError> {
+        let mut it = self.iter();
+        match (it.next(), it.next()) {
+            (Some(item), None) => Ok(item),
+            (None, _) => Err(QueryError::NoEntities),
+            _ => Err(QueryError::MultipleEntities),
+        }
+    }
+
+    /// 可变获取唯一匹配的实体，没有或有多个时返回NoEntities或MultipleEntities
+    pub fn get_single_mut(&mut self) -> Result<<Q as FetchComponents>::Item<'_>, QueryError> {
+        let mut it = self.iter_mut();
+        match (it.next(), it.next()) {
+            (Some(item), None) => Ok(item),
+            (None, _) => Err(QueryError::NoEntities),
+            _ => Err(QueryError::MultipleEntities),
+        }
+    }
+
+    /// 获取唯一匹配的实体，没有或有多个时panic
+    pub fn single(&self) -> <<Q as FetchComponents>::ReadOnly as FetchComponents>::Item<'_> {
+        match self.get_single() {
+            Ok(item) => item,
+            Err(err) => panic!(
+                "Query<{}, {}>::single failed: {:?}",
+                std::any::type_name::<Q>(),
+                std::any::type_name::<F>(),
+                err
+            ),
+        }
+    }
+
+    /// 可变获取唯一匹配的实体，没有或有多个时panic
+    pub fn single_mut(&mut self) -> <Q as FetchComponents>::Item<'_> {
+        match self.get_single_mut() {
+            Ok(item) => item,
+            Err(err) => panic!(
+                "Query<{}, {}>::single_mut failed: {:?}",
+                std::any::type_name::<Q>(),
+                std::any::type_name::<F>(),
+                err
+            ),
+        }
+    }
+
     pub fn iter(&self) -> QueryIter<'_, <Q as FetchComponents>::ReadOnly, F> {
         // let state = unsafe {&mut *(self.state as *const QueryState<Q, F> as usize as *mut QueryState<Q, F>)}; // 非安全， 强制可变
         // state.align();
@@ -181,6 +361,75 @@ impl<'w, Q: FetchComponents, F: FilterComponents> Query<'w, Q, F> {
     }
 }
 
+// 检查实体是否重复，重复时返回AliasedMutability
+fn verify_distinct(entities: &[Entity]) -> Result<(), QueryError> {
+    for i in 0..entities.len() {
+        for j in 0..i {
+            if entities[i] == entities[j] {
+                return Err(QueryError::AliasedMutability(entities[i]));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 从Query派生的窄查询，通过query方法使用
+pub struct QueryLens<'w, Q: FetchComponents + 'static, F: FilterComponents + 'static = ()> {
+    state: QueryState<Q, F>,
+    _p: PhantomData<&'w mut ()>,
+}
+
+impl<'w, Q: FetchComponents, F: FilterComponents> QueryLens<'w, Q, F> {
+    pub fn query(&mut self) -> Query<'_, Q, F> {
+        Query::new(&mut self.state)
+    }
+}
+
+// 检查lens的读写是否都在parent的读写内，返回没有权限的组件
+fn lens_access(
+    parent: &Related<ComponentIndex>,
+    lens: &Related<ComponentIndex>,
+) -> Option<ComponentIndex> {
+    let has = |f: &dyn Fn(&Relation<ComponentIndex>) -> bool| parent.vec.iter().any(|r| f(r));
+    for r in lens.vec.iter() {
+        let (ok, index) = match r {
+            Relation::Read(c) | Relation::OptRead(c) => (
+                has(&|r| match r {
+                    Relation::Read(i)
+                    | Relation::OptRead(i)
+                    | Relation::Write(i)
+                    | Relation::OptWrite(i)
+                    | Relation::ShareWrite(i) => i == c,
+                    Relation::ReadAll | Relation::WriteAll => true,
+                    _ => false,
+                }),
+                *c,
+            ),
+            Relation::Write(c) | Relation::OptWrite(c) | Relation::ShareWrite(c) => (
+                has(&|r| match r {
+                    Relation::Write(i) | Relation::OptWrite(i) | Relation::ShareWrite(i) => i == c,
+                    Relation::WriteAll => true,
+                    _ => false,
+                }),
+                *c,
+            ),
+            Relation::ReadAll => (
+                has(&|r| matches!(r, Relation::ReadAll | Relation::WriteAll)),
+                ComponentIndex::null(),
+            ),
+            Relation::WriteAll => (
+                has(&|r| matches!(r, Relation::WriteAll)),
+                ComponentIndex::null(),
+            ),
+            _ => (true, ComponentIndex::null()),
+        };
+        if !ok {
+            return Some(index);
+        }
+    }
+    None
+}
+
 // pub type QueryRef
 
 impl<'a, Q: FetchComponents + 'static, F: FilterComponents + Send + Sync> SystemParam
@@ -489,7 +738,9 @@ impl<Q: FetchComponents, F: FilterComponents> QueryState<Q, F> {
             unsafe { *self.cache_index.get() = archetype_index };
         };
         // let (fetch, filter) = unsafe { (&*self.fetch_filter.get()).assume_init_ref() };
-        if F::filter(unsafe { (&*self.filter.get()).assume_init_ref() }, addr.row, e) {
+        if F::filter(unsafe { (&*self.filter.get()).assume_init_ref() }, addr.row, e)
+            || Q::skip(unsafe { (&*self.fetch.get()).assume_init_ref() }, addr.row, e)
+        {
             return Err(QueryError::NoMatchEntity(e));
         }
         Ok(unsafe {transmute(Q::fetch( (&*self.fetch.get()).assume_init_ref() , addr.row, e))})
@@ -541,7 +792,7 @@ impl<Q: FetchComponents, F: FilterComponents> QueryState<Q, F> {
             // unsafe { (&mut *self.fetch_filter.get()).write(transmute((fetch, filter))) };
         // };
         // let (fetch, filter) = unsafe { (&*self.fetch_filter.get()).assume_init_ref() };
-        if F::filter(&filter, addr.row, e) {
+        if F::filter(&filter, addr.row, e) || Q::skip(&fetch, addr.row, e) {
             return Err(QueryError::NoMatchEntity(e));
         }
         Ok(unsafe {transmute(Q::fetch(&fetch, addr.row, e))})
@@ -664,7 +915,10 @@ pub struct QState {
 
 impl QState {
     pub fn new(system_meta: &mut SystemMeta) -> Self {
-        let related = system_meta.related_ok();
+        Self::with_related(system_meta.related_ok())
+    }
+    /// 用已有的读写关系创建，不记录到SystemMeta上
+    pub fn with_related(related: Share<Related<ComponentIndex>>) -> Self {
         Self {
             // id,
             related,
@@ -833,7 +1087,9 @@ impl<'w, Q: FetchComponents, F: FilterComponents> QueryIter<'w, Q, F> {
 
             if iscontinue {
                 if !self.e.is_null() {
-                    if F::filter(unsafe { self.filter.assume_init_ref() }, self.row, self.e) == false {
+                    if F::filter(unsafe { self.filter.assume_init_ref() }, self.row, self.e) == false
+                        && Q::skip(unsafe { self.fetch.assume_init_ref() }, self.row, self.e) == false
+                    {
                         let item = Q::fetch(unsafe { self.fetch.assume_init_ref() }, self.row, self.e);
                         return Some(item);
                     }
@@ -870,3 +1126,300 @@ impl<'w, Q: FetchComponents, F: FilterComponents> Iterator for QueryIter<'w, Q,
         self.size_hint_normal()
     }
 }
+
+/// 迭代指定的实体，跳过不存在或不匹配查询的实体
+pub struct QueryManyIter<'w, Q: FetchComponents + 'static, F: FilterComponents + 'static, I: Iterator<Item = Entity>> {
+    state: &'w QueryState<Q, F>,
+    entities: I,
+}
+
+impl<'w, Q: FetchComponents, F: FilterComponents, I: Iterator<Item = Entity>> Iterator
+    for QueryManyIter<'w, Q, F, I>
+{
+    type Item = Q::Item<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let e = self.entities.next()?;
+            if let Ok(item) = self.state.get_by_tick_catch(e) {
+                return Some(item);
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.entities.size_hint().1)
+    }
+}
+
+/// K个实体的组合迭代器
+pub struct QueryCombinationIter<'w, Q: FetchComponents + 'static, const K: usize> {
+    // 有实体的原型的fetch
+    fetchs: Vec<Q::Fetch<'w>>,
+    // 通过过滤的实体，及其fetch的位置和行
+    entries: Vec<(usize, Row, Entity)>,
+    // 当前组合在entries上的位置，递增排列
+    cursors: [usize; K],
+    started: bool,
+}
+
+impl<'w, Q: FetchComponents, const K: usize> QueryCombinationIter<'w, Q, K> {
+    pub fn new<F: FilterComponents>(state: &'w QueryState<Q, F>) -> Self {
+        let mut fetchs = Vec::new();
+        let mut entries = Vec::new();
+        for ar in state.archetypes.iter() {
+            let len = ar.len();
+            if len.0 == 0 {
+                continue;
+            }
+            let filter = F::init_filter(
+                &state.world,
+                &state.filter_state,
+                ar.index(),
+                state.system_meta.this_run,
+                state.system_meta.last_run,
+            );
+            let fetch = Q::init_fetch(
+                &state.world,
+                &state.fetch_state,
+                ar.index(),
+                state.system_meta.this_run,
+                state.system_meta.last_run,
+            );
+            let index = fetchs.len();
+            for row in 0..len.0 {
+                let row = Row(row);
+                let e = ar.get_unchecked(row);
+                if e.is_null() || F::filter(&filter, row, e) || Q::skip(&fetch, row, e) {
+                    continue;
+                }
+                entries.push((index, row, e));
+            }
+            if entries.last().map_or(true, |r| r.0 != index) {
+                continue;
+            }
+            fetchs.push(fetch);
+        }
+        let mut cursors = [0; K];
+        for (i, c) in cursors.iter_mut().enumerate() {
+            *c = i;
+        }
+        Self {
+            fetchs,
+            entries,
+            cursors,
+            started: false,
+        }
+    }
+    // 移动到下一个组合，返回是否还有组合
+    fn next_cursors(&mut self) -> bool {
+        let len = self.entries.len();
+        if K == 0 || len < K {
+            return false;
+        }
+        if !self.started {
+            self.started = true;
+            return true;
+        }
+        // 从后往前找到可以递增的位置，其后的位置依次排列
+        let mut i = K;
+        while i > 0 {
+            i -= 1;
+            if self.cursors[i] < len - K + i {
+                self.cursors[i] += 1;
+                for j in i + 1..K {
+                    self.cursors[j] = self.cursors[j - 1] + 1;
+                }
+                return true;
+            }
+        }
+        false
+    }
+    fn items(&self) -> [Q::Item<'w>; K] {
+        self.cursors.map(|i| {
+            let (index, row, e) = unsafe { *self.entries.get_unchecked(i) };
+            Q::fetch(unsafe { self.fetchs.get_unchecked(index) }, row, e)
+        })
+    }
+    // 剩余的组合数量
+    fn remaining(&self) -> Option<usize> {
+        let n = self.entries.len();
+        if K == 0 || n < K {
+            return Some(0);
+        }
+        // 当前组合之后的组合数量，按组合数系统计算：C(n, K) - 1 - rank
+        let mut total = binomial(n, K)?;
+        if self.started {
+            let mut rank = 0usize;
+            let mut prev = 0;
+            for (i, &c) in self.cursors.iter().enumerate() {
+                for v in prev..c {
+                    rank = rank.checked_add(binomial(n - v - 1, K - i - 1)?)?;
+                }
+                prev = c + 1;
+            }
+            total -= rank + 1;
+        }
+        Some(total)
+    }
+}
+
+// 组合数，溢出时返回None
+fn binomial(n: usize, k: usize) -> Option<usize> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    let mut r: usize = 1;
+    for i in 0..k {
+        r = r.checked_mul(n - i)? / (i + 1);
+    }
+    Some(r)
+}
+
+impl<'w, Q: FetchComponents, const K: usize> Iterator for QueryCombinationIter<'w, Q, K> {
+    type Item = [Q::Item<'w>; K];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_cursors() {
+            Some(self.items())
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining() {
+            Some(n) => (n, Some(n)),
+            None => (usize::MAX, None),
+        }
+    }
+}
+
+/// K个实体的可变组合迭代器，每次fetch_next返回的组合，在下次调用前必须释放
+pub struct QueryCombinationIterMut<'w, Q: FetchComponents + 'static, const K: usize>(
+    QueryCombinationIter<'w, Q, K>,
+);
+
+impl<'w, Q: FetchComponents, const K: usize> QueryCombinationIterMut<'w, Q, K> {
+    pub fn fetch_next(&mut self) -> Option<[Q::Item<'_>; K]> {
+        if self.0.next_cursors() {
+            Some(unsafe { transmute(self.0.items()) })
+        } else {
+            None
+        }
+    }
+}
+
+/// 查询的一块数据，为同一原型内连续的行
+pub struct QueryChunk<'w, C> {
+    pub data: C,
+    ar: &'w Archetype,
+    start: Row,
+    end: Row,
+}
+
+impl<'w, C> QueryChunk<'w, C> {
+    pub fn len(&self) -> usize {
+        self.end.index() - self.start.index()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.start.0 >= self.end.0
+    }
+    pub fn archetype_index(&self) -> ArchetypeIndex {
+        self.ar.index()
+    }
+    /// 块内第i个实体
+    pub fn entity(&self, i: usize) -> Entity {
+        assert!(i < self.len());
+        self.ar.get_unchecked(Row(self.start.0 + i as u32))
+    }
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + 'w {
+        let ar = self.ar;
+        (self.start.0..self.end.0).map(move |row| ar.get_unchecked(Row(row)))
+    }
+}
+
+/// 按块迭代查询，块在存储连续、实体有效且通过过滤的位置切分
+pub struct QueryChunkIter<'w, Q: ChunkFetch + 'static, F: FilterComponents + 'static> {
+    state: &'w QueryState<Q, F>,
+    ar_index: usize,
+    ar: &'w Archetype,
+    fetch: Option<Q::Fetch<'w>>,
+    filter: Option<F::Filter<'w>>,
+    row: Row,
+    len: Row,
+}
+
+impl<'w, Q: ChunkFetch, F: FilterComponents> QueryChunkIter<'w, Q, F> {
+    pub fn new(state: &'w QueryState<Q, F>) -> Self {
+        QueryChunkIter {
+            state,
+            ar_index: 0,
+            ar: state.world.empty_archetype(),
+            fetch: None,
+            filter: None,
+            row: Row(0),
+            len: Row(0),
+        }
+    }
+    // 行是否可以迭代
+    fn is_valid(&self, fetch: &Q::Fetch<'w>, filter: &F::Filter<'w>, row: Row) -> bool {
+        let e = self.ar.get_unchecked(row);
+        !e.is_null() && !F::filter(filter, row, e) && !Q::skip(fetch, row, e)
+    }
+}
+
+impl<'w, Q: ChunkFetch, F: FilterComponents> Iterator for QueryChunkIter<'w, Q, F> {
+    type Item = QueryChunk<'w, Q::Chunk<'w>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.row.0 < self.len.0 {
+                let (fetch, filter) = match (&self.fetch, &self.filter) {
+                    (Some(fetch), Some(filter)) => (fetch, filter),
+                    _ => unreachable!(),
+                };
+                if !self.is_valid(fetch, filter, self.row) {
+                    self.row.0 += 1;
+                    continue;
+                }
+                let start = self.row;
+                let end = Q::chunk_end(fetch, self.ar, start, self.len);
+                let mut row = Row(start.0 + 1);
+                while row.0 < end.0 && self.is_valid(fetch, filter, row) {
+                    row.0 += 1;
+                }
+                self.row = row;
+                return Some(QueryChunk {
+                    data: Q::fetch_chunk(fetch, self.ar, start, row),
+                    ar: self.ar,
+                    start,
+                    end: row,
+                });
+            }
+            // 下一个原型
+            if self.ar_index >= self.state.archetypes.len() {
+                return None;
+            }
+            self.ar = unsafe { self.state.archetypes.get_unchecked(self.ar_index) };
+            self.ar_index += 1;
+            self.row = Row(0);
+            self.len = self.ar.len();
+            if self.len.0 > 0 {
+                self.fetch = Some(Q::init_fetch(
+                    &self.state.world,
+                    &self.state.fetch_state,
+                    self.ar.index(),
+                    self.state.system_meta.this_run,
+                    self.state.system_meta.last_run,
+                ));
+                self.filter = Some(F::init_filter(
+                    &self.state.world,
+                    &self.state.filter_state,
+                    self.ar.index(),
+                    self.state.system_meta.this_run,
+                    self.state.system_meta.last_run,
+                ));
+            }
+        }
+    }
+}
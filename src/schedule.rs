@@ -5,11 +5,11 @@ use crate as pi_world;
 ///
 use crate::{
     archetype::Row,
-    exec_graph::{ExecGraph, ExecSystem, NodeIndex},
+    exec_graph::{Ambiguity, CycleError, EdgeKind, EdgeOrigin, ExecGraph, ExecSystem, NodeIndex},
     schedule_config::{
         BaseConfig, NodeType, ScheduleLabel, SetConfig, StageLabel, SystemConfig, SystemSet,
     },
-    system::BoxedSystem,
+    system::{AccessConflict, BoxedSystem},
     world::*,
 };
 use bevy_utils::intern::Interned;
@@ -36,6 +36,94 @@ pub struct Schedule {
     add_listener: bool,
 
     dirty_mark: bool,
+    // 歧义检查的处理方式
+    ambiguity_detection: AmbiguityDetection,
+    // 构建失败，但还未报告的错误
+    fatal_error: Option<ScheduleBuildError>,
+    // 构建失败，日程无法运行
+    build_failed: bool,
+    // 增量整理的预算，None表示每次全部整理
+    settle_budget: Option<usize>,
+    // 上次钳制system的last_run时的tick
+    last_check_tick: Tick,
+}
+
+/// 日程构建错误，包含构建时发现的所有问题
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleBuildError {
+    /// 图中的环
+    pub cycles: Vec<CycleError>,
+    /// system内参数之间的读写冲突
+    pub conflicts: Vec<AccessConflict>,
+    /// 歧义，仅在AmbiguityDetection::Error时记录
+    pub ambiguities: Vec<Ambiguity>,
+}
+impl ScheduleBuildError {
+    pub fn is_empty(&self) -> bool {
+        self.cycles.is_empty() && self.conflicts.is_empty() && self.ambiguities.is_empty()
+    }
+    /// 有环、system内读写冲突或歧义时，日程无法运行
+    /// system内的读写冲突会产生别名的可变引用，同样不能运行
+    pub fn is_fatal(&self) -> bool {
+        !self.is_empty()
+    }
+    /// 合并另一个错误
+    pub fn extend(&mut self, other: ScheduleBuildError) {
+        self.cycles.extend(other.cycles);
+        self.conflicts.extend(other.conflicts);
+        self.ambiguities.extend(other.ambiguities);
+    }
+}
+impl std::fmt::Display for ScheduleBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "schedule build error:")?;
+        for c in self.cycles.iter() {
+            writeln!(f, "  cycle, {}", c)?;
+        }
+        for c in self.conflicts.iter() {
+            writeln!(f, "  conflict, {}", c)?;
+        }
+        for a in self.ambiguities.iter() {
+            writeln!(f, "  ambiguity, {}", a)?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for ScheduleBuildError {}
+
+/// 歧义检查的处理方式，在日程初始化时检查
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmbiguityDetection {
+    /// 不检查
+    #[default]
+    Ignore,
+    /// 输出警告日志
+    Warn,
+    /// 有歧义则panic
+    Error,
+}
+
+/// 歧义报告，列出所有读写冲突但没有指定先后顺序的system对
+#[derive(Debug, Clone, Default)]
+pub struct AmbiguityReport {
+    pub ambiguities: Vec<Ambiguity>,
+}
+impl AmbiguityReport {
+    pub fn is_empty(&self) -> bool {
+        self.ambiguities.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.ambiguities.len()
+    }
+}
+impl std::fmt::Display for AmbiguityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} ambiguities:", self.ambiguities.len())?;
+        for a in self.ambiguities.iter() {
+            writeln!(f, "  {}", a)?;
+        }
+        Ok(())
+    }
 }
 
 impl Schedule {
@@ -68,9 +156,52 @@ impl Schedule {
             },
             add_listener,
             dirty_mark: false,
+            ambiguity_detection: AmbiguityDetection::Ignore,
+            fatal_error: None,
+            build_failed: false,
+            settle_budget: None,
+            last_check_tick: Tick::from(1u32),
+        }
+    }
+
+    /// 设置歧义检查的处理方式
+    pub fn set_ambiguity_detection(&mut self, detection: AmbiguityDetection) {
+        self.ambiguity_detection = detection;
+    }
+
+    /// 设置主调度后整理world的预算，Some(budget)表示使用World::settle_incremental增量整理，None表示全部整理
+    pub fn set_settle_budget(&mut self, budget: Option<usize>) {
+        self.settle_budget = budget;
+    }
+    /// 主调度后整理world
+    fn settle_world(&mut self, world: &mut World) {
+        match self.settle_budget {
+            Some(budget) => {
+                world.settle_incremental_by(budget, &mut self.action, &mut self.set);
+            }
+            None => world.settle_by(&mut self.action, &mut self.set),
         }
     }
 
+    /// 获得歧义报告，如果日程未初始化，会先初始化
+    pub fn ambiguity_report(&mut self, world: &mut World) -> AmbiguityReport {
+        // 构建错误留到运行时报告
+        if let Err(e) = self.initialize(world) {
+            self.fatal_error = Some(e);
+        }
+        self.check_ambiguity(world)
+    }
+
+    fn check_ambiguity(&self, world: &World) -> AmbiguityReport {
+        let mut ambiguities = Vec::new();
+        for (_, schedule) in self.schedule_graph.iter() {
+            for (_, stage) in schedule.iter() {
+                ambiguities.extend(stage.check_ambiguity(&self.systems, world));
+            }
+        }
+        AmbiguityReport { ambiguities }
+    }
+
     /// 在某个阶段之后，添加一个阶段
     pub fn add_stage_after(
         &mut self,
@@ -151,6 +282,7 @@ impl Schedule {
     ) {
         // println!("run:{:?}", (schedule, self.schedule_graph.get_mut(schedule).is_some(), self.schedule_graph.len()));
         self.try_initialize(world);
+        self.try_check_change_ticks(world);
 
         let g = match self.schedule_graph.get_mut(schedule) {
             Some(r) => r,
@@ -166,15 +298,47 @@ impl Schedule {
         // 按顺序运行stage
         for stage in self.stage_sort.iter() {
             if let Some(stage) = g.get_mut(stage) {
-                 
+                // 同步组件索引，本阶段的system只读索引
+                world.sync_indexes();
                 Self::run_graph(world, rt, stage, &self.systems, &self.set_conditions);
+                // 运行本阶段添加或移除组件的钩子
+                world.run_hooks();
             }
         }
 
+        // 运行Commands::run_system放入队列的一次性system
+        world.run_queued_systems();
+        // 定期钳制过旧的tick，避免tick回绕
+        if world.need_check_change_ticks() {
+            world.check_change_ticks();
+        }
+
         #[cfg(feature = "trace")]
         let settle_by = tracing::warn_span!("settle_by").entered();
         if schedule == &MainSchedule.intern() {
-            world.settle_by(&mut self.action, &mut self.set);
+            self.settle_world(world);
+        }
+    }
+    // 距离本日程上次检查超过CHECK_TICK_THRESHOLD时，钳制system的last_run。
+    // 每个日程独立检查，长时间未运行的日程在运行前也会被钳制
+    fn try_check_change_ticks(&mut self, world: &World) {
+        let now = world.tick();
+        if now.wrapping_sub(*self.last_check_tick) >= CHECK_TICK_THRESHOLD {
+            self.check_change_ticks(now);
+        }
+    }
+    /// 钳制所有system及条件过旧的last_run
+    pub fn check_change_ticks(&mut self, now: Tick) {
+        self.last_check_tick = now;
+        for i in 0..self.systems.len() {
+            let s = unsafe { self.systems.load_unchecked(i) };
+            s.system.check_change_ticks(now);
+            for c in s.conditions.iter_mut() {
+                c.check_change_ticks(now);
+            }
+        }
+        for i in 0..self.set_conditions.len() {
+            unsafe { self.set_conditions.load_unchecked(i) }.check_change_ticks(now);
         }
     }
     fn run_graph<A: AsyncRuntime + AsyncRuntimeExt>(
@@ -216,18 +380,29 @@ impl Schedule {
         schedule: &Interned<dyn ScheduleLabel>,
     ) {
         self.try_initialize(world);
+        self.try_check_change_ticks(world);
 
         // println!("async_run_stage, stage:{:?}", stage);
         let g = self.schedule_graph.get_mut(schedule).unwrap();
         // 按顺序运行stage
         for stage in self.stage_sort.iter() {
             if let Some(stage) = g.get_mut(stage) {
+                // 同步组件索引，本阶段的system只读索引
+                world.sync_indexes();
                 Self::async_run_graph(world, rt, stage, &mut self.systems, &mut self.set_conditions).await;
+                // 运行本阶段添加或移除组件的钩子
+                world.run_hooks();
             }
         }
+        // 运行Commands::run_system放入队列的一次性system
+        world.run_queued_systems();
+        // 定期钳制过旧的tick，避免tick回绕
+        if world.need_check_change_ticks() {
+            world.check_change_ticks();
+        }
 
         if schedule == &MainSchedule.intern() {
-            world.settle_by(&mut self.action, &mut self.set);
+            self.settle_world(world);
         }
     }
     async fn async_run_graph<A: AsyncRuntime + AsyncRuntimeExt>(
@@ -245,10 +420,26 @@ impl Schedule {
         g.settle();
     }
 
+    // 运行前初始化，构建失败时panic，错误报告后被清除，之后的运行只提示构建失败
     fn try_initialize(&mut self, world: &mut World) {
+        if let Err(e) = self.initialize(world) {
+            self.fatal_error = None;
+            panic!("{}", e);
+        }
+        if let Some(e) = self.fatal_error.take() {
+            panic!("{}", e);
+        }
+        if self.build_failed {
+            panic!("schedule build failed, the error has been reported");
+        }
+    }
+
+    /// 初始化日程，返回构建时发现的所有问题
+    pub fn initialize(&mut self, world: &mut World) -> Result<(), ScheduleBuildError> {
         if self.system_configs.is_empty() {
-            return;
+            return Ok(());
         }
+        let sys_start = self.systems.len();
 
         let mut temp_map = HashMap::default();
         // 用于关联一个集中直接连接的system的id
@@ -302,17 +493,43 @@ impl Schedule {
         //     sys.initialize(world);
         // }
 
+        let mut error = ScheduleBuildError::default();
         // 初始化图
         for (_name, schedule) in self.schedule_graph.iter_mut() {
             // println!("stage:{:?} initialize", name);
             for (_, stage) in schedule.iter_mut() {
-                stage.initialize(self.systems.clone(), self.set_conditions.clone(), world, self.add_listener);
+                if let Err(e) = stage.initialize(self.systems.clone(), self.set_conditions.clone(), world, self.add_listener) {
+                    error.cycles.push(e);
+                }
+            }
+        }
+        // 检查新增system内参数的读写冲突
+        for i in sys_start..self.systems.len() {
+            let sys = unsafe { self.systems.load_unchecked(i) };
+            for meta in sys.metas() {
+                error.conflicts.extend(meta.check_conflict(world));
             }
         }
 
         // println!("schedule initialize");
 
+        if error.cycles.is_empty() && self.ambiguity_detection != AmbiguityDetection::Ignore {
+            let report = self.check_ambiguity(world);
+            if !report.is_empty() {
+                if self.ambiguity_detection == AmbiguityDetection::Error {
+                    error.ambiguities = report.ambiguities;
+                } else {
+                    log::warn!("schedule ambiguity, {}", report);
+                }
+            }
+        }
+
         self.dirty_mark = false;
+        if error.is_empty() {
+            return Ok(());
+        }
+        self.build_failed = true;
+        Err(error)
     }
 
     fn add_system_config(
@@ -580,7 +797,7 @@ impl Schedule {
                 };
                 let before_set_index = graph.add_set(start, end, format!("{:?}_before", set).into());
                 let after_set_index = graph.add_set(0, 0, format!("{:?}_after", set).into());
-                graph.add_edge(before_set_index, after_set_index);
+                graph.add_edge(before_set_index, after_set_index, EdgeOrigin::new(EdgeKind::SetBounds, format!("{:?}", set).into()));
                 r.insert(((before_set_index, false), (after_set_index, false)))
             }
         };
@@ -591,7 +808,7 @@ impl Schedule {
                 if let Some(m) = map3.get(&set) {
                     for i in m.iter() {
                         if let Some(i) = map2.get(i) {
-                            graph.add_edge(r.0, *i);
+                            graph.add_edge(r.0, *i, EdgeOrigin::new(EdgeKind::InSet, format!("{:?}", set).into()));
                         }
                     }
                 }
@@ -604,7 +821,7 @@ impl Schedule {
                 if let Some(m) = map3.get(&set) {
                     for i in m.iter() {
                         if let Some(i) = map2.get(i) {
-                            graph.add_edge(*i, r.0);
+                            graph.add_edge(*i, r.0, EdgeOrigin::new(EdgeKind::InSet, format!("{:?}", set).into()));
                         }
                     }
                 }
@@ -651,6 +868,7 @@ impl Schedule {
 
                 // 添加该节点与其他节点的顺序关系
                 let node_index = map.0.get(&system_type_id).unwrap().clone();
+                let system_name = stage.node_name(node_index);
                 if before.len() > 0 {
                     for before in before.iter() {
                         let before_index = match before {
@@ -668,7 +886,7 @@ impl Schedule {
                                 None => continue,
                             },
                         };
-                        stage.add_edge(node_index, before_index);
+                        stage.add_edge(node_index, before_index, EdgeOrigin::new(EdgeKind::Before, system_name.clone()));
                     }
                 }
 
@@ -689,7 +907,7 @@ impl Schedule {
                                 None => continue,
                             },
                         };
-                        stage.add_edge(after_index, node_index);
+                        stage.add_edge(after_index, node_index, EdgeOrigin::new(EdgeKind::After, system_name.clone()));
                     }
                 }
             }
@@ -755,6 +973,7 @@ impl Schedule {
                         stage,
                     );
 
+                    let set_name: Cow<'static, str> = format!("{:?}", set).into();
                     for in_set in config.sets.iter() {
                         let in_set_before = Self::get_set_node(
                             in_set.clone(),
@@ -774,8 +993,8 @@ impl Schedule {
                             temp_set_condition_index,
                             stage,
                         );
-                        stage.add_edge(in_set_before, set_before);
-                        stage.add_edge(set_after, in_set_after);
+                        stage.add_edge(in_set_before, set_before, EdgeOrigin::new(EdgeKind::InSet, set_name.clone()));
+                        stage.add_edge(set_after, in_set_after, EdgeOrigin::new(EdgeKind::InSet, set_name.clone()));
                     }
 
                     if config.before.len() > 0 {
@@ -792,7 +1011,7 @@ impl Schedule {
                                 ),
                                 NodeType::System(r) => map.0.get(r).unwrap().clone(),
                             };
-                            stage.add_edge(set_after, before_index);
+                            stage.add_edge(set_after, before_index, EdgeOrigin::new(EdgeKind::Before, set_name.clone()));
                         }
                     }
 
@@ -813,7 +1032,7 @@ impl Schedule {
                                     None => continue,
                                 },
                             };
-                            stage.add_edge(after_index, set_before);
+                            stage.add_edge(after_index, set_before, EdgeOrigin::new(EdgeKind::After, set_name.clone()));
                         }
                     }
                 }
@@ -2,7 +2,7 @@ use std::{any::TypeId, hash::Hash};
 
 use bevy_utils::{define_label, intern::Interned};
 
-use crate::system::{BoxedSystem, IntoSystem};
+use crate::{condition::{AndThen, OrElse}, pipe::SystemInput, system::{BoxedSystem, IntoSystem}};
 pub use bevy_utils::label::{DynEq, DynHash};
 pub use pi_world_macros::{ScheduleLabel, StageLabel, SystemSet};
 
@@ -120,6 +120,13 @@ impl<T: SystemSet> IntoSystemSetConfigs for T {
 
 
 /****************************系统配置********************************/
+/// 可添加到调度中的system，system的输入必须为()，第一个参数为`In<T>`的system只能用在管道中：
+/// ```compile_fail
+/// use pi_world::prelude::*;
+/// fn b(_input: In<u32>) {}
+/// let mut app = App::new();
+/// app.add_system(Update, b);
+/// ```
 pub trait IntoSystemConfigs<Marker>
 where
     Self: Sized,
@@ -149,11 +156,27 @@ where
     }
 }
 
+/// 运行条件，条件的输入必须为()：
+/// ```compile_fail
+/// use pi_world::prelude::*;
+/// fn a() {}
+/// fn cond(_input: In<u32>) -> bool { true }
+/// let mut app = App::new();
+/// app.add_system(Update, a.run_if(cond));
+/// ```
 pub trait Condition<Marker>: IntoSystem<Marker, bool>
 where
     Self: Sized,
 {
+    /// 与条件，self不成立时，不再运行and条件
+    fn and_then<M, C: Condition<M>>(self, and: C) -> AndThen<Self::System, C::System> {
+        AndThen::new(self.into_system(), and.into_system())
+    }
 
+    /// 或条件，self成立时，不再运行or条件
+    fn or_else<M, C: Condition<M>>(self, or: C) -> OrElse<Self::System, C::System> {
+        OrElse::new(self.into_system(), or.into_system())
+    }
 }
 
 
@@ -194,7 +217,10 @@ impl IntoSystemConfigs<()> for SystemConfig {
     }
 }
 
-impl<Marker, T: IntoSystem<Marker, ()>> IntoSystemConfigs<Marker> for T  {
+impl<Marker, T: IntoSystem<Marker, ()>> IntoSystemConfigs<Marker> for T
+where
+    T::System: SystemInput<Input = ()>,
+{
     fn into_configs(self) -> SystemConfig {
         SystemConfig {
             system: BoxedSystem::Sync(Box::new(self.into_system())),
@@ -209,7 +235,7 @@ impl<Marker, T: IntoSystem<Marker, ()>> IntoSystemConfigs<Marker> for T  {
     }
 }
 
-impl<Marker, T: IntoSystem<Marker, bool>> Condition<Marker> for T  {}
+impl<Marker, T: IntoSystem<Marker, bool>> Condition<Marker> for T where T::System: SystemInput<Input = ()> {}
 
 impl<Marker, T: IntoSystem<Marker, ()> + 'static> IntoNodeType<(usize, Marker)> for T {
     fn into_node_type(self) -> NodeType {
@@ -1,18 +1,50 @@
 //! 单例资源， 先system依次写，然后多system并行读
 
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::marker::PhantomData;
-use std::mem::transmute;
+use std::mem::{size_of, transmute};
 use std::ops::{Deref, DerefMut};
 
 use pi_share::Share;
 
+use crate::memory::{ResMemSize, ResourceMemory};
 use crate::system::{Relation, SystemMeta, TypeInfo};
 use crate::system_params::SystemParam;
 use crate::world::{Downcast, Tick, TickMut, World};
 use crate::world_ptr::Ptr;
 
+pub use pi_world_macros::Resource;
+
+/// 单例资源，`SingleRes`、`SingleResMut`及`World::insert_single_res`要求资源实现该trait
+///
+/// 一般用`#[derive(Resource)]`实现，`#[resource(from_world)]`表示系统初始化时，
+/// 如果资源还未插入，则用`FromWorld`创建。派生时会检查类型不是组件(实现了Bundle)
+///
+/// 组件不能作为单例资源：
+/// ```compile_fail
+/// use pi_world::prelude::*;
+/// #[derive(Component)]
+/// pub struct Pos(pub f32);
+/// fn read(_pos: SingleRes<Pos>) {}
+/// ```
+/// 同时派生组件和资源也会编译失败：
+/// ```compile_fail
+/// use pi_world::prelude::*;
+/// #[derive(Component, Resource)]
+/// pub struct Pos(pub f32);
+/// ```
+pub trait Resource: Send + Sync + 'static {
+    /// 系统初始化时资源不存在，用该方法创建资源，返回None表示不创建
+    fn init_res(_world: &mut World) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct TickRes<T> {
     pub(crate) res: T,
@@ -42,6 +74,14 @@ impl<T: 'static> TickMut for TickRes<T> {
     fn set_tick(&mut self, tick: Tick) {
         self.changed_tick = tick;
     }
+    fn mem_info(&self) -> ResourceMemory {
+        let deep = self.res.res_mem_size();
+        ResourceMemory {
+            name: Cow::Borrowed(std::any::type_name::<T>()),
+            size: deep.unwrap_or(size_of::<T>()),
+            deep: deep.is_some(),
+        }
+    }
 }
 impl<T: 'static> TickRes<T> {
     pub fn new(res: T) -> Self {
@@ -65,17 +105,17 @@ impl<T: 'static> DerefMut for TickRes<T> {
 }
 
 // pub type SingleRes<'w, T> = &'w mut SingleRes<'w, T>;
-pub struct SingleRes<'w, T: 'static + Send + Sync> {
+pub struct SingleRes<'w, T: Resource> {
     pub(crate) state: &'w ResState<T>,
 }
-impl<T: 'static + Debug + Send + Sync> Debug for SingleRes<'_, T> {
+impl<T: Resource + Debug> Debug for SingleRes<'_, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SingleRes").field("value", &**self).finish()
     }
 }
-unsafe impl<T: 'static + Send + Sync> Send for SingleRes<'_, T> {}
-unsafe impl<T: 'static + Send + Sync> Sync for SingleRes<'_, T> {}
-impl<'w, T: 'static + Send + Sync> SingleRes<'w, T> {
+unsafe impl<T: Resource> Send for SingleRes<'_, T> {}
+unsafe impl<T: Resource> Sync for SingleRes<'_, T> {}
+impl<'w, T: Resource> SingleRes<'w, T> {
     pub(crate) fn new(state: &'w ResState<T>) -> Self {
         SingleRes { state }
     }
@@ -85,7 +125,7 @@ impl<'w, T: 'static + Send + Sync> SingleRes<'w, T> {
     }
 
     pub fn is_changed(&self) -> bool {
-        unsafe {&*self.state.value}.changed_tick > self.state.state.system_meta.last_run
+        unsafe {&*self.state.value}.changed_tick.is_newer_than(self.state.state.system_meta.last_run)
     }
 }
 
@@ -198,12 +238,12 @@ pub struct ResState1{
     index: usize, 
 }
 
-impl<T: 'static + Send + Sync> SystemParam for SingleRes<'_, T> {
+impl<T: Resource> SystemParam for SingleRes<'_, T> {
     type State = ResState<T>;
     type Item<'w> = SingleRes<'w, T>;
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
-        init_state::<T>(world, meta)
+        init_state::<T>(world, meta, Relation::Read)
     }
     #[inline(always)]
     fn init<'world>(state: &'world mut Self::State) {
@@ -224,7 +264,7 @@ impl<T: 'static + Send + Sync> SystemParam for SingleRes<'_, T> {
     }
 }
 
-impl<'w, T: Sync + Send + 'static> Deref for SingleRes<'w, T> {
+impl<'w, T: Resource> Deref for SingleRes<'w, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -233,19 +273,19 @@ impl<'w, T: Sync + Send + 'static> Deref for SingleRes<'w, T> {
 }
 
 // pub type SingleResMut<'w, T> = &'w mut SingleResMut<'w, T>;
-pub struct SingleResMut<'w, T: 'static + Send + Sync> {
+pub struct SingleResMut<'w, T: Resource> {
     pub(crate) state: &'w ResState<T>,
     mark: PhantomData<T>
 }
 
-impl<T: 'static + Send + Sync + Debug> Debug for SingleResMut<'_, T> {
+impl<T: Resource + Debug> Debug for SingleResMut<'_, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SingleResMut").field("value", &**self).finish()
     }
 }
-unsafe impl<T: 'static + Send + Sync> Send for SingleResMut<'_, T> {}
-unsafe impl<T: 'static + Send + Sync> Sync for SingleResMut<'_, T> {}
-impl<'w, T: 'static + Send + Sync> SingleResMut<'w, T> {
+unsafe impl<T: Resource> Send for SingleResMut<'_, T> {}
+unsafe impl<T: Resource> Sync for SingleResMut<'_, T> {}
+impl<'w, T: Resource> SingleResMut<'w, T> {
     pub(crate) fn new(state: &'w ResState<T>) -> Self {
         SingleResMut { state, mark: PhantomData }
     }
@@ -257,12 +297,12 @@ impl<'w, T: 'static + Send + Sync> SingleResMut<'w, T> {
         self.state.state.system_meta.this_run
     }
 }
-impl<T: 'static + Send + Sync> SystemParam for SingleResMut<'_, T> {
+impl<T: Resource> SystemParam for SingleResMut<'_, T> {
     type State = ResState<T>;
     type Item<'w> = SingleResMut<'w, T>;
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
-        init_state::<T>(world, meta)
+        init_state::<T>(world, meta, Relation::Write)
     }
 
     #[inline(always)]
@@ -286,26 +326,26 @@ impl<T: 'static + Send + Sync> SystemParam for SingleResMut<'_, T> {
         unsafe { transmute(Self::get_param(state)) }
     }
 }
-impl<'w, T: Sync + Send + 'static> Deref for SingleResMut<'w, T> {
+impl<'w, T: Resource> Deref for SingleResMut<'w, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
         unsafe {&*self.state.value}
     }
 }
-impl<'w, T: Sync + Send + 'static> DerefMut for SingleResMut<'w, T> {
+impl<'w, T: Resource> DerefMut for SingleResMut<'w, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe {&mut *self.state.value}.changed_tick = self.state.state.system_meta.this_run;
         unsafe {&mut **self.state.value}
     }
 }
 
-impl<T: 'static + Send + Sync> SystemParam for Option<SingleRes<'_, T>> {
+impl<T: Resource> SystemParam for Option<SingleRes<'_, T>> {
     type State = ResState<T>;
     type Item<'w> = Option<SingleRes<'w, T>>;
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
-        init_state::<T>(world, meta)
+        init_state::<T>(world, meta, Relation::Read)
     }
     fn get_param<'world>(
         // world: &'world World,
@@ -328,12 +368,12 @@ impl<T: 'static + Send + Sync> SystemParam for Option<SingleRes<'_, T>> {
     }
 }
 
-impl<T: 'static + Send + Sync> SystemParam for Option<SingleResMut<'_, T>> {
+impl<T: Resource> SystemParam for Option<SingleResMut<'_, T>> {
     type State = ResState<T>;
     type Item<'w> = Option<SingleResMut<'w, T>>;
 
     fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
-        init_state::<T>(world, meta)
+        init_state::<T>(world, meta, Relation::Write)
     }
 
     #[inline(always)]
@@ -359,10 +399,16 @@ impl<T: 'static + Send + Sync> SystemParam for Option<SingleResMut<'_, T>> {
     }
 }
 #[inline]
-fn init_state<T: 'static + Send + Sync>(
+fn init_state<T: Resource>(
     world: &mut World,
     meta: &mut SystemMeta,
+    r: fn(TypeId) -> Relation<TypeId>,
 ) -> ResState<T> {
+    if world.get_single_res::<T>().is_none() {
+        if let Some(value) = T::init_res(world) {
+            world.insert_single_res(value);
+        }
+    }
     let t = TypeInfo::of::<T>();
     // let r = Relation::Read(t.type_id);
     // let index = meta.add_single_res(world, t, r);
@@ -377,7 +423,7 @@ fn init_state<T: 'static + Send + Sync>(
 
     ResState {
         value: 0 as *mut TickRes<T>,
-        state: _init_state(t, world, meta)
+        state: _init_state(t, world, meta, r)
     }
 }
 
@@ -385,8 +431,9 @@ fn _init_state(
     t: TypeInfo,
     world: &mut World,
     meta: &mut SystemMeta,
+    r: fn(TypeId) -> Relation<TypeId>,
 ) -> ResState1 {
-    let r = Relation::Read(t.type_id);
+    let r = r(t.type_id);
     let index = meta.add_single_res(world, t, r);
     ResState1 {
         index,
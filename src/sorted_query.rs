@@ -0,0 +1,216 @@
+//! 按组件排序的查询
+//!
+//! `SortedQuery<Q, K, F>`按组件K的值排序迭代，K相同的实体保持上次的顺序。
+//! 排序结果缓存在参数状态上，每次运行增量调整：
+//! 根据原型新增的行及被移除的行，加入或移出实体；根据K的修改事件，调整实体的位置。
+//! 缓存只按原型匹配，F中的`Changed`等行过滤在迭代时检查，不会导致重新排序。
+
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
+use std::mem::{replace, transmute};
+
+use pi_null::Null;
+use pi_share::Share;
+
+use crate::archetype::{ComponentInfo, Row, COMPONENT_TICK};
+use crate::event::{init_changed_state, ComponentChanged, ComponentEventVec};
+use crate::fetch::FetchComponents;
+use crate::filter::FilterComponents;
+use crate::query::{Query, QueryState};
+use crate::system::SystemMeta;
+use crate::system_params::SystemParam;
+use crate::world::{Entity, World};
+use crate::world_ptr::Ptr;
+
+impl<'w, Q: FetchComponents, F: FilterComponents> Query<'w, Q, F> {
+    /// 按key_fn计算的键排序迭代，键相同的保持原来的迭代顺序
+    pub fn iter_sorted_by_key<K: Ord>(
+        &self,
+        mut key_fn: impl FnMut(&<Q::ReadOnly as FetchComponents>::Item<'_>) -> K,
+    ) -> std::vec::IntoIter<<Q::ReadOnly as FetchComponents>::Item<'_>> {
+        let mut items: Vec<_> = self.iter().collect();
+        items.sort_by_cached_key(|item| key_fn(item));
+        items.into_iter()
+    }
+    /// 按key_fn计算的键排序可变迭代，键相同的保持原来的迭代顺序
+    pub fn iter_sorted_by_key_mut<K: Ord>(
+        &mut self,
+        mut key_fn: impl FnMut(&Q::Item<'_>) -> K,
+    ) -> std::vec::IntoIter<Q::Item<'_>> {
+        let mut items: Vec<_> = self.iter_mut().collect();
+        items.sort_by_cached_key(|item| key_fn(item));
+        items.into_iter()
+    }
+}
+
+/// 按组件K排序的查询参数
+pub struct SortedQuery<'w, Q: FetchComponents + 'static, K: 'static, F: FilterComponents + 'static = ()> {
+    query: Query<'w, (Entity, &'static K, Q), F>,
+    list: &'w BTreeMap<(K, u64), Entity>,
+}
+
+impl<'w, Q: FetchComponents, K: 'static, F: FilterComponents> SortedQuery<'w, Q, K, F> {
+    /// 匹配原型的实体数量，不检查F中的行过滤
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+    /// 排序后的实体，不检查F中的行过滤
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.list.values().copied()
+    }
+    /// 按K排序迭代
+    pub fn iter(&self) -> impl Iterator<Item = <Q::ReadOnly as FetchComponents>::Item<'_>> {
+        self.list
+            .values()
+            .filter_map(|e| self.query.get(*e).ok().map(|r| r.2))
+    }
+    /// 按K排序可变迭代，列表中的实体不重复，所以不会产生重复的可变借用
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = Q::Item<'_>> {
+        let state: &QueryState<(Entity, &'static K, Q), F> = self.query.state;
+        self.list
+            .values()
+            .filter_map(move |e| state.get_by_tick(*e).ok().map(|r| r.2))
+    }
+    pub fn get(&self, e: Entity) -> Option<<Q::ReadOnly as FetchComponents>::Item<'_>> {
+        self.query.get(e).ok().map(|r| r.2)
+    }
+}
+
+// 上次更新时原型的行
+#[derive(Default)]
+struct ArchetypeRows {
+    entities: Vec<Entity>, // 每行的实体，被移除的行为null
+    removes: usize,        // 已处理的被移除的行的数量
+    settle_count: usize,   // 原型的整理次数，变化时行发生了移动
+}
+
+// 排序的实体列表
+struct SortedList<K> {
+    list: BTreeMap<(K, u64), Entity>, // 按键及加入的序号排序，键相同的保持加入的顺序
+    keys: HashMap<Entity, (K, u64)>,  // 实体在list上的键
+    seq: u64,
+}
+
+impl<K: Ord + Clone> SortedList<K> {
+    fn insert(&mut self, e: Entity, k: K) {
+        self.seq += 1;
+        self.keys.insert(e, (k.clone(), self.seq));
+        self.list.insert((k, self.seq), e);
+    }
+    fn remove(&mut self, e: Entity) {
+        if let Some(key) = self.keys.remove(&e) {
+            self.list.remove(&key);
+        }
+    }
+}
+
+pub struct SortedQueryState<Q: FetchComponents + 'static, K: 'static, F: FilterComponents + 'static> {
+    query: QueryState<(Entity, &'static K, Q), F>,
+    changed: (Share<ComponentEventVec>, usize), // K的修改事件
+    sorted: SortedList<K>,
+    rows: Vec<ArchetypeRows>, // 与query的原型一一对应
+}
+
+impl<Q: FetchComponents, K: Ord + Clone + 'static, F: FilterComponents> SortedQueryState<Q, K, F> {
+    // 根据原型行的变化及K的修改事件，增量调整排序
+    fn update(&mut self) {
+        let world: &World = &self.query.world;
+        let sorted = &mut self.sorted;
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for (i, ar) in self.query.archetypes.iter().enumerate() {
+            if i == self.rows.len() {
+                self.rows.push(ArchetypeRows::default());
+            }
+            let rows = &mut self.rows[i];
+            let start = if rows.settle_count != ar.settle_count {
+                // 原型整理过，重新记录该原型的实体
+                rows.settle_count = ar.settle_count;
+                removed.extend(rows.entities.drain(..).filter(|e| !e.is_null()));
+                0
+            } else {
+                for j in rows.removes..ar.removes.len() {
+                    let row = unsafe { ar.removes.get_unchecked(j) }.index();
+                    if let Some(e) = rows.entities.get_mut(row) {
+                        let e = replace(e, Entity::null());
+                        if !e.is_null() {
+                            removed.push(e);
+                        }
+                    }
+                }
+                rows.entities.len()
+            };
+            rows.removes = ar.removes.len();
+            for row in start..ar.len().index() {
+                let e = ar.get_unchecked(Row(row as u32));
+                rows.entities.push(e);
+                if !e.is_null() {
+                    added.push(e);
+                }
+            }
+        }
+        for e in removed {
+            // 实体可能移到了本查询的其他原型
+            if !self.query.contains(e) {
+                sorted.remove(e);
+            }
+        }
+        for e in added {
+            if sorted.keys.contains_key(&e) {
+                continue;
+            }
+            if let Ok(k) = world.get_component::<K>(e) {
+                sorted.insert(e, k.clone());
+            }
+        }
+        for e in self.changed.0.get_iter(self.changed.1) {
+            let k = match (sorted.keys.get(e), world.get_component::<K>(*e)) {
+                (Some(old), Ok(k)) if old.0 != *k => k.clone(),
+                _ => continue,
+            };
+            sorted.remove(*e);
+            sorted.insert(*e, k);
+        }
+    }
+}
+
+impl<Q: FetchComponents + 'static, K: Ord + Clone + Send + Sync + 'static, F: FilterComponents + Send + Sync>
+    SystemParam for SortedQuery<'_, Q, K, F>
+{
+    type State = SortedQueryState<Q, K, F>;
+    type Item<'w> = SortedQuery<'w, Q, K, F>;
+
+    fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
+        let changed = init_changed_state(
+            world,
+            TypeId::of::<ComponentChanged<'static, K>>(),
+            ComponentInfo::of::<K>(COMPONENT_TICK),
+        );
+        SortedQueryState {
+            query: QueryState::create(world, Ptr::new(meta)),
+            changed,
+            sorted: SortedList {
+                list: BTreeMap::new(),
+                keys: HashMap::new(),
+                seq: 0,
+            },
+            rows: Vec::new(),
+        }
+    }
+    fn align(state: &mut Self::State) {
+        state.query.align();
+    }
+    fn get_param<'world>(state: &'world mut Self::State) -> Self::Item<'world> {
+        state.update();
+        SortedQuery {
+            query: <Query<'static, (Entity, &'static K, Q), F> as SystemParam>::get_param(&mut state.query),
+            list: &state.sorted.list,
+        }
+    }
+    fn get_self<'world>(state: &'world mut Self::State) -> Self {
+        unsafe { transmute(Self::get_param(state)) }
+    }
+}
@@ -0,0 +1,144 @@
+//! 稀疏存储，组件以实体为键存放在原型表外，增删组件不会移动实体到其他原型。
+//! 每个稀疏组件的列上有一个SparseSet，实体映射到行，行上存放组件数据及tick，移除后的空行会被复用，整理时用末尾的行填充空行。
+
+use std::mem::size_of;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use pi_null::Null;
+use pi_share::ShareUsize;
+
+use crate::archetype::Row;
+use crate::column::BlobTicks;
+use crate::world::{Entity, Tick};
+
+pub(crate) struct SparseSet {
+    pub(crate) blob: BlobTicks,  // 按行存放的组件数据及tick
+    rows: DashMap<Entity, Row>,  // 实体所在的行
+    len: ShareUsize,             // 已分配的行数
+    frees: Mutex<Vec<Row>>,      // 可复用的空行
+    size: usize,
+    drop_fn: Option<fn(*mut u8)>,
+}
+
+impl SparseSet {
+    pub(crate) fn new(size: usize, drop_fn: Option<fn(*mut u8)>) -> Self {
+        let mut blob = BlobTicks::default();
+        // 和原型表的列一样，0大小的组件设置容量为最大数减1
+        unsafe { blob.blob.set_vec_capacity(if size == 0 { usize::MAX - 1 } else { 0 }) };
+        Self {
+            blob,
+            rows: DashMap::default(),
+            len: ShareUsize::new(0),
+            frees: Mutex::new(Vec::new()),
+            size,
+            drop_fn,
+        }
+    }
+    /// 有该组件的实体数量
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+    /// 实体所在的行，没有该组件则返回null
+    #[inline]
+    pub fn row(&self, e: Entity) -> Row {
+        self.rows.get(&e).map_or(Row::null(), |r| *r)
+    }
+    /// 所有已分配的行
+    pub(crate) fn rows(&self) -> impl Iterator<Item = Row> + '_ {
+        self.rows.iter().map(|r| *r.value())
+    }
+    /// 为实体分配行，返回行及是否已存在
+    pub(crate) fn alloc(&self, e: Entity) -> (Row, bool) {
+        match self.rows.entry(e) {
+            Entry::Occupied(entry) => (*entry.get(), true),
+            Entry::Vacant(entry) => {
+                let row = match self.frees.lock().unwrap().pop() {
+                    Some(row) => row,
+                    None => self.len.fetch_add(1, Ordering::Relaxed).into(),
+                };
+                entry.insert(row);
+                (row, false)
+            }
+        }
+    }
+    /// 移除实体的映射，返回所在的行，行上的数据需要由调用者释放后，再调用free
+    pub(crate) fn remove(&self, e: Entity) -> Row {
+        self.rows.remove(&e).map_or(Row::null(), |(_, row)| row)
+    }
+    /// 回收空行
+    pub(crate) fn free(&self, row: Row) {
+        self.frees.lock().unwrap().push(row);
+    }
+    pub fn memsize(&self) -> usize {
+        self.blob.memsize() + self.rows.capacity() * size_of::<(Entity, Row)>()
+    }
+    /// 整理内存，用末尾的行填充空行，并将bucket上的数据移到连续内存上
+    pub(crate) fn settle(&mut self, is_tick: bool) {
+        self.compact(is_tick);
+        let len = *self.len.get_mut();
+        if self.size > 0 {
+            self.blob.blob.settle(len, 0, self.size);
+        }
+        if is_tick {
+            self.blob.ticks.settle(len, 0);
+        }
+    }
+    /// 整理内存，并释放未使用的内存
+    pub(crate) fn shrink_to_fit(&mut self, is_tick: bool) {
+        self.settle(is_tick);
+        let len = *self.len.get_mut();
+        if self.size > 0 {
+            self.blob.blob.shrink_to_fit(len, self.size);
+        }
+    }
+    // 将len之后的行移到前len行的空行上，移动后已分配的行数和实体数量相同
+    fn compact(&mut self, is_tick: bool) {
+        let len = self.rows.len();
+        if *self.len.get_mut() == len {
+            return;
+        }
+        // 前len行中的空行，和len之后的行数量相同
+        let mut frees: Vec<Row> = self
+            .frees
+            .get_mut()
+            .unwrap()
+            .drain(..)
+            .filter(|row| row.index() < len)
+            .collect();
+        for mut r in self.rows.iter_mut() {
+            let src = *r.value();
+            if src.index() < len {
+                continue;
+            }
+            let dst = frees.pop().unwrap();
+            if self.size > 0 {
+                let src_data: *mut u8 = self.blob.blob.get_multiple_unchecked(src.index(), self.size);
+                let dst_data: *mut u8 = self.blob.blob.load_alloc_multiple(dst.index(), self.size);
+                unsafe { src_data.copy_to_nonoverlapping(dst_data, self.size) };
+            }
+            if is_tick {
+                let tick = self.blob.ticks.get(src.index()).map_or(Tick::default(), |t| *t);
+                *self.blob.ticks.load_alloc(dst.index()) = tick;
+            }
+            *r.value_mut() = dst;
+        }
+        *self.len.get_mut() = len;
+    }
+}
+
+impl Drop for SparseSet {
+    fn drop(&mut self) {
+        let f = match self.drop_fn {
+            Some(f) => f,
+            None => return,
+        };
+        for r in self.rows.iter() {
+            if let Some(ptr) = self.blob.blob.get_multiple(r.value().index(), self.size) {
+                f(ptr)
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    any::TypeId,
+    any::{Any, TypeId},
     borrow::Cow,
     collections::HashMap,
     fmt::Debug,
@@ -15,6 +15,8 @@ use pi_share::Share;
 use crate::{
     archetype::{Archetype, ComponentInfo, ShareArchetype},
     column::Column,
+    pipe::{PipeSystem, SystemInput},
+    query::QueryError,
     world::{ComponentIndex, Tick, World},
 };
 
@@ -115,6 +117,23 @@ impl<T: Eq> Relation<T> {
             _ => false,
         }
     }
+    // 检查other是否和自身的读写冲突, 返回Some(T)表示冲突
+    pub fn conflict(&self, other: &Relation<T>) -> Option<T>
+    where
+        T: Copy,
+    {
+        match self {
+            Relation::Read(t) | Relation::OptRead(t) => {
+                let mut t = *t;
+                (!other.read(&mut t)).then_some(t)
+            }
+            Relation::Write(t) | Relation::OptWrite(t) => {
+                let mut t = *t;
+                (!other.write(&mut t)).then_some(t)
+            }
+            _ => None,
+        }
+    }
 }
 #[derive(Debug, Default, Clone, Copy)]
 pub struct RelateNode {
@@ -165,11 +184,17 @@ where
     node.result
 }
 
-struct ArchetypeFilter<'a>(&'a Archetype);
+struct ArchetypeFilter<'a>(&'a Archetype, &'a Vec<ComponentIndex>);
 
 // 判断原型是否和该关系相关
 fn archetype_relate<'a>(r: &Relation<ComponentIndex>, arg: &mut ArchetypeFilter<'a>) -> bool {
     match r {
+        // 稀疏存储的组件不在原型上，由查询时逐行判断
+        Relation::Without(i) | Relation::With(i) | Relation::Read(i) | Relation::Write(i)
+            if arg.1.contains(i) =>
+        {
+            true
+        }
         Relation::Without(i) => !arg.0.contains(*i),
         Relation::With(i) => arg.0.contains(*i),
         Relation::Read(i) => arg.0.contains(*i),
@@ -182,31 +207,50 @@ fn archetype_relate<'a>(r: &Relation<ComponentIndex>, arg: &mut ArchetypeFilter<
 #[derive(Debug, Default)]
 pub struct Related<T: Eq> {
     pub(crate) vec: Vec<Relation<T>>,
+    pub(crate) sparse: Vec<T>, // 稀疏存储的组件
 }
+impl Related<ComponentIndex> {
+    /// 不修改world，获得已注册组件的列，并记录读写关系，用于从已有的查询派生查询。
+    /// 组件未注册或未记录需要的tick时，返回错误
+    pub fn component_relate_ref(
+        &mut self,
+        world: &World,
+        info: ComponentInfo,
+        r: Relation<ComponentIndex>,
+    ) -> Result<Share<Column>, QueryError> {
+        let c = match world.get_column_by_id(info.type_id()) {
+            Some(c) if c.info().tick_info | info.tick_info == c.info().tick_info => c,
+            _ => return Err(QueryError::UnregisteredComponent(info.type_name().clone())),
+        };
+        if c.info().is_sparse() {
+            match r {
+                Relation::Read(_) | Relation::Write(_) | Relation::With(_) | Relation::Without(_) => {
+                    self.sparse.push(c.info().index)
+                }
+                // 可选的读写与原型无关，逐行判断实体是否有该组件
+                Relation::OptRead(_) | Relation::OptWrite(_) => (),
+                _ => return Err(QueryError::LensUnsupported(c.info().type_name().clone())),
+            }
+        }
+        self.vec.push(r.replace(c.info().index));
+        Ok(c.clone())
+    }
+}
+
 impl<T: Eq + Copy + Debug> Related<T> {
     pub fn new() -> Self {
-        Self { vec: Vec::new() }
+        Self {
+            vec: Vec::new(),
+            sparse: Vec::new(),
+        }
     }
-    // 检查新旧读写在reads或writes是否完全不重合
-    pub fn check_conflict(&self, other: &Related<T>) {
+    // 检查新旧读写在reads或writes是否完全不重合, 返回Some(T)表示冲突
+    pub fn check_conflict(&self, other: &Related<T>) -> Option<T> {
         // 先检查withouts
         if self.check_without(other) || other.check_without(self) {
-            return;
+            return None;
         }
-        assert_eq!(
-            self.check_rw(other),
-            None,
-            "conflict1!, {:?} {:?}",
-            self,
-            other
-        );
-        assert_eq!(
-            other.check_rw(self),
-            None,
-            "conflict2!, {:?} {:?}",
-            self,
-            other
-        );
+        self.check_rw(other).or_else(|| other.check_rw(self))
     }
     // 检查other的每一个without，和self的with read或writes判断，返回true表示查询完全不重合
     pub fn check_without(&self, other: &Related<T>) -> bool {
@@ -289,6 +333,24 @@ impl<T: Eq + Copy + Debug> Related<T> {
         }
         None
     }
+    // 收集数据集的全部读写冲突
+    pub fn collect_rw(&self, other: &Related<T>, result: &mut Vec<T>) {
+        for w in other.vec.iter() {
+            match w {
+                Relation::Read(t) | Relation::OptRead(t) => {
+                    if !self.check_read(*t, 0) && !result.contains(t) {
+                        result.push(*t);
+                    }
+                }
+                Relation::Write(t) | Relation::OptWrite(t) => {
+                    if !self.check_write(*t, 0) && !result.contains(t) {
+                        result.push(*t);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
     // 检查数据集是否读冲突
     pub fn check_read(&self, mut t: T, mut start: usize) -> bool {
         // println!("check_read {:?}", (t, start));
@@ -313,7 +375,7 @@ impl<T: Eq + Copy + Debug> Related<T> {
 }
 // 判断该原型是否相关
 pub fn relate(r: &Related<ComponentIndex>, archetype: &Archetype, mut start: usize) -> bool {
-    let mut filter = ArchetypeFilter(archetype);
+    let mut filter = ArchetypeFilter(archetype, &r.sparse);
     traversal(
         &r.vec,
         &archetype_relate,
@@ -335,10 +397,17 @@ pub struct SystemMeta {
     pub(crate) vec: Vec<Share<Related<ComponentIndex>>>, // SystemParam参数的组件关系列表
     pub(crate) cur_related: Related<ComponentIndex>,     // 当前SystemParam参数的关系
     pub(crate) param_set_locations: Vec<Range<usize>>,   // 参数集在vec的位置
+    pub(crate) merged_locations: Vec<(Range<usize>, Range<usize>)>, // 合并的system在vec和res_related的位置
     pub(crate) res_related: Related<TypeId>,             // Res资源的关系
 
     pub(crate) res_reads: HashMap<TypeId, Cow<'static, str>>, // 读Res
     pub(crate) res_writes: HashMap<TypeId, Cow<'static, str>>, // 写ResMut
+
+    pub(crate) cur_param: Cow<'static, str>,         // 当前SystemParam参数的名字
+    pub(crate) params: Vec<Cow<'static, str>>,       // vec中每个关系对应的参数名字
+    pub(crate) res_params: Vec<Cow<'static, str>>,   // res_related中每个关系对应的参数名字
+
+    pub(crate) input: Option<Box<dyn Any + Send + Sync>>, // 管道输入，运行时作为In<T>参数传入
 }
 
 impl SystemMeta {
@@ -348,10 +417,15 @@ impl SystemMeta {
             vec: Default::default(),
             cur_related: Default::default(),
             param_set_locations: Default::default(),
+            merged_locations: Default::default(),
             res_related: Related::new(),
 
             res_reads: Default::default(),
             res_writes: Default::default(),
+            cur_param: Cow::Borrowed(""),
+            params: Default::default(),
+            res_params: Default::default(),
+            input: None,
             last_run: Tick::null(),
             this_run: Tick::null(),
         }
@@ -375,6 +449,18 @@ impl SystemMeta {
         r: Relation<ComponentIndex>,
     ) -> (ComponentIndex, Share<Column>) {
         let rc = world.add_component_info(info);
+        if rc.1.info().is_sparse() {
+            match r {
+                Relation::Read(_) | Relation::Write(_) | Relation::With(_) | Relation::Without(_) => {
+                    self.cur_related.sparse.push(rc.0)
+                }
+                Relation::OptRead(_) | Relation::OptWrite(_) => (),
+                _ => panic!(
+                    "sparse component `{}` does not support shared writes in queries",
+                    rc.1.info().type_name()
+                ),
+            }
+        }
         self.cur_related.vec.push(r.replace(rc.0));
         rc
     }
@@ -384,8 +470,9 @@ impl SystemMeta {
         for info in &components {
             self.cur_related.vec.push(Relation::Write(info.index));
         }
-        // 在关联分析上为了精确关联原型，加一个Count(usize)
-        self.cur_related.vec.push(Relation::Count(components.len()));
+        // 在关联分析上为了精确关联原型，加一个Count(usize)，稀疏存储的组件不在原型上
+        let count = components.iter().filter(|c| !c.is_sparse()).count();
+        self.cur_related.vec.push(Relation::Count(count));
         self.related_ok();
         world.find_ar(components)
     }
@@ -398,8 +485,55 @@ impl SystemMeta {
     pub fn related_ok(&mut self) -> Share<Related<ComponentIndex>> {
         let ar = Share::new(take(&mut self.cur_related));
         self.vec.push(ar.clone());
+        self.params.push(self.cur_param.clone());
         ar
     }
+    /// 钳制过旧的last_run和this_run，下次运行时this_run会成为last_run
+    pub fn check_change_ticks(&mut self, now: Tick) {
+        self.last_run.check_tick(now);
+        self.this_run.check_tick(now);
+    }
+    /// 设置当前SystemParam参数的名字，用于冲突报告
+    pub fn param_name(&mut self, name: &'static str) {
+        self.cur_param = Cow::Borrowed(name);
+    }
+    /// 合并另一个system的读写关系，用于管道等组合system
+    /// 被合并的system依次运行，它们之间不检查冲突
+    pub fn merge(&mut self, other: &SystemMeta) {
+        let offset = self.vec.len();
+        let res_offset = self.res_related.vec.len();
+        if other.merged_locations.is_empty() {
+            self.merged_locations.push((
+                offset..offset + other.vec.len(),
+                res_offset..res_offset + other.res_related.vec.len(),
+            ));
+        } else {
+            self.merged_locations.extend(other.merged_locations.iter().map(|(v, r)| {
+                (
+                    v.start + offset..v.end + offset,
+                    r.start + res_offset..r.end + res_offset,
+                )
+            }));
+        }
+        self.vec.extend(other.vec.iter().cloned());
+        self.param_set_locations.extend(
+            other
+                .param_set_locations
+                .iter()
+                .map(|r| r.start + offset..r.end + offset),
+        );
+        self.params.extend(other.params.iter().cloned());
+        self.res_related
+            .vec
+            .extend(other.res_related.vec.iter().cloned());
+        self.res_params.extend(other.res_params.iter().cloned());
+        for (k, v) in other.res_reads.iter() {
+            self.res_reads.insert(*k, v.clone());
+        }
+        for (k, v) in other.res_writes.iter() {
+            self.res_writes.insert(*k, v.clone());
+        }
+    }
     pub fn param_set_start(&mut self) {
         self.param_set_locations
             .push(self.vec.len()..self.vec.len());
@@ -414,47 +548,128 @@ impl SystemMeta {
         info: TypeInfo,
         r: Relation<TypeId>,
     ) -> usize {
+        // 记录资源的名字，用于冲突报告
+        match &r {
+            Relation::Write(_) => self.res_writes.insert(info.type_id, info.type_name.clone()),
+            _ => self.res_reads.insert(info.type_id, info.type_name.clone()),
+        };
         self.res_related.vec.push(r);
+        self.res_params.push(self.cur_param.clone());
         world.or_register_single_res(info)
     }
     /// 加入一个资源
     pub fn add_res<'w>(&mut self, r: Relation<TypeId>) {
         self.res_related.vec.push(r);
+        self.res_params.push(self.cur_param.clone());
     }
 
-    // 检查冲突
-    pub fn check_conflict(&self) {
+    /// 检查system内参数之间的读写冲突，参数集内的参数不检查
+    pub fn check_conflict(&self, world: &World) -> Vec<AccessConflict> {
+        let mut result = Vec::new();
         // 先检查资源是否冲突
-        assert_eq!(
-            self.res_related.check_self(),
-            None,
-            "self res conflict, related:{:?}",
-            &self.res_related
-        );
-        for r in self.vec.iter() {
-            // 先检查自身
-            assert_eq!(r.check_self(), None, "self conflict, related:{:?}", r);
-        }
-        let mut range_it = self.param_set_locations.iter();
-        let mut set_loc = range_it.next();
-        for mut i in 0..self.vec.len() {
-            let r = &self.vec[i];
-            if let Some(range) = set_loc {
-                if range.end == i {
-                    // 本次param_set_location结束，跳到下一个param_set_location
-                    set_loc = range_it.next();
-                } else if range.start <= i {
-                    // 跳过参数集内的检查
-                    i = range.end - 1;
+        let res = &self.res_related.vec;
+        for i in 0..res.len() {
+            for j in i + 1..res.len() {
+                if self.merged_apart(|(_, r)| r, i, j) {
+                    continue;
+                }
+                if let Some(id) = res[i].conflict(&res[j]).or_else(|| res[j].conflict(&res[i])) {
+                    result.push(self.access_conflict(
+                        vec![self.res_params[i].clone(), self.res_params[j].clone()],
+                        match self.res_name(&id) {
+                            Some(name) => name.clone(),
+                            None => Cow::Owned(format!("{:?}", id)),
+                        },
+                    ));
                 }
             }
-            i += 1;
+        }
+        for i in 0..self.vec.len() {
+            // 先检查自身
+            if let Some(index) = self.vec[i].check_self() {
+                result.push(self.access_conflict(
+                    vec![self.params[i].clone()],
+                    component_name(world, index),
+                ));
+            }
             // 依次和后面的Related比较
-            for j in i..self.vec.len() {
-                let r2 = &self.vec[j];
-                r.check_conflict(r2);
+            for j in i + 1..self.vec.len() {
+                // 跳过参数集内的检查
+                if self
+                    .param_set_locations
+                    .iter()
+                    .any(|r| r.contains(&i) && r.contains(&j))
+                {
+                    continue;
+                }
+                // 跳过被合并的不同system间的检查
+                if self.merged_apart(|(v, _)| v, i, j) {
+                    continue;
+                }
+                if let Some(index) = self.vec[i].check_conflict(&self.vec[j]) {
+                    result.push(self.access_conflict(
+                        vec![self.params[i].clone(), self.params[j].clone()],
+                        component_name(world, index),
+                    ));
+                }
             }
         }
+        result
+    }
+    // i和j是否属于被合并的不同system
+    fn merged_apart(
+        &self,
+        f: impl Fn(&(Range<usize>, Range<usize>)) -> &Range<usize>,
+        i: usize,
+        j: usize,
+    ) -> bool {
+        let pi = self.merged_locations.iter().position(|l| f(l).contains(&i));
+        let pj = self.merged_locations.iter().position(|l| f(l).contains(&j));
+        pi.is_some() && pj.is_some() && pi != pj
+    }
+    fn access_conflict(
+        &self,
+        params: Vec<Cow<'static, str>>,
+        access: Cow<'static, str>,
+    ) -> AccessConflict {
+        AccessConflict {
+            system: self.type_info.type_name.clone(),
+            params,
+            access,
+        }
+    }
+
+    /// 检查和另一个system的读写冲突，返回冲突的组件或资源的名字
+    pub fn conflicts(&self, other: &SystemMeta, world: &World) -> Vec<Cow<'static, str>> {
+        let mut result = Vec::new();
+        let mut indexs = Vec::new();
+        for r1 in self.vec.iter() {
+            for r2 in other.vec.iter() {
+                // 查询的原型完全不重合
+                if r1.check_without(r2) || r2.check_without(r1) {
+                    continue;
+                }
+                r1.collect_rw(r2, &mut indexs);
+                r2.collect_rw(r1, &mut indexs);
+            }
+        }
+        for index in indexs {
+            result.push(component_name(world, index));
+        }
+        let mut ids = Vec::new();
+        self.res_related.collect_rw(&other.res_related, &mut ids);
+        other.res_related.collect_rw(&self.res_related, &mut ids);
+        for id in ids {
+            result.push(match self.res_name(&id).or_else(|| other.res_name(&id)) {
+                Some(name) => name.clone(),
+                None => Cow::Owned(format!("{:?}", id)),
+            });
+        }
+        result
+    }
+    // 获得资源的名字
+    fn res_name(&self, id: &TypeId) -> Option<&Cow<'static, str>> {
+        self.res_writes.get(id).or_else(|| self.res_reads.get(id))
     }
 
     pub fn res_read(&mut self, type_info: &TypeInfo) {
@@ -494,6 +709,23 @@ pub trait System: Send + Sync + 'static {
 
     /// system align the world archetypes
     fn align(&mut self, world: &World);
+
+    /// Returns the system's metadata, used for dependency analysis and pipe input.
+    fn system_meta(&self) -> Option<&SystemMeta> {
+        None
+    }
+
+    /// Returns the system's mutable metadata.
+    fn system_meta_mut(&mut self) -> Option<&mut SystemMeta> {
+        None
+    }
+
+    /// 钳制过旧的last_run，避免tick回绕后误判变化
+    fn check_change_ticks(&mut self, now: Tick) {
+        if let Some(meta) = self.system_meta_mut() {
+            meta.check_change_ticks(now);
+        }
+    }
 }
 
 pub trait RunSystem: System {
@@ -529,6 +761,44 @@ pub trait AsyncRunSystem: System {
         -> Pin<Box<dyn Future<Output = Self::Out> + Send + 'static>>;
 }
 
+// 获得组件的名字
+pub(crate) fn component_name(world: &World, index: ComponentIndex) -> Cow<'static, str> {
+    match world.get_column(index) {
+        Some(c) => c.info().type_name().clone(),
+        None => Cow::Owned(format!("{:?}", index)),
+    }
+}
+
+/// system内参数之间的读写冲突
+#[derive(Debug, Clone)]
+pub struct AccessConflict {
+    /// system的名字
+    pub system: Cow<'static, str>,
+    /// 冲突的参数的名字
+    pub params: Vec<Cow<'static, str>>,
+    /// 冲突的组件或资源的名字
+    pub access: Cow<'static, str>,
+}
+impl std::fmt::Display for AccessConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: params {:?} conflict on {}",
+            self.system, self.params, self.access
+        )
+    }
+}
+
+/// 一次性system的id，由[`World::register_system`]返回
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemId(pub(crate) usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunSystemError {
+    NoSuchSystem(SystemId),
+    Running(SystemId), // system正在运行，不能递归运行
+}
+
 /// A convenience type alias for a boxed [`System`] trait object.
 pub enum BoxedSystem<Out> {
     Sync(Box<dyn RunSystem<Out = Out>>),
@@ -564,6 +834,20 @@ impl<Out: 'static> BoxedSystem<Out> {
         }
     }
 
+    pub fn system_meta(&self) -> Option<&SystemMeta> {
+        match self {
+            BoxedSystem::Sync(s) => s.system_meta(),
+            BoxedSystem::Async(s) => s.system_meta(),
+        }
+    }
+
+    pub fn check_change_ticks(&mut self, now: Tick) {
+        match self {
+            BoxedSystem::Sync(s) => s.check_change_ticks(now),
+            BoxedSystem::Async(s) => s.check_change_ticks(now),
+        }
+    }
+
     pub async fn run(&mut self, world: &'static World) -> Out {
         match self {
             BoxedSystem::Sync(s) => s.run(world),
@@ -577,6 +861,26 @@ pub trait IntoSystem<Marker, Out>: Sized {
 
     /// Turns this value into its corresponding [`System`].
     fn into_system(self) -> Self::System;
+
+    /// 管道，将self的输出作为system的输入，system的第一个参数必须为[`In<Out>`]
+    ///
+    /// 输入类型不匹配时编译失败：
+    /// ```compile_fail
+    /// use pi_world::prelude::*;
+    /// fn a() -> usize { 1 }
+    /// fn b(_input: In<u32>) {}
+    /// let _ = a.pipe(b);
+    /// ```
+    fn pipe<BMarker, BOut, B: IntoSystem<BMarker, BOut>>(
+        self,
+        system: B,
+    ) -> PipeSystem<Self::System, B::System>
+    where
+        Out: Send + Sync + 'static,
+        B::System: SystemInput<Input = Out>,
+    {
+        PipeSystem::new(self.into_system(), system.into_system())
+    }
 }
 pub trait IntoAsyncSystem<Marker, Out>: Sized {
     /// The type of [`System`] that this instance converts into.
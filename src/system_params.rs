@@ -279,7 +279,11 @@ macro_rules! impl_system_param_tuple {
             // type Fetch<'w> = ($($param::Fetch::<'w>,)*);
 
             fn init_state(_world: &mut World, _system_meta: &mut SystemMeta) -> Self::State {
-                (($($param::init_state(_world, _system_meta),)*))
+                (($({
+                    // 记录参数名字，用于冲突报告
+                    _system_meta.param_name(std::any::type_name::<$param>());
+                    $param::init_state(_world, _system_meta)
+                },)*))
             }
             #[allow(clippy::unused_unit)]
             fn init<'world>(
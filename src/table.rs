@@ -24,6 +24,7 @@ pub struct Table {
     per_entity_mem_size: usize,         // 每实体的内存大小
     bit_set: FixedBitSet,               // 记录组件是否在table中
     pub(crate) removes: AppendVec<Row>, // 整理前被移除的实例
+    pub(crate) settle_count: usize, // 移动过行的整理次数，用于检查行是否发生了变化
 }
 impl Table {
     pub fn new(sorted_columns: Vec<Share<Column>>) -> Self {
@@ -46,6 +47,7 @@ impl Table {
             per_entity_mem_size,
             bit_set,
             removes: AppendVec::default(),
+            settle_count: 0,
         }
     }
     /// Returns the number of elements in the archetype.
@@ -53,6 +55,14 @@ impl Table {
     pub fn len(&self) -> Row {
         Row(self.entities.len() as u32)
     }
+    /// 获得容量
+    pub fn capacity(&self) -> usize {
+        self.entities.capacity()
+    }
+    /// 获得实体表及移除表的内存大小
+    pub fn entities_mem_size(&self) -> usize {
+        self.entities.capacity() * size_of::<Entity>() + self.removes.capacity() * size_of::<Row>()
+    }
     /// 获得内存大小
     pub fn mem_size(&self) -> usize {
         let c = self.entities.capacity() * self.per_entity_mem_size;
@@ -103,6 +113,24 @@ impl Table {
         let vec = vec![];
         self.settle_columns(len, additional, &vec);
     }
+    /// 释放未使用的内存，需要在整理后调用，如果还有未整理的移除行，则返回false
+    pub fn shrink_to_fit(&mut self) -> bool {
+        if self.removes.len() > 0 {
+            return false;
+        }
+        let len = self.entities.len();
+        let mut entities = AppendVec::with_capacity(len);
+        for e in self.entities.iter() {
+            *entities.alloc().0 = *e;
+        }
+        self.entities = entities;
+        self.removes = AppendVec::default();
+        for c in self.sorted_columns.iter_mut() {
+            let c = unsafe { Share::get_mut_unchecked(c) };
+            c.shrink_by_index(self.index, len);
+        }
+        true
+    }
     /// 整理每个列
     pub(crate) fn settle_columns(&mut self, len: usize, additional: usize, vec: &Vec<(Row, Row)>) {
         // println!("Table settle_columns, {:?}", (self.index, len));
@@ -126,6 +154,7 @@ impl Table {
         for c in self.sorted_columns.iter() {
             let c = c.blob_ref_unchecked(self.index);
             c.drop_row(row, *e);
+            c.info.hook_removed(*e);
         }
         self.removes.insert(row);
         replace(e, Entity::null())
@@ -228,6 +257,10 @@ impl Table {
         }
         end
     }
+    /// 是否需要整理，有被移除的行，或内存未合并
+    pub(crate) fn need_settle(&self) -> bool {
+        self.removes.len() > 0 || self.entities.len() > self.entities.vec_capacity()
+    }
     /// 只有主调度完毕后，才能调用的整理方法
     /// 尝试清空所有列的脏列表，所有的脏都被成功的处理和清理后，才能进行row调整
     /// 调整Row，将空位的entity换到尾部，将entitys变紧凑，没有空位。
@@ -252,6 +285,7 @@ impl Table {
             Self::removes_action(&self.removes, remove_len, self.entities.len(), action, set);
         // 清理removes
         self.removes.clear(0);
+        self.settle_count += 1;
         // 整理全部的列, 合并空位
         self.settle_columns(new_entity_len, 0, &action);
         // 再移动entitys的空位
@@ -0,0 +1,226 @@
+//! 世界内部一致性检查
+//!
+//! 检查实体地址与原型表的双向映射、列的blob长度、原型表与原型数组、事件监听器的读取位置。
+//! 一般在测试中调用，也可以通过`World::set_validate_on_settle`，在debug模式下每次整理后检查。
+
+use std::fmt::{Display, Formatter, Result};
+
+use pi_null::Null;
+use pi_share::Share;
+
+use crate::archetype::{ArchetypeIndex, Row};
+use crate::world::{ComponentIndex, Entity, World};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// 实体的地址指向的原型不存在
+    MissingArchetype(Entity, ArchetypeIndex),
+    /// 实体的行超出了原型的长度
+    RowOutOfRange(Entity, ArchetypeIndex, Row),
+    /// 实体的地址指向的行记录的是另一个实体
+    EntityMismatch {
+        entity: Entity,
+        archetype: ArchetypeIndex,
+        row: Row,
+        found: Entity,
+    },
+    /// 原型表中的实体，在实体表中的地址不指向该行
+    AddrMismatch {
+        entity: Entity,
+        archetype: ArchetypeIndex,
+        row: Row,
+    },
+    /// 列未初始化该原型的blob
+    MissingColumn(ArchetypeIndex, ComponentIndex),
+    /// 列的blob长度小于原型的长度
+    ColumnLength(ArchetypeIndex, ComponentIndex, usize),
+    /// archetype_map中原型的id与键不一致，或原型不在archetype_arr中
+    ArchetypeMap(u64, ArchetypeIndex),
+    /// 原型在archetype_arr中的位置与其index不一致，或不在archetype_map中
+    ArchetypeArr(usize, ArchetypeIndex),
+    /// 事件监听器的读取位置超出了事件列表的长度
+    ListenerOutOfRange {
+        event: String,
+        listener: usize,
+        read: usize,
+        len: usize,
+    },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ValidationError::MissingArchetype(e, ar) => {
+                write!(f, "entity {:?} points to missing archetype {:?}", e, ar)
+            }
+            ValidationError::RowOutOfRange(e, ar, row) => {
+                write!(f, "entity {:?} points to {:?} {:?}, out of range", e, ar, row)
+            }
+            ValidationError::EntityMismatch {
+                entity,
+                archetype,
+                row,
+                found,
+            } => write!(
+                f,
+                "entity {:?} points to {:?} {:?}, but the row holds {:?}",
+                entity, archetype, row, found
+            ),
+            ValidationError::AddrMismatch {
+                entity,
+                archetype,
+                row,
+            } => write!(
+                f,
+                "{:?} {:?} holds entity {:?}, but the entity does not point to it",
+                archetype, row, entity
+            ),
+            ValidationError::MissingColumn(ar, c) => {
+                write!(f, "column {:?} is not initialized for archetype {:?}", c, ar)
+            }
+            ValidationError::ColumnLength(ar, c, len) => write!(
+                f,
+                "column {:?} of archetype {:?} is shorter than the table length {}",
+                c, ar, len
+            ),
+            ValidationError::ArchetypeMap(id, ar) => write!(
+                f,
+                "archetype_map entry {} does not match archetype {:?}",
+                id, ar
+            ),
+            ValidationError::ArchetypeArr(index, ar) => write!(
+                f,
+                "archetype_arr[{}] does not match archetype {:?}",
+                index, ar
+            ),
+            ValidationError::ListenerOutOfRange {
+                event,
+                listener,
+                read,
+                len,
+            } => write!(
+                f,
+                "listener {} of event {} has read {}, but the event list length is {}",
+                listener, event, read, len
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl World {
+    /// 检查世界内部的一致性，返回所有发现的错误。必须保证调用时没有其他线程读写world
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.validate_entities(&mut errors);
+        self.validate_archetypes(&mut errors);
+        self.validate_events(&mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+    // 检查实体表到原型表的映射
+    fn validate_entities(&self, errors: &mut Vec<ValidationError>) {
+        for (e, addr) in self.entities.iter() {
+            if addr.row.is_null() {
+                continue;
+            }
+            let index = addr.archetype_index();
+            let ar = match self.archetype_arr.get(index.index()) {
+                Some(ar) => ar,
+                None => {
+                    errors.push(ValidationError::MissingArchetype(e, index));
+                    continue;
+                }
+            };
+            if addr.row.index() >= ar.len().index() {
+                errors.push(ValidationError::RowOutOfRange(e, index, addr.row));
+                continue;
+            }
+            let found = ar.get_unchecked(addr.row);
+            if found != e {
+                errors.push(ValidationError::EntityMismatch {
+                    entity: e,
+                    archetype: index,
+                    row: addr.row,
+                    found,
+                });
+            }
+        }
+    }
+    // 检查原型表到实体表的映射、列的长度、原型表与原型数组
+    fn validate_archetypes(&self, errors: &mut Vec<ValidationError>) {
+        for (i, ar) in self.archetype_arr.iter().enumerate() {
+            let index = ar.index();
+            if index.index() != i {
+                errors.push(ValidationError::ArchetypeArr(i, index));
+            }
+            match self.archetype_map.get(&ar.id()) {
+                Some(r) if Share::ptr_eq(r.value(), ar) => (),
+                _ => errors.push(ValidationError::ArchetypeArr(i, index)),
+            }
+            let len = ar.len().index();
+            for row in 0..len {
+                let row = Row(row as u32);
+                let e = ar.get_unchecked(row);
+                if e.is_null() {
+                    continue;
+                }
+                match self.entities.get(e) {
+                    Some(addr) if addr.archetype_index() == index && addr.row == row => (),
+                    _ => errors.push(ValidationError::AddrMismatch {
+                        entity: e,
+                        archetype: index,
+                        row,
+                    }),
+                }
+            }
+            for c in ar.get_columns().iter() {
+                if !c.contains(index) {
+                    errors.push(ValidationError::MissingColumn(index, c.info().index));
+                } else if !c.check_len(index, len) {
+                    errors.push(ValidationError::ColumnLength(index, c.info().index, len));
+                }
+            }
+        }
+        for r in self.archetype_map.iter() {
+            let ar = r.value();
+            let ok = ar.id() == *r.key()
+                && match self.archetype_arr.get(ar.index().index()) {
+                    Some(a) => Share::ptr_eq(a, ar),
+                    None => false,
+                };
+            if !ok {
+                errors.push(ValidationError::ArchetypeMap(*r.key(), ar.index()));
+            }
+        }
+    }
+    // 检查事件监听器的读取位置
+    fn validate_events(&self, errors: &mut Vec<ValidationError>) {
+        for er in self.event_map.values() {
+            if let Some((listener, read, len)) = er.check_listeners() {
+                errors.push(ValidationError::ListenerOutOfRange {
+                    event: er.mem_info().0.to_string(),
+                    listener,
+                    read,
+                    len,
+                });
+            }
+        }
+        for c in self.component_arr.iter() {
+            for er in [&c.info.changed, &c.info.added, &c.info.removed].into_iter().flatten() {
+                if let Some((listener, read, len)) = er.check_listeners() {
+                    errors.push(ValidationError::ListenerOutOfRange {
+                        event: er.name().to_string(),
+                        listener,
+                        read,
+                        len,
+                    });
+                }
+            }
+        }
+    }
+}
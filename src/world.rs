@@ -15,7 +15,7 @@ use crate::alter::{AlterState, QueryAlterState};
 use crate::archetype::{
     Archetype, ArchetypeIndex, ArchetypeInfo, ComponentInfo, Row, ShareArchetype,
 };
-use crate::column::{BlobRef, Column};
+use crate::column::{BlobRef, Column, HookQueue};
 #[cfg(debug_assertions)]
 use crate::column::{ARCHETYPE_INDEX, COMPONENT_INDEX};
 use crate::editor::{EditorState, EntityEditor};
@@ -26,8 +26,10 @@ use crate::listener::{EventListKey, ListenerMgr};
 use crate::multi_res::ResVec;
 use crate::prelude::Mut;
 use crate::query::{QueryError, QueryState};
-use crate::single_res::TickRes;
-use crate::system::{SystemMeta, TypeInfo};
+use crate::memory::{MemoryReport, ResourceMemory};
+use crate::single_res::{Resource, TickRes};
+use crate::pipe::SystemInput;
+use crate::system::{IntoSystem, RunSystem, RunSystemError, SystemId, SystemMeta, TypeInfo};
 use crate::world_ptr::Ptr;
 use core::fmt::*;
 use core::result::Result;
@@ -36,7 +38,7 @@ use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use fixedbitset::FixedBitSet;
 use pi_append_vec::{SafeVec, SafeVecIter};
-use pi_key_alloter::new_key_type;
+use pi_key_alloter::{key_data, Key, KeyData};
 use std::any::{Any, TypeId};
 use std::borrow::Cow;
 use std::collections::{hash_map::Entry as StdEntry, HashMap};
@@ -50,9 +52,41 @@ use pi_null::Null;
 use pi_share::{Share, ShareUsize};
 use pi_slot::{Iter, SlotMap};
 
-new_key_type! {
-    pub struct Entity;
+/// 实体，Debug在实体有名称时一并显示名称，见`name`模块
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct Entity(KeyData);
+impl From<KeyData> for Entity {
+    #[inline(always)]
+    fn from(k: KeyData) -> Self {
+        Entity(k)
+    }
+}
+impl Null for Entity {
+    #[inline(always)]
+    fn null() -> Self {
+        KeyData::null().into()
+    }
+    #[inline(always)]
+    fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+}
+impl Key for Entity {
+    #[inline(always)]
+    fn with(idx: usize) -> Self {
+        Entity(unsafe { key_data(idx as u32, 0) })
+    }
+    #[inline(always)]
+    fn data(&self) -> KeyData {
+        self.0
+    }
+    #[inline(always)]
+    fn index(&self) -> usize {
+        self.0.index() as usize
+    }
 }
+pi_key_alloter::__serialize_key!(Entity);
 
 #[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ComponentIndex(pub(crate) u32);
@@ -79,6 +113,11 @@ impl pi_null::Null for ComponentIndex {
         self.0 == u32::null()
     }
 }
+/// tick的最大有效年龄，更旧的tick会被[`World::check_change_ticks`]钳制
+pub const MAX_CHANGE_AGE: u32 = 1 << 30;
+/// 每隔多少tick，检查一次过旧的tick
+pub const CHECK_TICK_THRESHOLD: u32 = 1 << 29;
+
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Tick(u32);
 impl Tick {
@@ -88,6 +127,33 @@ impl Tick {
     pub fn max() -> Self {
         Self(u32::MAX)
     }
+    /// 是否比other新，考虑了tick回绕。
+    /// 两个tick的差需要小于2^31，由定期的[`World::check_change_ticks`]保证。
+    /// null的tick表示未设置（如从未运行过的system的last_run），比所有的tick都旧
+    #[inline(always)]
+    pub fn is_newer_than(self, other: Tick) -> bool {
+        if self.is_null() {
+            return false;
+        }
+        if other.is_null() {
+            return true;
+        }
+        (self.0.wrapping_sub(other.0) as i32) > 0
+    }
+    /// 如果比now旧了MAX_CHANGE_AGE以上，则钳制到now - MAX_CHANGE_AGE，返回是否被钳制。
+    /// null的tick不钳制
+    #[inline]
+    pub fn check_tick(&mut self, now: Tick) -> bool {
+        if !self.is_null() && now.0.wrapping_sub(self.0) > MAX_CHANGE_AGE {
+            self.0 = now.0.wrapping_sub(MAX_CHANGE_AGE);
+            if self.0 == 0 {
+                self.0 = 1;
+            }
+            true
+        } else {
+            false
+        }
+    }
 }
 impl Deref for Tick {
     type Target = u32;
@@ -127,17 +193,28 @@ pub struct World {
     pub(crate) event_map: HashMap<TypeId, Share<dyn Settle>>, // 事件表
     pub(crate) component_map: HashMap<TypeId, ComponentIndex>,
     pub(crate) component_arr: Vec<Share<Column>>,
+    pub(crate) sparse_columns: Vec<Share<Column>>, // 稀疏存储的组件列，实体销毁时需要清理
     pub(crate) entities: SlotMap<Entity, EntityAddr>,
     pub(crate) archetype_map: DashMap<u64, ShareArchetype>,
     pub(crate) archetype_arr: SafeVec<ShareArchetype>,
     pub(crate) archetype_arr_len: usize,
+    // 增量整理时，下次开始整理的原型位置
+    settle_cursor: usize,
+    // debug模式下，是否每次整理后检查一致性
+    validate_on_settle: bool,
     pub(crate) empty_archetype: ShareArchetype,
     pub(crate) entity_editor_state: EditorState,
     pub(crate) listener_mgr: ListenerMgr,
+    pub(crate) systems: Vec<Option<Box<dyn RunSystem<Out = ()>>>>, // 注册的一次性system
+    pub(crate) system_queue: SafeVec<SystemId>, // 等待运行的一次性system
+    pub(crate) hook_queue: Share<HookQueue>, // 等待运行的组件钩子
+    pub(crate) index_syncs: Vec<fn(&World)>, // 注册的组件索引的同步函数
     archetype_init_key: EventListKey,
     archetype_ok_key: EventListKey,
     // 世界当前的tick
     tick: ShareUsize,
+    // 上次检查过旧tick时的tick
+    last_check_tick: Tick,
     default_system_meta: SystemMeta,
 }
 impl Debug for World {
@@ -195,14 +272,22 @@ impl World {
             entities: Default::default(),
             component_map: Default::default(),
             component_arr: Default::default(),
+            sparse_columns: Default::default(),
             archetype_map,
             archetype_arr,
             archetype_arr_len: 1,
+            settle_cursor: 0,
+            validate_on_settle: false,
             empty_archetype,
             listener_mgr,
+            systems: Default::default(),
+            system_queue: Default::default(),
+            hook_queue: Default::default(),
+            index_syncs: Vec::new(),
             archetype_init_key,
             archetype_ok_key,
             tick: ShareUsize::new(1),
+            last_check_tick: Tick(1),
             entity_editor_state: Default::default(),
             default_system_meta: SystemMeta::new(TypeInfo::of::<()>()),
         }
@@ -213,7 +298,42 @@ impl World {
     }
     // 递增世界当前的tick，一般是每执行图执行时递增
     pub fn increment_tick(&self) -> Tick {
-        self.tick.fetch_add(1, Ordering::Relaxed).into()
+        let tick = self.tick.fetch_add(1, Ordering::Relaxed);
+        // 0为null的tick，回绕时跳过
+        if (tick.wrapping_add(1) as u32) == 0 {
+            self.tick.fetch_add(1, Ordering::Relaxed);
+        }
+        tick.into()
+    }
+    /// 设置世界当前的tick，用于测试tick回绕
+    #[doc(hidden)]
+    pub fn set_tick(&self, tick: Tick) {
+        self.tick.store(tick.index(), Ordering::Relaxed);
+    }
+    /// 距离上次检查，是否已超过CHECK_TICK_THRESHOLD
+    pub fn need_check_change_ticks(&self) -> bool {
+        self.tick().0.wrapping_sub(self.last_check_tick.0) >= CHECK_TICK_THRESHOLD
+    }
+    /// 钳制所有过旧的tick（组件的tick、单例资源的tick、一次性system的last_run），
+    /// 避免tick回绕后误判变化。Schedule中system的last_run由各个Schedule运行时自行钳制
+    pub fn check_change_ticks(&mut self) {
+        let now = self.tick();
+        for c in self.component_arr.iter() {
+            if c.info().is_tick() {
+                c.check_change_ticks(&self.archetype_arr, now);
+            }
+        }
+        for r in self.single_res_arr.iter_mut().flatten() {
+            let mut tick = r.get_tick();
+            if tick.check_tick(now) {
+                unsafe { Share::get_mut_unchecked(r) }.set_tick(tick);
+            }
+        }
+        for s in self.systems.iter_mut().flatten() {
+            s.check_change_ticks(now);
+        }
+        self.default_system_meta.check_change_ticks(now);
+        self.last_check_tick = now;
     }
     // /// 批量插入
     // pub fn batch_insert<'w, I, Ins>(&'w mut self, iter: I) -> InsertBatchIter<'w, I, Ins>
@@ -282,7 +402,14 @@ impl World {
             StdEntry::Vacant(entry) => {
                 let index = self.component_arr.len().into();
                 info.index = index;
-                let c = Share::new(Column::new(info));
+                let mut column = Column::new(info);
+                if column.info.has_hooks() {
+                    column.info.hook_queue = Some(self.hook_queue.clone());
+                }
+                let c = Share::new(column);
+                if c.info().is_sparse() {
+                    self.sparse_columns.push(c.clone());
+                }
                 self.component_arr.push(c.clone());
                 entry.insert(index);
                 return (index, c);
@@ -305,20 +432,24 @@ impl World {
     pub fn init_component<T: 'static>(&mut self) -> ComponentIndex {
         self.add_component_info(ComponentInfo::of::<T>(0)).0
     }
-    /// 计算所有原型信息，设置了所有组件的索引，按索引大小进行排序
+    /// 计算所有原型信息，设置了所有组件的索引，按索引大小进行排序，稀疏存储的组件不在原型上
     pub(crate) fn archetype_info(&mut self, components: Vec<ComponentInfo>) -> ArchetypeInfo {
         let vec: Vec<Share<Column>> = components
             .into_iter()
             .map(|c| self.add_component_info(c).1)
+            .filter(|c| !c.info().is_sparse())
             .collect();
         ArchetypeInfo::sort(vec)
     }
     /// 创建一个插入器
     pub fn make_insert<B: Bundle>(&mut self) -> InsertState<B> {
-        let components = B::components(Vec::new());
+        let mut components = B::components(Vec::new());
+        let required = ComponentInfo::add_required(&mut components);
         let ar = self.find_ar(components);
         let s = B::init_item(self, &ar);
-        InsertState::new(ar, s, Ptr::new(&mut self.default_system_meta), Ptr::new(self))
+        let mut state = InsertState::new(ar, s, Ptr::new(&mut self.default_system_meta), Ptr::new(self));
+        state.set_required(self, required);
+        state
     }
     /// 兼容bevy的接口，提供query
     pub fn query<Q: FetchComponents + 'static, F: FilterComponents + 'static = ()>(
@@ -375,7 +506,7 @@ impl World {
         self.entities.iter()
     }
     /// 插入指定的单例资源，返回索引
-    pub fn insert_single_res<T: 'static>(&mut self, value: T) -> usize {
+    pub fn insert_single_res<T: Resource>(&mut self, value: T) -> usize {
         let tid = TypeId::of::<T>();
         let (flag, index) = self._insert_single_res(tid);
         if flag {
@@ -619,7 +750,7 @@ impl World {
         // }
         match self._get_component_by_index(e, index) {
             Ok((c, addr)) => match c {
-                Some(c) => Ok(c.get::<T>(addr.row, e)),
+                Some((c, row)) => Ok(c.get::<T>(row, e)),
                 None => Err(QueryError::MissingComponent(index, addr.archetype_index())),
             },
             Err(e) => Err(e),
@@ -630,7 +761,7 @@ impl World {
         &'w self,
         e: Entity,
         index: ComponentIndex,
-    ) -> Result<(Option<BlobRef<'_>>, &'w EntityAddr), QueryError> {
+    ) -> Result<(Option<(BlobRef<'_>, Row)>, &'w EntityAddr), QueryError> {
         let addr = match self.entities.get(e) {
             Some(v) => v,
             None => return Err(QueryError::NoSuchEntity(e)),
@@ -639,7 +770,7 @@ impl World {
             Some(c) => c,
             None => return Err(QueryError::NoSuchComponent(index)),
         };
-        let column = column.blob_ref(addr.archetype_index());
+        let column = column.entity_ref(addr.archetype_index(), addr.row, e);
         return Ok((column, addr));
     }
     /// 获得指定实体的指定组件
@@ -656,11 +787,11 @@ impl World {
             Some(c) => c,
             None => return Err(QueryError::NoSuchComponent(index)),
         };
-        let column = column.blob_ref(addr.archetype_index());
+        let column = column.entity_ref(addr.archetype_index(), addr.row, e);
         match column {
-            Some(c) => {
+            Some((c, row)) => {
                 let t = self.tick();
-                let value: Mut<T> = Mut::new(&ColumnTick::new(c, t, t), e, addr.row);
+                let value: Mut<T> = Mut::new(&ColumnTick::new(c, t, t), e, row);
                 Ok(unsafe { transmute(value) })
             },
             None => Err(QueryError::MissingComponent(index, addr.archetype_index())),
@@ -746,6 +877,7 @@ impl World {
         };
         if addr.row.is_null() {
             self.entities.remove(e).unwrap();
+            self.remove_sparse(e);
             return Ok(());
         }
         let ar = unsafe {
@@ -757,9 +889,75 @@ impl World {
             return Err(QueryError::NoSuchRow(addr.row));
         }
         self.entities.remove(e).unwrap();
+        self.remove_sparse(e);
         Ok(())
     }
+    /// 移除实体上所有稀疏存储的组件
+    pub(crate) fn remove_sparse(&self, e: Entity) {
+        for c in self.sparse_columns.iter() {
+            c.sparse_blob_ref().sparse_remove(e);
+        }
+    }
 
+    /// 注册一次性system，system立即初始化，但不会被调度，只在调用run_system时运行
+    pub fn register_system<M, S: IntoSystem<M, ()>>(&mut self, system: S) -> SystemId
+    where
+        S::System: SystemInput<Input = ()>,
+    {
+        let mut system = system.into_system();
+        system.initialize(self);
+        let id = SystemId(self.systems.len());
+        self.systems.push(Some(Box::new(system)));
+        id
+    }
+    /// 运行指定的一次性system
+    pub fn run_system(&mut self, id: SystemId) -> Result<(), RunSystemError> {
+        let mut system = match self.systems.get_mut(id.0) {
+            Some(r) => match r.take() {
+                Some(s) => s,
+                None => return Err(RunSystemError::Running(id)),
+            },
+            None => return Err(RunSystemError::NoSuchSystem(id)),
+        };
+        self.sync_indexes();
+        // system运行时panic，也要放回，否则以后一直返回Running
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            system.align(self);
+            system.run(self);
+        }));
+        self.systems[id.0] = Some(system);
+        if let Err(e) = r {
+            std::panic::resume_unwind(e);
+        }
+        Ok(())
+    }
+    /// 将一次性system放入等待队列，由run_queued_systems运行
+    pub fn queue_system(&self, id: SystemId) {
+        self.system_queue.insert(id);
+    }
+    /// 运行组件的添加及移除钩子，钩子内再添加或移除的组件，其钩子也在本次运行
+    pub fn run_hooks(&mut self) {
+        while self.hook_queue.len() > 0 {
+            let hooks = self.hook_queue.iter().map(|r| *r).collect::<Vec<_>>();
+            unsafe { Share::get_mut_unchecked(&mut self.hook_queue) }.clear(0);
+            for (hook, e) in hooks {
+                hook(self, e);
+            }
+        }
+    }
+    /// 运行等待队列中的一次性system，运行时新加入队列的system在下次调用时运行
+    pub fn run_queued_systems(&mut self) {
+        if self.system_queue.len() == 0 {
+            return;
+        }
+        let ids = self.system_queue.iter().map(|r| *r).collect::<Vec<SystemId>>();
+        self.system_queue.clear(0);
+        for id in ids {
+            if let Err(e) = self.run_system(id) {
+                log::warn!("run queued system failed, {:?}", e);
+            }
+        }
+    }
     /// 创建一个新的空实体
     pub fn spawn_empty(&self) -> Entity {
         self.entities
@@ -771,6 +969,10 @@ impl World {
         let addr = unsafe { self.entities.load_unchecked(e) };
         addr.row = row;
     }
+    /// 获得内存报告，包括每个原型、每个组件、事件、监听器、单例资源及实体表的内存大小
+    pub fn memory_report(&self) -> MemoryReport {
+        MemoryReport::new(self)
+    }
     /// 获得内存大小
     pub fn mem_size(&self) -> usize {
         let mut size = self.entities.mem_size();
@@ -794,12 +996,102 @@ impl World {
     pub fn settle(&mut self) {
         self.settle_by(&mut Vec::new(), &mut FixedBitSet::new())
     }
+    /// 设置debug模式下，是否在每次整理后调用validate检查一致性，检查失败则panic
+    pub fn set_validate_on_settle(&mut self, value: bool) {
+        self.validate_on_settle = value;
+    }
+    // debug模式下，整理后检查一致性
+    fn validate_after_settle(&self) {
+        #[cfg(debug_assertions)]
+        if self.validate_on_settle {
+            if let Err(errors) = self.validate() {
+                let list: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                panic!("world validate failed after settle:\n{}", list.join("\n"));
+            }
+        }
+    }
+    /// 整理并释放所有原型未使用的内存，一般在大量实体销毁后调用（如卸载关卡），必须保证调用时没有其他线程读写world
+    pub fn shrink_to_fit(&mut self) {
+        self.settle();
+        for ar in self.archetype_arr.iter() {
+            let archetype = unsafe { Share::get_mut_unchecked(ar) };
+            archetype.shrink_to_fit();
+        }
+        for c in self.sparse_columns.iter_mut() {
+            let c = unsafe { Share::get_mut_unchecked(c) };
+            c.shrink_to_fit();
+        }
+    }
+    /// 为指定Bundle的原型预分配n个实体的内存，在批量创建实体前调用，必须保证调用时没有其他线程读写world
+    pub fn reserve<B: Bundle>(&mut self, n: usize) {
+        let mut components = B::components(Vec::new());
+        ComponentInfo::add_required(&mut components);
+        let mut ar = self.find_ar(components);
+        let archetype = unsafe { Share::get_mut_unchecked(&mut ar) };
+        archetype.reserve(n);
+    }
     /// 只有全部的插件都注册完毕，准备开始运行前调用。如果单独有个注册过程，则add_component_info等都使用&mut self。 则可以使用普通vec，不再需要整理
     pub fn init_ok(&mut self) {
         // todo 整理 self.listener_mgr.settle(0);
     }
     /// 只有主调度完毕后，才能调用的整理方法，必须保证调用时没有其他线程读写world
     pub fn settle_by(&mut self, action: &mut Vec<(Row, Row)>, set: &mut FixedBitSet) {
+        self.settle_base();
+        // 整理每个原型
+        // #[cfg(not(feature="rc"))]
+        for ar in self.archetype_arr.iter() {
+            let archetype = unsafe { Share::get_mut_unchecked(ar) };
+            archetype.settle(self, action, set);
+        }
+        self.validate_after_settle();
+    }
+    /// 增量整理，每次最多整理budget行（至少整理一个原型），返回是否全部整理完毕。
+    /// 被移除行最多的原型优先，其余按轮转顺序整理，未整理的原型在下次调用时继续。
+    /// 未整理的原型依然可被Query、Alter等正确访问，只是保留了被移除的空行及未合并的内存。
+    /// 与settle相同，必须保证调用时没有其他线程读写world
+    pub fn settle_incremental(&mut self, budget: usize) -> bool {
+        self.settle_incremental_by(budget, &mut Vec::new(), &mut FixedBitSet::new())
+    }
+    /// 增量整理，使用外部提供的临时数组
+    pub fn settle_incremental_by(
+        &mut self,
+        budget: usize,
+        action: &mut Vec<(Row, Row)>,
+        set: &mut FixedBitSet,
+    ) -> bool {
+        self.settle_base();
+        let len = self.archetype_arr.len();
+        if len == 0 {
+            return true;
+        }
+        // 收集需要整理的原型，(被移除的行数, 距离游标的轮转距离, 原型索引)
+        let mut list: Vec<(usize, usize, usize)> = self
+            .archetype_arr
+            .iter()
+            .enumerate()
+            .filter(|(_, ar)| ar.need_settle())
+            .map(|(i, ar)| (ar.removes.len(), (i + len - self.settle_cursor % len) % len, i))
+            .collect();
+        list.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        let mut cost = 0;
+        let mut count = 0;
+        for (_, _, i) in list.iter() {
+            if count > 0 && cost >= budget {
+                break;
+            }
+            let ar = unsafe { self.archetype_arr.load_unchecked(*i) };
+            // 整理的消耗，按原型的行数估算
+            cost += ar.len().index().max(1);
+            count += 1;
+            let archetype = unsafe { Share::get_mut_unchecked(ar) };
+            archetype.settle(self, action, set);
+            self.settle_cursor = *i + 1;
+        }
+        self.validate_after_settle();
+        count == list.len()
+    }
+    // 整理实体、原型数组、列及事件列表，这些整理的消耗很小，每次都执行
+    fn settle_base(&mut self) {
         // 整理实体
         self.entities.settle(0);
         // 整理原型数组
@@ -813,17 +1105,16 @@ impl World {
             }
             self.archetype_arr_len = len;
         }
+        // 稀疏存储的列不随原型增加，每次都整理
+        for c in self.sparse_columns.iter_mut() {
+            let c = unsafe { Share::get_mut_unchecked(c) };
+            c.settle();
+        }
         // 整理事件列表
         for aer in self.event_map.values_mut() {
             let er = unsafe { Share::get_mut_unchecked(aer) };
             er.settle();
         }
-        // 整理每个原型
-        // #[cfg(not(feature="rc"))]
-        for ar in self.archetype_arr.iter() {
-            let archetype = unsafe { Share::get_mut_unchecked(ar) };
-            archetype.settle(self, action, set);
-        }
     }
 }
 unsafe impl Send for World {}
@@ -844,10 +1135,20 @@ pub trait Downcast {
 pub trait TickMut: Downcast {
     fn get_tick(&self) -> Tick;
     fn set_tick(&mut self, tick: Tick);
+    /// 资源的内存信息
+    fn mem_info(&self) -> ResourceMemory;
 }
 
 pub trait Settle: Downcast {
     fn settle(&mut self);
+    /// 名称及内存大小
+    fn mem_info(&self) -> (&str, usize) {
+        ("", 0)
+    }
+    /// 检查监听器的读取位置，返回第一个超出范围的监听器(index, 读取位置, 长度)
+    fn check_listeners(&self) -> Option<(usize, usize, usize)> {
+        None
+    }
 }
 
 /// Creates an instance of the type this trait is implemented for
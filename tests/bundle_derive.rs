@@ -0,0 +1,69 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pos(pub f32);
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Size(pub f32);
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Color(pub u32);
+
+#[derive(Bundle, Default)]
+pub struct LayoutBundle {
+    pub pos: Pos,
+    pub size: Size,
+}
+
+#[derive(Bundle, Default)]
+pub struct NodeBundle {
+    #[bundle]
+    pub layout: LayoutBundle,
+    pub color: Color,
+    #[bundle(ignore)]
+    pub debug_name: String,
+}
+
+#[derive(Bundle, Default)]
+pub struct BadBundle {
+    #[bundle]
+    pub layout: LayoutBundle,
+    #[bundle]
+    pub node: NodeBundle,
+}
+
+#[test]
+fn test_nested() {
+    let mut world = World::create();
+    let i = world.make_insert::<NodeBundle>();
+    let e = i.insert(
+        &world,
+        NodeBundle {
+            layout: LayoutBundle { pos: Pos(1.0), size: Size(2.0) },
+            color: Color(3),
+            debug_name: "node".to_string(),
+        },
+    );
+    assert_eq!(world.get_component::<Pos>(e).unwrap(), &Pos(1.0));
+    assert_eq!(world.get_component::<Size>(e).unwrap(), &Size(2.0));
+    assert_eq!(world.get_component::<Color>(e).unwrap(), &Color(3));
+    assert!(world.get_component::<String>(e).is_err());
+
+    let mut state = world.make_query::<(&Pos, &Size, &Color), ()>();
+    assert_eq!(state.get_param(&world).iter().count(), 1);
+}
+
+#[test]
+fn test_paths() {
+    let mut paths = Vec::new();
+    <NodeBundle as Bundle>::component_paths("NodeBundle".to_string(), &mut paths);
+    let paths = paths.into_iter().map(|(_, p)| p).collect::<Vec<_>>();
+    assert_eq!(paths, ["NodeBundle.layout.pos", "NodeBundle.layout.size", "NodeBundle.color"]);
+}
+
+#[test]
+#[should_panic(expected = "`BadBundle.layout.pos` and `BadBundle.node.layout.pos`")]
+fn test_duplicate() {
+    let mut world = World::create();
+    world.make_insert::<BadBundle>();
+}
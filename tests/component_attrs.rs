@@ -0,0 +1,135 @@
+use pi_world::{archetype::StorageKind, prelude::*};
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pos(pub f32);
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Vel(pub f32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[component(require(Pos, Vel))]
+pub struct Player(pub u32);
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+#[component(require(Vel))]
+pub struct Boost(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[component(on_add = hp_added, on_remove = hp_removed)]
+pub struct Hp(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[component(storage = "sparse", tick)]
+pub struct Marker;
+
+#[derive(Debug, Default, Resource)]
+pub struct HookLog(Vec<(bool, Entity)>);
+
+fn hp_added(world: &mut World, e: Entity) {
+    world.get_single_res_mut::<HookLog>().unwrap().0.push((true, e));
+}
+fn hp_removed(world: &mut World, e: Entity) {
+    world.get_single_res_mut::<HookLog>().unwrap().0.push((false, e));
+}
+
+#[test]
+fn test_config() {
+    let mut world = World::create();
+    let index = world.init_component::<Marker>();
+    let info = world.get_column(index).unwrap().info();
+    assert_eq!(info.storage, StorageKind::Sparse);
+    assert!(info.is_tick());
+    let index = world.init_component::<Pos>();
+    let info = world.get_column(index).unwrap().info();
+    assert_eq!(info.storage, StorageKind::Table);
+    assert!(!info.is_tick());
+}
+
+#[test]
+fn test_required() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Player,)>();
+    let e = i.insert(&world, (Player(1),));
+    assert_eq!(world.get_component::<Player>(e).unwrap(), &Player(1));
+    assert_eq!(world.get_component::<Pos>(e).unwrap(), &Pos(0.0));
+    assert_eq!(world.get_component::<Vel>(e).unwrap(), &Vel(0.0));
+    // Bundle中已有的必需组件不会被覆盖
+    let i = world.make_insert::<(Player, Pos)>();
+    let e = i.insert(&world, (Player(2), Pos(5.0)));
+    assert_eq!(world.get_component::<Pos>(e).unwrap(), &Pos(5.0));
+    assert_eq!(world.get_component::<Vel>(e).unwrap(), &Vel(0.0));
+}
+
+#[test]
+fn test_required_alter() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos,)>();
+    let e1 = i.insert(&world, (Pos(7.0),));
+    let i = world.make_insert::<(Pos, Vel)>();
+    let e2 = i.insert(&world, (Pos(8.0), Vel(3.0)));
+    let mut alter = world.make_alter::<(), (), (Player,), ()>();
+    let mut param = alter.get_param(&world);
+    param.alter(e1, (Player(1),)).unwrap();
+    param.alter(e2, (Player(2),)).unwrap();
+    drop(param);
+    assert_eq!(world.get_component::<Player>(e1).unwrap(), &Player(1));
+    assert_eq!(world.get_component::<Pos>(e1).unwrap(), &Pos(7.0));
+    assert_eq!(world.get_component::<Vel>(e1).unwrap(), &Vel(0.0));
+    // 实体上已有的必需组件不会被覆盖
+    assert_eq!(world.get_component::<Pos>(e2).unwrap(), &Pos(8.0));
+    assert_eq!(world.get_component::<Vel>(e2).unwrap(), &Vel(3.0));
+    // 同时移除的必需组件不会被添加
+    let e3 = world.make_insert::<(Pos,)>().insert(&world, (Pos(9.0),));
+    let mut alter = world.make_alter::<(), (), (Player,), (Pos,)>();
+    alter.get_param(&world).alter(e3, (Player(3),)).unwrap();
+    assert!(world.get_component::<Pos>(e3).is_err());
+    assert_eq!(world.get_component::<Vel>(e3).unwrap(), &Vel(0.0));
+    assert!(world.validate().is_ok());
+}
+
+#[test]
+fn test_required_editor() {
+    let mut world = World::create();
+    let boost = world.init_component::<Boost>();
+    let e = world.make_insert::<(Pos,)>().insert(&world, (Pos(1.0),));
+    let mut editor = EntityEditor::new(&mut world);
+    editor.add_components_by_index(e, &[boost]).unwrap();
+    assert_eq!(world.get_component::<Boost>(e).unwrap(), &Boost(0));
+    assert_eq!(world.get_component::<Pos>(e).unwrap(), &Pos(1.0));
+    assert_eq!(world.get_component::<Vel>(e).unwrap(), &Vel(0.0));
+}
+
+#[test]
+fn test_hooks() {
+    let mut world = World::create();
+    world.insert_single_res(HookLog::default());
+    let i = world.make_insert::<(Hp,)>();
+    let e1 = i.insert(&world, (Hp(1),));
+    let e2 = i.insert(&world, (Hp(2),));
+    // 钩子延迟到run_hooks时运行
+    assert!(world.get_single_res::<HookLog>().unwrap().0.is_empty());
+    world.run_hooks();
+    assert_eq!(world.get_single_res::<HookLog>().unwrap().0, vec![(true, e1), (true, e2)]);
+
+    let mut alter = world.make_alter::<(), (), (), (Hp,)>();
+    alter.get_param(&world).alter(e1, ()).unwrap();
+    world.destroy_entity(e2).unwrap();
+    world.run_hooks();
+    assert_eq!(
+        world.get_single_res::<HookLog>().unwrap().0[2..],
+        [(false, e1), (false, e2)]
+    );
+}
+
+#[test]
+fn test_hooks_in_schedule() {
+    fn spawn(i: Insert<(Hp,)>) {
+        i.insert((Hp(1),));
+    }
+    let mut app = App::new();
+    app.world.insert_single_res(HookLog::default());
+    app.add_system(Update, spawn);
+    app.run();
+    app.run();
+    assert_eq!(app.world.get_single_res::<HookLog>().unwrap().0.len(), 2);
+}
@@ -0,0 +1,45 @@
+use pi_world::prelude::*;
+
+#[derive(Component)]
+pub struct Hp(pub u32);
+
+// 第二次运行时改名
+pub fn rename(mut q: Query<&mut Name>, mut runs: Local<usize>) {
+    *runs += 1;
+    if *runs != 2 {
+        return;
+    }
+    for mut n in q.iter_mut() {
+        if n.as_str() == "player" {
+            n.set("hero");
+        }
+    }
+}
+
+// 名称表是全局的，单独放在一个测试中，避免与其他World的实体键冲突
+#[test]
+fn test_entity_debug() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(Name, Hp)>();
+    let e1 = i.insert(&app.world, (Name::new("player"), Hp(1)));
+    let i = app.world.make_insert::<(Hp,)>();
+    let e2 = i.insert(&app.world, (Hp(2),));
+    // 添加钩子运行前，名称还未同步
+    assert!(!format!("{:?}", e1).contains("player"));
+
+    app.add_system(Update, rename);
+    app.run();
+    assert!(format!("{:?}", e1).ends_with(" \"player\")"));
+    assert!(!format!("{:?}", e2).contains('"'));
+    assert_eq!(format!("{:?}", e1), format!("{:?}", app.world.entity_debug(e1)));
+
+    // 改名后，下次同步索引时更新
+    app.run();
+    app.run();
+    assert!(format!("{:?}", e1).ends_with(" \"hero\")"));
+
+    let mut q = app.world.make_query::<(Entity, ArchetypeName), ()>();
+    for (e, a) in q.iter(&app.world) {
+        assert_eq!(a.entity_name(&app.world).map(|n| n.as_str()), app.world.entity_name(e).map(|n| n.as_str()));
+    }
+}
@@ -0,0 +1,64 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct NetId(pub u32);
+
+#[derive(Default, Resource)]
+pub struct Found(pub Vec<Option<Entity>>);
+
+pub fn change(mut q: Query<&mut NetId>) {
+    for mut n in q.iter_mut() {
+        if n.0 == 2 {
+            n.0 = 20;
+        }
+    }
+}
+
+pub fn lookup(index: Indexed<u32, NetId>, mut found: SingleResMut<Found>) {
+    found.0 = vec![index.get(&1u32), index.get(&2u32), index.get(&20u32)];
+}
+
+#[test]
+fn test_index() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(NetId,)>();
+    let e1 = i.insert(&app.world, (NetId(1),));
+    let e2 = i.insert(&app.world, (NetId(2),));
+    app.world.register_index(|c: &NetId| c.0);
+    app.world.insert_single_res(Found::default());
+    app.add_system(Update, change);
+    app.add_system(Update, lookup);
+
+    app.run();
+    app.run();
+    assert_eq!(app.world.get_single_res::<Found>().unwrap().0, vec![Some(e1), None, Some(e2)]);
+
+    // 销毁后，不能再查到
+    app.world.destroy_entity(e1).unwrap();
+    app.run();
+    assert_eq!(app.world.get_single_res::<Found>().unwrap().0, vec![None, None, Some(e2)]);
+
+    // 新插入的实体
+    let e3 = i.insert(&app.world, (NetId(1),));
+    app.run();
+    assert_eq!(app.world.get_single_res::<Found>().unwrap().0, vec![Some(e3), None, Some(e2)]);
+}
+
+pub fn lookup_post(index: Indexed<u32, NetId>, mut found: SingleResMut<Found>) {
+    found.0 = vec![index.get(&2u32), index.get(&20u32)];
+}
+
+#[test]
+fn test_index_sync_per_stage() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(NetId,)>();
+    let e = i.insert(&app.world, (NetId(2),));
+    app.world.register_index(|c: &NetId| c.0);
+    app.world.insert_single_res(Found::default());
+    app.add_system(Update, change);
+    app.add_system(PostUpdate, lookup_post);
+
+    // 下一个阶段运行前同步了索引，能查到本次运行的修改
+    app.run();
+    assert_eq!(app.world.get_single_res::<Found>().unwrap().0, vec![None, Some(e)]);
+}
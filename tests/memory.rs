@@ -0,0 +1,46 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Default, Clone, Debug)]
+pub struct Pos(pub f32, pub f32);
+#[derive(Component, Default, Clone, Debug)]
+pub struct Vel(pub f32, pub f32);
+
+#[derive(Resource)]
+pub struct Names(pub Vec<String>);
+impl MemSize for Names {
+    fn mem_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.0.iter().map(|s| s.capacity()).sum::<usize>()
+    }
+}
+#[derive(Resource)]
+pub struct Counter(pub usize);
+
+#[test]
+fn test_memory_report() {
+    let mut app = App::new();
+    app.world.insert_single_res(Names(vec!["a".to_string(), "bb".to_string()]));
+    app.world.insert_single_res(Counter(0));
+    let i = app.world.make_insert::<(Pos, Vel)>();
+    for _ in 0..10 {
+        i.insert(&app.world, (Pos(0.0, 0.0), Vel(1.0, 1.0)));
+    }
+    app.run();
+
+    let report = app.world.memory_report();
+    assert_eq!(report.entities, 10);
+    let ar = report.archetypes.iter().find(|a| a.entities == 10).unwrap();
+    assert_eq!(ar.columns.len(), 2);
+    assert!(ar.capacity >= 10);
+    assert!(report.components.iter().any(|c| c.name.contains("Pos") && c.archetypes == 1));
+
+    let names = report.resources.iter().find(|r| r.name.contains("Names")).unwrap();
+    assert!(names.deep);
+    let counter = report.resources.iter().find(|r| r.name.contains("Counter")).unwrap();
+    assert!(!counter.deep);
+    assert_eq!(counter.size, std::mem::size_of::<Counter>());
+    assert!(report.total() > 0);
+
+    let json = report.to_json();
+    assert!(json.starts_with("{\"total\":"));
+    assert!(json.contains("\"resources\":["));
+}
@@ -0,0 +1,76 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Hp(pub u32);
+
+#[derive(Default, Resource)]
+pub struct Found(pub Option<Entity>, pub usize);
+
+pub fn rename(mut q: Query<&mut Name>) {
+    for mut n in q.iter_mut() {
+        if n.as_str() == "b" {
+            n.set("c");
+        }
+    }
+}
+
+pub fn lookup(names: Names, mut found: SingleResMut<Found>) {
+    found.0 = names.get("c");
+    found.1 = names.get_all("a").len();
+}
+
+#[test]
+fn test_name() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(Name, Hp)>();
+    let e1 = i.insert(&app.world, (Name::new("a"), Hp(1)));
+    let e2 = i.insert(&app.world, (Name::new("b"), Hp(2)));
+    let e3 = i.insert(&app.world, (Name::new("a"), Hp(3)));
+    let i = app.world.make_insert::<(Hp,)>();
+    let e4 = i.insert(&app.world, (Hp(4),));
+
+    assert_eq!(app.world.find_by_name("b"), Some(e2));
+    assert_eq!(app.world.find_all_by_name("a").len(), 2);
+    assert_eq!(app.world.find_by_name("x"), None);
+    assert!(format!("{:?}", app.world.entity_debug(e1)).ends_with(" \"a\")"));
+    assert!(!format!("{:?}", app.world.entity_debug(e4)).contains('"'));
+
+    app.world.insert_single_res(Found::default());
+    app.add_system(Update, rename);
+    app.add_system(Update, lookup);
+    app.run();
+    app.run();
+    assert_eq!(app.world.get_single_res::<Found>().unwrap().0, Some(e2));
+    assert_eq!(app.world.get_single_res::<Found>().unwrap().1, 2);
+    assert_eq!(app.world.find_by_name("b"), None);
+
+    app.world.destroy_entity(e3).unwrap();
+    assert_eq!(app.world.find_all_by_name("a"), vec![e1]);
+
+    let mut q = app.world.make_query::<(Entity, ArchetypeName), ()>();
+    for (e, a) in q.iter(&app.world) {
+        assert_eq!(a.entity_name(&app.world).map(|n| n.as_str()), app.world.entity_name(e).map(|n| n.as_str()));
+    }
+}
+
+
+#[test]
+fn test_find_by_name_shared() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(Name,)>();
+    i.insert(&app.world, (Name::new("a"),));
+    let e2 = i.insert(&app.world, (Name::new("b"),));
+    // 没有名称索引时遍历实体
+    let world: &World = &app.world;
+    assert_eq!(world.find_by_name("b"), Some(e2));
+    assert_eq!(world.find_all_by_name("a").len(), 1);
+
+    // 注册索引后，通过共享引用同步索引
+    app.world.register_name_index();
+    i.insert(&app.world, (Name::new("a"),));
+    let world: &World = &app.world;
+    assert_eq!(world.find_by_name("b"), Some(e2));
+    assert_eq!(world.find_all_by_name("a").len(), 2);
+    app.world.destroy_entity(e2).unwrap();
+    assert_eq!(app.world.find_by_name("b"), None);
+}
@@ -0,0 +1,79 @@
+use pi_world::prelude::{App, Commands, Component, Local, Query, Resource, SingleRes, SingleResMut, SystemId, Update};
+
+#[derive(Debug, Default, Resource)]
+pub struct Counter(usize);
+
+#[derive(Component)]
+pub struct Age(usize);
+
+#[test]
+fn test_run_system() {
+    fn count(mut local: Local<usize>, q: Query<&Age>, mut counter: SingleResMut<Counter>) {
+        *local += 1;
+        counter.0 = *local * 100 + q.iter().map(|a| a.0).sum::<usize>();
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Counter::default());
+    let i = app.world.make_insert::<(Age,)>();
+    i.insert(&app.world, (Age(1),));
+    i.insert(&app.world, (Age(2),));
+    app.world.settle();
+
+    let id = app.world.register_system(count);
+    // 注册后不会被调度
+    app.run();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 0);
+
+    app.world.run_system(id).unwrap();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 103);
+
+    // Local状态被保留
+    app.world.run_system(id).unwrap();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 203);
+}
+
+#[test]
+fn test_commands_run_system() {
+    #[derive(Resource)]
+    pub struct Callback(SystemId);
+
+    fn callback(mut counter: SingleResMut<Counter>) {
+        counter.0 += 1;
+    }
+
+    fn trigger(mut commands: Commands, cb: SingleRes<Callback>) {
+        commands.run_system(cb.0);
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Counter::default());
+    let id = app.world.register_system(callback);
+    app.world.insert_single_res(Callback(id));
+    app.add_system(Update, trigger);
+
+    app.run();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 1);
+    app.run();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 2);
+}
+
+#[test]
+fn test_run_system_panic() {
+    fn may_panic(mut local: Local<usize>, mut counter: SingleResMut<Counter>) {
+        *local += 1;
+        if *local == 1 {
+            panic!("first run");
+        }
+        counter.0 += 1;
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Counter::default());
+    let id = app.world.register_system(may_panic);
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| app.world.run_system(id)));
+    assert!(r.is_err());
+    // panic后system被放回，可以再次运行
+    app.world.run_system(id).unwrap();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 1);
+}
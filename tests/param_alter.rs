@@ -5,7 +5,7 @@ mod defined;
 extern crate test;
 use defined::*;
 use test::Bencher;
-use pi_world::{debug::{ArchetypeDebug, ColumnDebug}, prelude::{World, Entity, EntityEditor, ParamSet, Query, Update}};
+use pi_world::{debug::{ArchetypeDebug, ColumnDebug}, prelude::{World, Entity, EntityEditor, ParamSet, Query, Resource, Update}};
 
 type Bundle0 = (Age0, Age1, Age2, Age3, Age4, Age5, Age6, Age7);
 type Bundle1 = (Age8, Age9, Age10, Age11, Age12, Age13, Age14, Age15);
@@ -241,6 +241,7 @@ fn test_alter2() {
 #[test] 
 fn test_alter3() {
     #[allow(dead_code)]
+    #[derive(Resource)]
     pub struct EntityRes(Entity);
 
     let mut app = pi_world::prelude::App::new();
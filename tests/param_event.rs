@@ -1,12 +1,13 @@
 
-use pi_world::prelude::{Update, SingleRes, EventWriter, EventReader};
+use pi_world::prelude::{Update, Event, Resource, SingleRes, EventWriter, EventReader};
 
 
 
 #[test]
 fn test_event() { 
+    #[derive(Resource)]
     struct A(f32);
-    #[derive(Clone, Copy, Default)]
+    #[derive(Clone, Copy, Default, Event)]
     struct B(f32);
 
     fn ab(a: SingleRes<A>, b: EventWriter<B>) {
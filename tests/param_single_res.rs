@@ -1,7 +1,8 @@
-use pi_world::prelude::{World, App, Update, SingleRes, SingleResMut};
+use pi_world::prelude::{World, App, Update, Resource, SingleRes, SingleResMut};
 
-#[derive(Debug)]
+#[derive(Debug, Resource)]
 pub struct Age(pub usize);
+#[derive(Resource)]
 pub struct Id(pub usize);
 
 #[test]
@@ -38,10 +39,15 @@ fn test() {
 
 #[test]
 fn test_res() { 
+    #[derive(Resource)]
     struct A(f32);
+    #[derive(Resource)]
     struct B(f32);
+    #[derive(Resource)]
     struct C(f32);
+    #[derive(Resource)]
     struct D(f32);
+    #[derive(Resource)]
     struct E(f32);
 
     fn ab(a: SingleRes<A>, mut b: SingleResMut<B>) {
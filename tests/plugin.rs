@@ -0,0 +1,183 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use pi_world::prelude::{plugin_name, App, AppError, Plugin, PluginGroup, PluginGroupBuilder, PluginsState, Resource, WorldPluginExtent};
+
+#[derive(Debug, Default, Resource)]
+pub struct Log(Vec<&'static str>);
+
+fn log(app: &mut App, s: &'static str) {
+    app.world.get_single_res_mut::<Log>().unwrap().0.push(s);
+}
+
+pub struct PluginA;
+impl Plugin for PluginA {
+    fn build(&self, app: &mut App) {
+        log(app, "build a");
+    }
+    fn finish(&self, app: &mut App) {
+        log(app, "finish a");
+    }
+    fn cleanup(&self, app: &mut App) {
+        log(app, "cleanup a");
+    }
+}
+
+// 轮询3次后才准备好
+pub struct PluginB(AtomicUsize);
+impl Plugin for PluginB {
+    fn build(&self, app: &mut App) {
+        log(app, "build b");
+    }
+    fn ready(&self, _app: &App) -> bool {
+        self.0.fetch_add(1, Ordering::Relaxed) >= 2
+    }
+    fn finish(&self, app: &mut App) {
+        log(app, "finish b");
+    }
+    fn cleanup(&self, app: &mut App) {
+        log(app, "cleanup b");
+    }
+}
+
+pub struct PluginC;
+impl Plugin for PluginC {
+    fn build(&self, _app: &mut App) {}
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_plugin_lifecycle() {
+    let mut app = App::new();
+    app.world.insert_single_res(Log::default());
+    app.add_plugins(PluginA);
+    app.add_plugins(PluginB(AtomicUsize::new(0)));
+
+    assert_eq!(app.plugins_state(), PluginsState::Adding);
+    // 插件未准备好，不会运行
+    app.run();
+    assert_eq!(app.world.get_single_res::<Log>().unwrap().0, vec!["build a", "build b"]);
+    app.run();
+    assert_eq!(app.plugins_state(), PluginsState::Cleaned);
+    assert_eq!(
+        app.world.get_single_res::<Log>().unwrap().0,
+        vec!["build a", "build b", "finish a", "finish b", "cleanup a", "cleanup b"]
+    );
+
+    // 再次运行，不会重复调用finish和cleanup
+    app.run();
+    assert_eq!(app.world.get_single_res::<Log>().unwrap().0.len(), 6);
+}
+
+#[test]
+fn test_duplicate_plugin() {
+    let mut app = App::new();
+    app.world.insert_single_res(Log::default());
+    app.add_plugins(PluginA);
+    assert_eq!(
+        app.try_add_boxed_plugin(Box::new(PluginA)).err(),
+        Some(AppError::DuplicatePlugin {
+            plugin_name: std::any::type_name::<PluginA>().to_string()
+        })
+    );
+    // 非唯一插件可以重复添加
+    app.add_plugins(PluginC);
+    app.add_plugins(PluginC);
+}
+
+pub struct PluginD;
+impl Plugin for PluginD {
+    fn build(&self, app: &mut App) {
+        log(app, "build d");
+    }
+    fn dependencies(&self) -> Vec<&'static str> {
+        vec![plugin_name::<PluginE>()]
+    }
+}
+
+pub struct PluginE;
+impl Plugin for PluginE {
+    fn build(&self, app: &mut App) {
+        log(app, "build e");
+    }
+}
+
+pub struct PluginF;
+impl Plugin for PluginF {
+    fn build(&self, app: &mut App) {
+        log(app, "build f");
+    }
+    fn after(&self) -> Vec<&'static str> {
+        vec![plugin_name::<PluginD>(), "NotExist"]
+    }
+}
+
+pub struct Group;
+impl PluginGroup for Group {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(PluginF)
+            .add(PluginD)
+            .add(PluginA)
+            .add(PluginE)
+    }
+}
+
+#[test]
+fn test_plugin_dependencies() {
+    let mut app = App::new();
+    app.world.insert_single_res(Log::default());
+    Group.build().finish(&mut app);
+    assert_eq!(
+        app.world.get_single_res::<Log>().unwrap().0,
+        vec!["build a", "build e", "build d", "build f"]
+    );
+
+    // 单独添加时，依赖build后才build
+    let mut app = App::new();
+    app.world.insert_single_res(Log::default());
+    app.add_plugins(PluginD);
+    assert!(app.world.get_single_res::<Log>().unwrap().0.is_empty());
+    assert_eq!(
+        app.check_plugin_dependencies(),
+        Err(AppError::MissingDependency {
+            plugin_name: plugin_name::<PluginD>().to_string(),
+            dependency: plugin_name::<PluginE>().to_string(),
+        })
+    );
+    app.add_plugins(PluginE);
+    assert_eq!(app.check_plugin_dependencies(), Ok(()));
+    assert_eq!(
+        app.world.get_single_res::<Log>().unwrap().0,
+        vec!["build e", "build d"]
+    );
+}
+
+// finish时添加插件
+pub struct PluginG;
+impl Plugin for PluginG {
+    fn build(&self, app: &mut App) {
+        log(app, "build g");
+    }
+    fn finish(&self, app: &mut App) {
+        log(app, "finish g");
+        app.add_plugins(PluginA);
+    }
+    fn cleanup(&self, app: &mut App) {
+        log(app, "cleanup g");
+    }
+}
+
+#[test]
+fn test_add_plugin_in_finish() {
+    let mut app = App::new();
+    app.world.insert_single_res(Log::default());
+    app.add_plugins(PluginG);
+    app.run();
+    assert_eq!(app.plugins_state(), PluginsState::Cleaned);
+    assert_eq!(
+        app.world.get_single_res::<Log>().unwrap().0,
+        vec!["build g", "finish g", "build a", "finish a", "cleanup g", "cleanup a"]
+    );
+}
@@ -0,0 +1,50 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Pos(pub f32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Vel(pub f32);
+
+#[test]
+fn test_iter_chunks() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos, Vel)>();
+    let mut list = Vec::new();
+    for n in 0..100 {
+        list.push(i.insert(&world, (Pos(n as f32), Vel(1.0))));
+    }
+    let i = world.make_insert::<(Pos,)>();
+    for n in 0..10 {
+        i.insert(&world, (Pos(n as f32),));
+    }
+    world.destroy_entity(list[50]).unwrap();
+
+    let mut state = world.make_query::<(&mut Pos, &Vel), ()>();
+    let mut q = state.get_param(&world);
+    let mut count = 0;
+    for mut chunk in q.iter_chunks_mut() {
+        let (pos, vel) = &mut chunk.data;
+        assert_eq!(pos.len(), vel.len());
+        for (p, v) in pos.iter_mut().zip(vel.iter()) {
+            p.0 += v.0;
+        }
+        count += chunk.len();
+    }
+    // 被销毁的实体不在块内
+    assert_eq!(count, 99);
+    for (n, e) in list.iter().enumerate() {
+        if n != 50 {
+            assert_eq!(q.get(*e).unwrap().0 .0, n as f32 + 1.0);
+        }
+    }
+
+    let mut state = world.make_query::<&Pos, ()>();
+    let q = state.get_param(&world);
+    let mut entities = 0;
+    for chunk in q.iter_chunks() {
+        assert_eq!(chunk.entities().count(), chunk.len());
+        entities += chunk.len();
+    }
+    assert_eq!(entities, 109);
+}
@@ -0,0 +1,94 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Pos(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Mass(pub u32);
+
+#[derive(Debug, Default, Resource)]
+pub struct Seen(Vec<Vec<(u32, u32)>>);
+
+#[test]
+fn test_iter_combinations() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos,)>();
+    for n in 0..3 {
+        i.insert(&world, (Pos(n),));
+    }
+    let i = world.make_insert::<(Pos, Mass)>();
+    for n in 3..5 {
+        i.insert(&world, (Pos(n), Mass(1)));
+    }
+
+    let mut state = world.make_query::<&mut Pos, ()>();
+    let mut q = state.get_param(&world);
+    let it = q.iter_combinations::<2>();
+    assert_eq!(it.size_hint(), (10, Some(10)));
+    let mut pairs: Vec<(u32, u32)> = it.map(|[a, b]| (a.0.min(b.0), a.0.max(b.0))).collect();
+    pairs.sort();
+    pairs.dedup();
+    assert_eq!(pairs.len(), 10);
+    assert_eq!(q.iter_combinations::<3>().count(), 10);
+    assert_eq!(q.iter_combinations::<6>().count(), 0);
+
+    // 每对实体都加1，每个实体属于4对
+    let mut it = q.iter_combinations_mut::<2>();
+    while let Some([mut a, mut b]) = it.fetch_next() {
+        a.0 += 1;
+        b.0 += 1;
+    }
+    let mut r: Vec<u32> = q.iter().map(|p| p.0).collect();
+    r.sort();
+    assert_eq!(r, vec![4, 5, 6, 7, 8]);
+
+    // 过滤器
+    let mut state = world.make_query::<&Pos, With<Mass>>();
+    let q = state.get_param(&world);
+    assert_eq!(q.iter_combinations::<2>().count(), 1);
+}
+
+#[test]
+fn test_iter_combinations_changed() {
+    fn spawn(i: Insert<(Pos,)>, mut frame: Local<u32>) {
+        if *frame == 0 {
+            for n in 0..4 {
+                i.insert((Pos(n),));
+            }
+        }
+        *frame += 1;
+    }
+    // 第二帧只修改部分实体
+    fn bump(mut q: Query<&mut Pos>, mut frame: Local<u32>) {
+        if *frame == 1 {
+            for mut p in q.iter_mut() {
+                if p.0 % 2 == 1 {
+                    p.0 += 10;
+                }
+            }
+        }
+        *frame += 1;
+    }
+    fn pairs(q: Query<&Pos, Changed<Pos>>, mut seen: SingleResMut<Seen>) {
+        assert_eq!(q.iter_combinations::<3>().count(), if seen.0.is_empty() { 4 } else { 0 });
+        let mut r: Vec<(u32, u32)> = q
+            .iter_combinations::<2>()
+            .map(|[a, b]| (a.0.min(b.0), a.0.max(b.0)))
+            .collect();
+        r.sort();
+        seen.0.push(r);
+    }
+    let mut app = App::new();
+    app.world.insert_single_res(Seen::default());
+    app.add_system(Update, spawn);
+    app.add_system(Update, bump.after(spawn));
+    app.add_system(Update, pairs.after(bump));
+    app.run();
+    app.run();
+    app.run();
+    let seen = &app.world.get_single_res::<Seen>().unwrap().0;
+    assert_eq!(seen[0].len(), 6);
+    // 只有变化的实体参与组合
+    assert_eq!(seen[1], vec![(11, 13)]);
+    assert!(seen[2].is_empty());
+}
@@ -0,0 +1,53 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Pos(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Vel(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Mass(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Frozen;
+
+#[derive(QueryData)]
+pub struct Body<'w> {
+    pub entity: Entity,
+    pub pos: &'w mut Pos,
+    pub vel: &'w Vel,
+    pub mass: Option<&'w Mass>,
+}
+
+#[derive(QueryFilter)]
+pub struct Movable {
+    _vel: With<Vel>,
+    _frozen: Without<Frozen>,
+}
+
+pub fn movement(mut q: Query<Body, Movable>) {
+    for mut body in q.iter_mut() {
+        body.pos.0 += body.vel.0 * body.mass.map_or(1, |m| m.0);
+    }
+}
+
+#[test]
+fn test_query_data() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(Pos, Vel)>();
+    let e1 = i.insert(&app.world, (Pos(0), Vel(1)));
+    let i = app.world.make_insert::<(Pos, Vel, Mass)>();
+    let e2 = i.insert(&app.world, (Pos(0), Vel(1), Mass(3)));
+    let i = app.world.make_insert::<(Pos, Vel, Frozen)>();
+    let e3 = i.insert(&app.world, (Pos(0), Vel(1), Frozen));
+    app.add_system(Update, movement);
+    app.run();
+
+    let mut state = app.world.make_query::<Body, ()>();
+    let q = state.get_param(&app.world);
+    let r: BodyReadOnlyItem = q.get(e2).unwrap();
+    assert_eq!((r.entity, r.pos.0, r.mass.map(|m| m.0)), (e2, 3, Some(3)));
+    assert_eq!(q.get(e1).unwrap().pos.0, 1);
+    assert_eq!(q.get(e3).unwrap().pos.0, 0);
+}
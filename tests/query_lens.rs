@@ -0,0 +1,74 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct A(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct B(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct C(pub u32);
+
+// 未注册的组件
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct D(pub u32);
+
+fn sum_a(q: Query<&A>) -> u32 {
+    q.iter().map(|a| a.0).sum()
+}
+
+fn add_b(mut q: Query<&mut B>) {
+    for mut b in q.iter_mut() {
+        b.0 += 1;
+    }
+}
+
+#[test]
+fn test_transmute_lens() {
+    let mut world = World::create();
+    let i = world.make_insert::<(A, B, C)>();
+    i.insert(&world, (A(1), B(0), C(0)));
+    i.insert(&world, (A(2), B(0), C(0)));
+    let i = world.make_insert::<(A, B)>();
+    i.insert(&world, (A(4), B(0)));
+
+    let mut state = world.make_query::<(&A, &mut B, &C), ()>();
+    let mut q = state.get_param(&world);
+    // 只包含父查询的原型
+    assert_eq!(sum_a(q.transmute_lens::<&A, ()>().query()), 3);
+    add_b(q.transmute_lens::<&mut B, ()>().query());
+    assert_eq!(q.iter().map(|(_, b, _)| b.0).sum::<u32>(), 2);
+    // 写可以降为读
+    assert_eq!(q.transmute_lens::<&B, ()>().query().iter().count(), 2);
+}
+
+#[test]
+#[should_panic]
+fn test_transmute_lens_write() {
+    let mut world = World::create();
+    let i = world.make_insert::<(A, B)>();
+    i.insert(&world, (A(1), B(0)));
+    let mut state = world.make_query::<(&A, &B), ()>();
+    let mut q = state.get_param(&world);
+    q.transmute_lens::<&mut A, ()>();
+}
+
+#[test]
+fn test_try_transmute_lens() {
+    let mut world = World::create();
+    let i = world.make_insert::<(A, B)>();
+    i.insert(&world, (A(1), B(0)));
+    let mut state = world.make_query::<(&A, &B), ()>();
+    let mut q = state.get_param(&world);
+    assert!(matches!(
+        q.try_transmute_lens::<&D, ()>().err(),
+        Some(QueryError::UnregisteredComponent(_))
+    ));
+    assert!(matches!(
+        q.try_transmute_lens::<&mut A, ()>().err(),
+        Some(QueryError::LensNoAccess(_))
+    ));
+    assert_eq!(q.try_transmute_lens::<&A, With<B>>().unwrap().query().iter().count(), 1);
+    // 派生查询不修改world，未注册的组件仍未注册
+    assert!(world.get_column_by_id(&std::any::TypeId::of::<D>()).is_none());
+}
@@ -0,0 +1,39 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Pos(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Vel(pub u32);
+
+#[test]
+fn test_get_many() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos, Vel)>();
+    let e1 = i.insert(&world, (Pos(1), Vel(10)));
+    let e2 = i.insert(&world, (Pos(2), Vel(20)));
+    let i = world.make_insert::<(Pos,)>();
+    let e3 = i.insert(&world, (Pos(3),));
+
+    let mut state = world.make_query::<(&mut Pos, &Vel), ()>();
+    let mut q = state.get_param(&world);
+    let [(a, _), (b, _)] = q.get_many([e1, e2]).unwrap();
+    assert_eq!((a.0, b.0), (1, 2));
+    // 实体不匹配
+    assert!(q.get_many([e1, e3]).is_err());
+
+    {
+        let [(mut a, va), (mut b, vb)] = q.get_many_mut([e1, e2]).unwrap();
+        a.0 += vb.0;
+        b.0 += va.0;
+    }
+    assert_eq!(q.get(e1).unwrap().0 .0, 21);
+    assert_eq!(q.get(e2).unwrap().0 .0, 12);
+    // 实体重复
+    assert!(matches!(q.get_many([e2, e1, e2]), Err(QueryError::AliasedMutability(e)) if e == e2));
+    assert!(matches!(q.get_many_mut([e1, e1]), Err(QueryError::AliasedMutability(e)) if e == e1));
+
+    // 跳过不匹配的实体
+    let r: Vec<u32> = q.iter_many([e3, e2, e1, e3]).map(|(p, _)| p.0).collect();
+    assert_eq!(r, vec![12, 21]);
+}
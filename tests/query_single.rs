@@ -0,0 +1,46 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Camera(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Active;
+
+#[test]
+fn test_single() {
+    let mut world = World::create();
+    let mut state = world.make_query::<&mut Camera, ()>();
+    {
+        let mut q = state.get_param(&world);
+        assert!(q.is_empty());
+        assert_eq!(q.get_single().err(), Some(QueryError::NoEntities));
+        assert!(matches!(q.get_single_mut(), Err(QueryError::NoEntities)));
+    }
+
+    let i = world.make_insert::<(Camera, Active)>();
+    let e1 = i.insert(&world, (Camera(1), Active));
+    state.align();
+    {
+        let mut q = state.get_param(&world);
+        assert!(!q.is_empty());
+        assert_eq!(q.single().0, 1);
+        q.single_mut().0 = 2;
+        assert_eq!(q.get_single().unwrap().0, 2);
+    }
+
+    let i = world.make_insert::<(Camera,)>();
+    let e2 = i.insert(&world, (Camera(3),));
+    state.align();
+    {
+        let q = state.get_param(&world);
+        assert_eq!(q.get_single().err(), Some(QueryError::MultipleEntities));
+    }
+    let mut state1 = world.make_query::<&Camera, With<Active>>();
+    assert_eq!(state1.get_param(&world).single().0, 2);
+
+    // 原型不为空，但实体都已销毁
+    world.destroy_entity(e1).unwrap();
+    world.destroy_entity(e2).unwrap();
+    let q = state.get_param(&world);
+    assert!(q.is_empty());
+}
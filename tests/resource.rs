@@ -0,0 +1,105 @@
+use pi_world::prelude::*;
+
+#[derive(Debug, Default, Resource)]
+#[resource(from_world)]
+pub struct Counter(usize);
+
+#[derive(Debug, Resource)]
+pub struct Missing(usize);
+
+pub struct EntityCount(usize);
+impl FromWorld for EntityCount {
+    fn from_world(world: &mut World) -> Self {
+        EntityCount(world.len())
+    }
+}
+impl Resource for EntityCount {
+    fn init_res(world: &mut World) -> Option<Self> {
+        Some(Self::from_world(world))
+    }
+}
+
+#[test]
+fn test_from_world() {
+    fn count(mut c: SingleResMut<Counter>, m: Option<SingleRes<Missing>>, e: SingleRes<EntityCount>) {
+        assert!(m.is_none());
+        assert_eq!(e.0, 0);
+        c.0 += 1;
+    }
+    let mut app = App::new();
+    app.add_system(Update, count);
+    app.run();
+    app.run();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 2);
+    assert!(app.world.get_single_res::<Missing>().is_none());
+}
+
+#[test]
+fn test_inserted_not_overwritten() {
+    fn count(mut c: SingleResMut<Counter>) {
+        c.0 += 1;
+    }
+    let mut app = App::new();
+    app.world.insert_single_res(Counter(10));
+    app.add_system(Update, count);
+    app.run();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 11);
+}
+
+#[derive(Event)]
+#[event(retain = 1)]
+pub struct Hit(u32);
+
+#[derive(Event)]
+#[event(retain = 2)]
+pub struct Hit2(u32);
+
+#[derive(Event)]
+pub struct Kept(u32);
+
+#[derive(Default, Resource)]
+pub struct Lens(Vec<(usize, usize, usize)>);
+
+#[test]
+fn test_event_retain() {
+    fn send(hit: EventWriter<Hit>, hit2: EventWriter<Hit2>, kept: EventWriter<Kept>) {
+        hit.send(Hit(1));
+        hit2.send(Hit2(1));
+        kept.send(Kept(1));
+    }
+    // 只查看数量，不读取事件
+    fn observe(hit: EventReader<Hit>, hit2: EventReader<Hit2>, kept: EventReader<Kept>, mut lens: SingleResMut<Lens>) {
+        lens.0.push((hit.len(), hit2.len(), kept.len()));
+    }
+    let mut app = App::new();
+    app.world.insert_single_res(Lens::default());
+    app.add_system(Update, send);
+    app.add_system(Update, observe.after(send));
+    for _ in 0..4 {
+        app.run();
+    }
+    assert_eq!(
+        app.world.get_single_res::<Lens>().unwrap().0,
+        vec![(1, 1, 1), (1, 2, 2), (1, 2, 3), (1, 2, 4)]
+    );
+    assert!(app.world.validate().is_ok());
+}
+
+#[test]
+fn test_event_read() {
+    fn send(hit: EventWriter<Hit>) {
+        hit.send(Hit(1));
+        hit.send(Hit(2));
+    }
+    fn read(hit: EventReader<Hit>, mut lens: SingleResMut<Lens>) {
+        let sum: u32 = hit.iter().map(|h| h.0).sum();
+        lens.0.push((sum as usize, 0, 0));
+    }
+    let mut app = App::new();
+    app.world.insert_single_res(Lens::default());
+    app.add_system(Update, send);
+    app.add_system(Update, read.after(send));
+    app.run();
+    app.run();
+    assert_eq!(app.world.get_single_res::<Lens>().unwrap().0, vec![(3, 0, 0), (3, 0, 0)]);
+}
@@ -1,4 +1,4 @@
-use pi_world::{debug::{ArchetypeDebug, ColumnDebug}, prelude::{App, Component, Entity, Query}, schedule::Update, schedule_config::{IntoSystemConfigs, IntoSystemSetConfigs}, single_res::SingleResMut};
+use pi_world::{condition::{any_with_component, every_n_runs, not, resource_changed, resource_exists, run_once}, debug::{ArchetypeDebug, ColumnDebug}, exec_graph::EdgeKind, prelude::{App, Component, Entity, Query}, schedule::{AmbiguityDetection, Update}, schedule_config::{Condition, IntoSystemConfigs, IntoSystemSetConfigs}, single_res::{Resource, SingleRes, SingleResMut}};
 use pi_world_macros::SystemSet;
 
 #[test]
@@ -116,6 +116,238 @@ fn test_set_condition() {
     // println!("run systems: {:?}", );
 }
 
+#[test]
+fn test_condition_combinator() {
+    #[derive(Component)]
+    struct A(f32);
+    #[derive(Resource)]
+    pub struct Missing;
+
+    let mut app = App::new();
+    app.world.insert_single_res(RunSystem::default());
+
+    app.add_system(Update, system1.run_if(condition_true.and_then(condition_false)));
+    app.add_system(Update, system2.run_if(condition_false.or_else(condition_true)));
+    app.add_system(Update, system3.run_if(not(condition_false)));
+    app.add_system(Update, system4.run_if(resource_exists::<Missing>));
+    app.add_system(Update, system5.run_if(not(resource_exists::<Missing>).and_then(resource_exists::<RunSystem>)));
+    app.add_system(Update, system6.run_if(any_with_component::<A>));
+    app.add_system(Update, system7.in_set(Set::Set7));
+    app.configure_set(Update, Set::Set7.run_if(run_once()));
+
+    app.run();
+
+    let run_systems = &**app.world.get_single_res::<RunSystem>().unwrap();
+    assert_eq!(run_systems.0.contains(&"system1"), false);
+    assert_eq!(run_systems.0.contains(&"system2"), true);
+    assert_eq!(run_systems.0.contains(&"system3"), true);
+    assert_eq!(run_systems.0.contains(&"system4"), false);
+    assert_eq!(run_systems.0.contains(&"system5"), true);
+    assert_eq!(run_systems.0.contains(&"system6"), false);
+    assert_eq!(run_systems.0.contains(&"system7"), true);
+
+    app.world.get_single_res_mut::<RunSystem>().unwrap().0.clear();
+    let i = app.world.make_insert::<(A,)>();
+    i.insert(&app.world, (A(0.0),));
+    app.world.settle();
+    app.run();
+
+    let run_systems = &**app.world.get_single_res::<RunSystem>().unwrap();
+    assert_eq!(run_systems.0.contains(&"system6"), true);
+    // run_once只运行一次
+    assert_eq!(run_systems.0.contains(&"system7"), false);
+}
+
+#[test]
+fn test_common_condition() {
+    #[derive(Debug, Default, Resource)]
+    pub struct Counter(usize);
+    #[derive(Debug, Default, Resource)]
+    pub struct ChangedCount(usize);
+
+    fn count(mut c: SingleResMut<Counter>) {
+        c.0 += 1;
+    }
+    fn changed(mut c: SingleResMut<ChangedCount>) {
+        c.0 += 1;
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Counter::default());
+    app.world.insert_single_res(ChangedCount::default());
+    app.add_system(Update, count.run_if(every_n_runs(3)));
+    app.add_system(Update, changed.run_if(resource_changed::<Counter>));
+
+    for _ in 0..6 {
+        app.run();
+    }
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 2);
+    assert_eq!(app.world.get_single_res::<ChangedCount>().unwrap().0, 2);
+}
+
+#[test]
+fn test_ambiguity_report() {
+    #[derive(Component)]
+    struct Age(usize);
+    #[derive(Debug, Default, Resource)]
+    pub struct Counter(usize);
+
+    fn write_age1(q: Query<&mut Age>) {
+        let _ = q;
+    }
+    fn write_age2(q: Query<&mut Age>) {
+        let _ = q;
+    }
+    fn read_age(q: Query<&Age>) {
+        let _ = q;
+    }
+    fn count1(mut c: SingleResMut<Counter>) {
+        c.0 += 1;
+    }
+    fn count2(mut c: SingleResMut<Counter>) {
+        c.0 += 1;
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Counter::default());
+    app.add_system(Update, write_age1);
+    app.add_system(Update, write_age2);
+    app.add_system(Update, read_age.after(write_age1).after(write_age2));
+    app.add_system(Update, count1);
+    app.add_system(Update, count2);
+
+    let report = app.schedule.ambiguity_report(&mut app.world);
+    // write_age1和write_age2冲突，count1和count2冲突，read_age已指定顺序
+    assert_eq!(report.len(), 2);
+    for a in report.ambiguities.iter() {
+        assert_eq!(a.conflicts.len(), 1);
+        let names = a.systems.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(",");
+        assert_eq!(names.contains("read_age"), false);
+        if names.contains("write_age") {
+            assert!(a.conflicts[0].contains("Age"));
+        } else {
+            assert!(names.contains("count1") && names.contains("count2"));
+            assert!(a.conflicts[0].contains("Counter"));
+        }
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Counter::default());
+    app.add_system(Update, count1);
+    app.add_system(Update, count2.after(count1));
+    app.schedule.set_ambiguity_detection(AmbiguityDetection::Error);
+    assert!(app.schedule.ambiguity_report(&mut app.world).is_empty());
+    app.run();
+    assert_eq!(app.world.get_single_res::<Counter>().unwrap().0, 2);
+}
+
+#[test]
+fn test_condition_ambiguity() {
+    #[derive(Debug, Default, Resource)]
+    pub struct Counter(usize);
+
+    fn counter_even(c: SingleRes<Counter>) -> bool {
+        c.0 % 2 == 0
+    }
+    fn count(mut c: SingleResMut<Counter>) {
+        c.0 += 1;
+    }
+    fn other() {}
+
+    let mut app = App::new();
+    app.world.insert_single_res(Counter::default());
+    app.add_system(Update, count);
+    app.add_system(Update, other.run_if(condition_true.and_then(not(counter_even))));
+
+    // 组合条件的读写关系同样参与歧义检查
+    let report = app.schedule.ambiguity_report(&mut app.world);
+    assert_eq!(report.len(), 1);
+    assert!(report.ambiguities[0].conflicts[0].contains("Counter"));
+}
+
+#[test]
+fn test_schedule_build_error() {
+    #[derive(Component)]
+    struct Age(usize);
+
+    fn cycle_a() {}
+    fn cycle_b() {}
+    fn conflict(q1: Query<&mut Age>, q2: Query<&Age>) {
+        let _ = (q1, q2);
+    }
+
+    let mut app = App::new();
+    app.add_system(Update, cycle_a.after(cycle_b));
+    app.add_system(Update, cycle_b.after(cycle_a));
+    app.add_system(Update, conflict);
+
+    // 环和冲突一次全部返回
+    let e = app.initialize().unwrap_err();
+    assert!(e.is_fatal());
+    assert_eq!(e.cycles.len(), 1);
+    let path = e.cycles[0].path.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(",");
+    assert!(path.contains("cycle_a") && path.contains("cycle_b"));
+    assert_eq!(e.cycles[0].edges.len(), e.cycles[0].path.len());
+
+    assert_eq!(e.conflicts.len(), 1);
+    assert!(e.conflicts[0].system.contains("conflict"));
+    assert_eq!(e.conflicts[0].params.len(), 2);
+    assert!(e.conflicts[0].access.contains("Age"));
+}
+
+#[test]
+fn test_conflict_is_fatal() {
+    #[derive(Component)]
+    struct Age(usize);
+
+    fn conflict(q1: Query<&mut Age>, q2: Query<&mut Age>) {
+        let _ = (q1, q2);
+    }
+
+    let mut app = App::new();
+    app.add_system(Update, conflict);
+    // system内的读写冲突同样无法运行
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| app.run()));
+    assert!(r.is_err());
+    // 错误报告后被清除，再次运行仍然失败
+    let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| app.run()));
+    assert!(r.is_err());
+}
+
+#[test]
+fn test_cycle_through_set() {
+    fn cycle_a() {}
+    fn cycle_b() {}
+
+    let mut app = App::new();
+    app.add_system(Update, cycle_a.in_set(Set::Set2));
+    app.add_system(Update, cycle_b.after(cycle_a));
+    app.configure_set(Update, Set::Set2.after(cycle_b));
+
+    let e = app.initialize().unwrap_err();
+    assert_eq!(e.cycles.len(), 1);
+    let edges = &e.cycles[0].edges;
+    assert_eq!(edges.len(), e.cycles[0].path.len());
+    // 每条边都能找到声明它的system或集
+    assert!(edges.iter().all(|edge| edge.origin.is_some()));
+    // cycle_b -> Set2：由Set2的after声明
+    assert!(edges.iter().any(|edge| {
+        let origin = edge.origin.as_ref().unwrap();
+        edge.before.contains("cycle_b") && origin.kind == EdgeKind::After && origin.declared_by == "Set2"
+    }));
+    // Set2 -> cycle_a：cycle_a属于Set2
+    assert!(edges.iter().any(|edge| {
+        let origin = edge.origin.as_ref().unwrap();
+        edge.after.contains("cycle_a") && origin.kind == EdgeKind::InSet && origin.declared_by == "Set2"
+    }));
+    // cycle_a -> cycle_b：由cycle_b的after声明
+    assert!(edges.iter().any(|edge| {
+        let origin = edge.origin.as_ref().unwrap();
+        edge.before.contains("cycle_a") && edge.after.contains("cycle_b") && origin.kind == EdgeKind::After && origin.declared_by.contains("cycle_b")
+    }));
+    assert!(e.to_string().contains("declared by Set2"));
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
 pub enum Set {
     Set1,
@@ -135,7 +367,7 @@ pub fn condition_false() -> bool {
     return false;
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Resource)]
 pub struct RunSystem(Vec<&'static str>);
 pub fn system1(mut run_system: SingleResMut<RunSystem>) {
     run_system.0.push("system1");
@@ -0,0 +1,100 @@
+
+#[path = "./defined.rs"]
+mod defined;
+use defined::*;
+use pi_world::prelude::{Component, World};
+
+#[test]
+fn test_settle_incremental() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Age0,)>();
+    let i1 = world.make_insert::<(Age1,)>();
+    let mut list = Vec::new();
+    let mut list1 = Vec::new();
+    for n in 0..20 {
+        list.push(i.insert(&world, (Age0(n),)));
+        list1.push(i1.insert(&world, (Age1(n),)));
+    }
+    world.settle();
+    // Age0原型移除的行更多，优先整理
+    for e in list.iter().step_by(2) {
+        world.destroy_entity(*e).unwrap();
+    }
+    world.destroy_entity(list1[0]).unwrap();
+
+    // 预算为1，每次只整理一个原型
+    assert_eq!(world.settle_incremental(1), false);
+    // 未整理的原型依然可以正确查询
+    let mut q = world.make_query::<&Age1, ()>();
+    assert_eq!(q.iter(&world).count(), 19);
+    let mut q0 = world.make_query::<&Age0, ()>();
+    assert_eq!(q0.iter(&world).count(), 10);
+    for (n, e) in list.iter().enumerate() {
+        if n % 2 == 1 {
+            assert_eq!(world.get_component::<Age0>(*e).unwrap().0, n);
+        }
+    }
+    assert_eq!(world.settle_incremental(1), true);
+    assert_eq!(q.iter(&world).count(), 19);
+    for (n, e) in list1.iter().enumerate().skip(1) {
+        assert_eq!(world.get_component::<Age1>(*e).unwrap().0, n);
+    }
+    // 已全部整理
+    assert_eq!(world.settle_incremental(1), true);
+}
+
+#[test]
+fn test_shrink_to_fit_and_reserve() {
+    let mut world = World::create();
+    world.reserve::<(Age0, Age1)>(1000);
+    let capacity = |world: &World| {
+        world.memory_report().archetypes.iter().find(|a| a.columns.len() == 2).map(|a| a.capacity).unwrap()
+    };
+    assert!(capacity(&world) >= 1000);
+
+    let i = world.make_insert::<(Age0, Age1)>();
+    let mut list = Vec::new();
+    for n in 0..1000 {
+        list.push(i.insert(&world, (Age0(n), Age1(n))));
+    }
+    // 预分配后，插入不需要扩容
+    assert!(capacity(&world) >= 1000);
+    world.settle();
+    let size = world.memory_report().total();
+    for e in list.iter().skip(10) {
+        world.destroy_entity(*e).unwrap();
+    }
+    world.shrink_to_fit();
+    assert!(capacity(&world) < 1000);
+    assert!(world.memory_report().total() < size);
+    for (n, e) in list.iter().enumerate().take(10) {
+        assert_eq!(world.get_component::<Age0>(*e).unwrap().0, n);
+        assert_eq!(world.get_component::<Age1>(*e).unwrap().0, n);
+    }
+    // 释放后，依然可以继续插入
+    let e = i.insert(&world, (Age0(10), Age1(10)));
+    world.settle();
+    assert_eq!(world.get_component::<Age1>(e).unwrap().0, 10);
+}
+
+#[derive(Component, Default)]
+pub struct Marker;
+
+#[test]
+fn test_shrink_to_fit_zst() {
+    let mut world = World::create();
+    // 零大小的组件和未分配的列，shrink_to_fit不做处理
+    world.reserve::<(Marker,)>(10);
+    world.shrink_to_fit();
+    let i = world.make_insert::<(Age0, Marker)>();
+    let list = (0..100).map(|n| i.insert(&world, (Age0(n), Marker))).collect::<Vec<_>>();
+    world.settle();
+    for e in list.iter().skip(10) {
+        world.destroy_entity(*e).unwrap();
+    }
+    world.shrink_to_fit();
+    for (n, e) in list.iter().enumerate().take(10) {
+        assert_eq!(world.get_component::<Age0>(*e).unwrap().0, n);
+        assert!(world.get_component::<Marker>(*e).is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub struct ZIndex(pub i32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Node(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Visible;
+
+#[derive(Default, Resource)]
+pub struct Order(pub Vec<u32>);
+
+pub fn draw(q: SortedQuery<&Node, ZIndex>, mut order: SingleResMut<Order>) {
+    order.0 = q.iter().map(|n| n.0).collect();
+}
+
+#[test]
+fn test_iter_sorted_by_key() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Node, ZIndex)>();
+    for (n, z) in [(0, 3), (1, 1), (2, 2), (3, 1)] {
+        i.insert(&world, (Node(n), ZIndex(z)));
+    }
+    let mut state = world.make_query::<(&Node, &ZIndex), ()>();
+    let q = state.get_param(&world);
+    let mut r: Vec<u32> = q.iter_sorted_by_key(|(_, z)| z.0).map(|(n, _)| n.0).collect();
+    // 键相同的保持原来的顺序
+    let first = r.remove(0);
+    let second = r.remove(0);
+    assert!([first, second] == [1, 3] || [first, second] == [3, 1]);
+    assert_eq!(r, vec![2, 0]);
+}
+
+#[test]
+fn test_sorted_query() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(Node, ZIndex)>();
+    let e0 = i.insert(&app.world, (Node(0), ZIndex(0)));
+    i.insert(&app.world, (Node(1), ZIndex(5)));
+    i.insert(&app.world, (Node(2), ZIndex(3)));
+    app.world.insert_single_res(Order::default());
+    app.add_system(Update, draw);
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![0, 2, 1]);
+
+    // 修改排序键
+    app.world.get_component_mut::<ZIndex>(e0).unwrap().0 = 10;
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![2, 1, 0]);
+
+    // 新增和销毁实体
+    i.insert(&app.world, (Node(3), ZIndex(4)));
+    app.world.destroy_entity(e0).unwrap();
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![2, 3, 1]);
+}
+
+pub fn draw_changed(q: SortedQuery<&Node, ZIndex, Changed<Node>>, mut order: SingleResMut<Order>) {
+    order.0 = q.iter().map(|n| n.0).collect();
+}
+
+#[test]
+fn test_sorted_query_changed() {
+    // 第二帧修改部分实体
+    fn bump(mut q: Query<&mut Node>, mut frame: Local<u32>) {
+        if *frame == 1 {
+            for mut n in q.iter_mut() {
+                if n.0 > 0 {
+                    n.0 += 10;
+                }
+            }
+        }
+        *frame += 1;
+    }
+    let mut app = App::new();
+    let i = app.world.make_insert::<(Node, ZIndex)>();
+    i.insert(&app.world, (Node(0), ZIndex(2)));
+    i.insert(&app.world, (Node(1), ZIndex(1)));
+    i.insert(&app.world, (Node(2), ZIndex(0)));
+    app.world.insert_single_res(Order::default());
+    app.add_system(Update, bump);
+    app.add_system(Update, draw_changed.after(bump));
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![2, 1, 0]);
+
+    // F中的Changed只过滤迭代的实体，排序保持不变
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![12, 11]);
+    app.run();
+    assert!(app.world.get_single_res::<Order>().unwrap().0.is_empty());
+}
+
+#[test]
+fn test_sorted_query_archetype_change() {
+    let mut app = App::new();
+    let i = app.world.make_insert::<(Node, ZIndex)>();
+    let e0 = i.insert(&app.world, (Node(0), ZIndex(3)));
+    let e1 = i.insert(&app.world, (Node(1), ZIndex(1)));
+    i.insert(&app.world, (Node(2), ZIndex(2)));
+    app.world.insert_single_res(Order::default());
+    app.add_system(Update, draw);
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![1, 2, 0]);
+
+    // 实体移到其他原型，仍在查询内
+    let mut alter = app.world.make_alter::<(), (), (Visible,), ()>();
+    alter.get_param(&app.world).alter(e0, (Visible,)).unwrap();
+    alter.get_param(&app.world).alter(e1, (Visible,)).unwrap();
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![1, 2, 0]);
+
+    // 整理后行发生移动，再销毁及新增实体
+    app.world.settle();
+    app.world.destroy_entity(e1).unwrap();
+    i.insert(&app.world, (Node(3), ZIndex(0)));
+    app.run();
+    assert_eq!(app.world.get_single_res::<Order>().unwrap().0, vec![3, 2, 0]);
+}
@@ -0,0 +1,387 @@
+use std::sync::Arc;
+
+use pi_world::prelude::*;
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pos(pub f32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[component(storage = "sparse")]
+pub struct Selected(pub u32);
+
+#[derive(Component)]
+#[component(storage = "sparse")]
+pub struct Handle(pub Arc<()>);
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+#[component(storage = "sparse", on_add = flag_added, on_remove = flag_removed)]
+pub struct Flag(pub u32);
+
+#[derive(Debug, Default, Resource)]
+pub struct Seen(Vec<Vec<u32>>);
+
+#[derive(Debug, Default, Resource)]
+pub struct HookLog(Vec<(bool, Entity)>);
+
+fn flag_added(world: &mut World, e: Entity) {
+    world.get_single_res_mut::<HookLog>().unwrap().0.push((true, e));
+}
+fn flag_removed(world: &mut World, e: Entity) {
+    world.get_single_res_mut::<HookLog>().unwrap().0.push((false, e));
+}
+
+#[test]
+fn test_alter_in_place() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos,)>();
+    let e1 = i.insert(&world, (Pos(1.0),));
+    let e2 = i.insert(&world, (Pos(2.0),));
+    let ar = world.get_entity_prototype(e1).unwrap().1;
+
+    let mut add = world.make_alter::<(), (), (Selected,), ()>();
+    add.get_param(&world).alter(e1, (Selected(1),)).unwrap();
+    // 稀疏存储的组件不改变实体的原型
+    assert_eq!(world.get_entity_prototype(e1).unwrap().1, ar);
+    assert_eq!(world.get_component::<Selected>(e1).unwrap(), &Selected(1));
+    assert!(world.get_component::<Selected>(e2).is_err());
+
+    let mut remove = world.make_alter::<(), (), (), (Selected,)>();
+    remove.get_param(&world).alter(e1, ()).unwrap();
+    assert_eq!(world.get_entity_prototype(e1).unwrap().1, ar);
+    assert!(world.get_component::<Selected>(e1).is_err());
+    assert_eq!(world.get_component::<Pos>(e1).unwrap(), &Pos(1.0));
+}
+
+#[test]
+fn test_query() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos,)>();
+    let es = (0..4).map(|n| i.insert(&world, (Pos(n as f32),))).collect::<Vec<_>>();
+    let i = world.make_insert::<(Pos, Selected)>();
+    let e4 = i.insert(&world, (Pos(4.0), Selected(4)));
+    assert_eq!(
+        world.get_entity_prototype(e4).unwrap().1,
+        world.get_entity_prototype(es[0]).unwrap().1
+    );
+    let mut alter = world.make_alter::<(), (), (Selected,), ()>();
+    alter.get_param(&world).alter(es[1], (Selected(1),)).unwrap();
+
+    let mut q = world.make_query::<(Entity, &Selected), ()>();
+    let mut r = q.get_param(&world).iter().map(|(e, s)| (e, *s)).collect::<Vec<_>>();
+    r.sort_by_key(|(_, s)| s.0);
+    assert_eq!(r, vec![(es[1], Selected(1)), (e4, Selected(4))]);
+    assert!(q.get_param(&world).get(es[0]).is_err());
+
+    let mut q = world.make_query::<&Pos, With<Selected>>();
+    let mut r = q.get_param(&world).iter().map(|p| p.0 as u32).collect::<Vec<_>>();
+    r.sort();
+    assert_eq!(r, vec![1, 4]);
+
+    let mut q = world.make_query::<&Pos, Without<Selected>>();
+    let mut r = q.get_param(&world).iter().map(|p| p.0 as u32).collect::<Vec<_>>();
+    r.sort();
+    assert_eq!(r, vec![0, 2, 3]);
+
+    let mut q = world.make_query::<&mut Selected, ()>();
+    for mut s in q.get_param(&world).iter_mut() {
+        s.0 += 10;
+    }
+    assert_eq!(world.get_component::<Selected>(es[1]).unwrap(), &Selected(11));
+    assert_eq!(world.get_component::<Selected>(e4).unwrap(), &Selected(14));
+}
+
+#[test]
+fn test_changed() {
+    fn spawn(i: Insert<(Pos, Selected)>, mut frame: Local<u32>) {
+        if *frame == 0 {
+            i.insert((Pos(0.0), Selected(0)));
+            i.insert((Pos(1.0), Selected(1)));
+        }
+        *frame += 1;
+    }
+    fn bump(mut q: Query<&mut Selected>, mut frame: Local<u32>) {
+        if *frame == 1 {
+            for mut s in q.iter_mut() {
+                if s.0 == 1 {
+                    s.0 = 11;
+                }
+            }
+        }
+        *frame += 1;
+    }
+    fn changed(q: Query<&Selected, Changed<Selected>>, mut seen: SingleResMut<Seen>) {
+        let mut r = q.iter().map(|s| s.0).collect::<Vec<_>>();
+        r.sort();
+        seen.0.push(r);
+    }
+    let mut app = App::new();
+    app.world.insert_single_res(Seen::default());
+    app.add_system(Update, spawn);
+    app.add_system(Update, bump.after(spawn));
+    app.add_system(Update, changed.after(bump));
+    app.run();
+    app.run();
+    app.run();
+    assert_eq!(
+        app.world.get_single_res::<Seen>().unwrap().0,
+        vec![vec![0, 1], vec![11], vec![]]
+    );
+}
+
+#[test]
+fn test_destroy() {
+    let rc = Arc::new(());
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos, Handle)>();
+    let e1 = i.insert(&world, (Pos(1.0), Handle(rc.clone())));
+    let e2 = i.insert(&world, (Pos(2.0), Handle(rc.clone())));
+    assert_eq!(Arc::strong_count(&rc), 3);
+    world.destroy_entity(e1).unwrap();
+    assert_eq!(Arc::strong_count(&rc), 2);
+    assert!(world.get_component::<Handle>(e1).is_err());
+
+    // 再次添加会释放旧的组件
+    let mut alter = world.make_alter::<(), (), (Handle,), ()>();
+    alter.get_param(&world).alter(e2, (Handle(rc.clone()),)).unwrap();
+    assert_eq!(Arc::strong_count(&rc), 2);
+    alter.get_param(&world).destroy(e2).unwrap();
+    assert_eq!(Arc::strong_count(&rc), 1);
+}
+
+#[test]
+fn test_editor() {
+    let mut world = World::create();
+    world.insert_single_res(HookLog::default());
+    let pos = world.init_component::<Pos>();
+    let flag = world.init_component::<Flag>();
+    let e1 = world.make_insert::<(Pos,)>().insert(&world, (Pos(1.0),));
+    let ar = world.get_entity_prototype(e1).unwrap().1;
+
+    let mut editor = EntityEditor::new(&mut world);
+    editor.add_components_by_index(e1, &[flag]).unwrap();
+    assert_eq!(editor.get_component::<Flag>(e1).unwrap(), &Flag(0));
+    editor.get_component_mut::<Flag>(e1).unwrap().0 = 5;
+    // 已有的组件再次添加，保留原有的值，也不会再次触发添加
+    editor.add_components_by_index(e1, &[flag]).unwrap();
+    assert_eq!(editor.get_component::<Flag>(e1).unwrap(), &Flag(5));
+    // 稀疏存储的组件不改变实体的原型，也不会进入原型
+    let e2 = editor.insert_entity_by_index(&[pos, flag]).unwrap();
+    assert_eq!(editor.get_component::<Flag>(e2).unwrap(), &Flag(0));
+    editor.remove_components_by_index(e1, &[flag]).unwrap();
+    assert!(editor.get_component::<Flag>(e1).is_err());
+    // 没有原型行的实体，销毁时也移除稀疏存储的组件
+    let e3 = editor.alloc_entity();
+    editor.add_components_by_index(e3, &[flag]).unwrap();
+    editor.destroy(e3).unwrap();
+
+    assert_eq!(world.get_entity_prototype(e1).unwrap().1, ar);
+    assert_eq!(world.get_entity_prototype(e2).unwrap().1, ar);
+    world.run_hooks();
+    assert_eq!(
+        world.get_single_res::<HookLog>().unwrap().0,
+        vec![(true, e1), (true, e2), (false, e1), (true, e3), (false, e3)]
+    );
+    assert!(world.validate().is_ok());
+}
+
+#[test]
+fn test_readd() {
+    let mut world = World::create();
+    world.insert_single_res(HookLog::default());
+    let e = world.make_insert::<(Pos,)>().insert(&world, (Pos(1.0),));
+    let mut alter = world.make_alter::<(), (), (Flag,), ()>();
+    alter.get_param(&world).alter(e, (Flag(1),)).unwrap();
+    alter.get_param(&world).alter(e, (Flag(2),)).unwrap();
+    assert_eq!(world.get_component::<Flag>(e).unwrap(), &Flag(2));
+    world.run_hooks();
+    assert_eq!(world.get_single_res::<HookLog>().unwrap().0, vec![(true, e)]);
+}
+
+#[test]
+fn test_shrink_to_fit() {
+    let rc = Arc::new(());
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos, Selected, Handle)>();
+    let es = (0..6)
+        .map(|n| i.insert(&world, (Pos(n as f32), Selected(n), Handle(rc.clone()))))
+        .collect::<Vec<_>>();
+    world.destroy_entity(es[0]).unwrap();
+    world.destroy_entity(es[2]).unwrap();
+    world.shrink_to_fit();
+    assert_eq!(Arc::strong_count(&rc), 5);
+    for n in [1, 3, 4, 5] {
+        assert_eq!(world.get_component::<Selected>(es[n]).unwrap(), &Selected(n as u32));
+    }
+    // 整理后新分配的行不会覆盖已有的组件
+    let i = world.make_insert::<(Pos, Selected)>();
+    let e = i.insert(&world, (Pos(6.0), Selected(6)));
+    assert_eq!(world.get_component::<Selected>(e).unwrap(), &Selected(6));
+    for n in [1, 3, 4, 5] {
+        assert_eq!(world.get_component::<Selected>(es[n]).unwrap(), &Selected(n as u32));
+    }
+    drop(world);
+    assert_eq!(Arc::strong_count(&rc), 1);
+}
+
+#[test]
+fn test_settle() {
+    let rc = Arc::new(());
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos, Selected, Handle)>();
+    let es = (0..6)
+        .map(|n| i.insert(&world, (Pos(n as f32), Selected(n), Handle(rc.clone()))))
+        .collect::<Vec<_>>();
+    world.destroy_entity(es[0]).unwrap();
+    world.destroy_entity(es[2]).unwrap();
+    // 整理时用末尾的行填充空行
+    world.settle();
+    assert_eq!(Arc::strong_count(&rc), 5);
+    for n in [1, 3, 4, 5] {
+        assert_eq!(world.get_component::<Selected>(es[n]).unwrap(), &Selected(n as u32));
+    }
+    let i = world.make_insert::<(Pos, Selected)>();
+    let e = i.insert(&world, (Pos(6.0), Selected(6)));
+    assert_eq!(world.get_component::<Selected>(e).unwrap(), &Selected(6));
+    for n in [1, 3, 4, 5] {
+        assert_eq!(world.get_component::<Selected>(es[n]).unwrap(), &Selected(n as u32));
+    }
+    drop(world);
+    assert_eq!(Arc::strong_count(&rc), 1);
+}
+
+#[test]
+fn test_optional() {
+    let mut world = World::create();
+    world.insert_single_res(HookLog::default());
+    let i = world.make_insert::<(Pos,)>();
+    let es = (0..4).map(|n| i.insert(&world, (Pos(n as f32),))).collect::<Vec<_>>();
+    let mut alter = world.make_alter::<(), (), (Selected, Flag), ()>();
+    alter.get_param(&world).alter(es[1], (Selected(1), Flag(1))).unwrap();
+    alter.get_param(&world).alter(es[3], (Selected(3), Flag(3))).unwrap();
+    let selected = |e: Entity| match e_index(&es, e) {
+        n @ (1 | 3) => Some(Selected(n)),
+        _ => None,
+    };
+
+    let mut q = world.make_query::<(Entity, Option<&Selected>, Has<Selected>), ()>();
+    for (e, s, has) in q.get_param(&world).iter() {
+        assert_eq!(s.copied(), selected(e));
+        assert_eq!(has, selected(e).is_some());
+    }
+    let mut q = world.make_query::<(Entity, OrDefault<Flag>, OrDefaultRef<Flag>), ()>();
+    for (e, f, r) in q.get_param(&world).iter() {
+        let n = selected(e).map_or(0, |s| s.0);
+        assert_eq!(f, &Flag(n));
+        assert_eq!(*r, Flag(n));
+    }
+    let mut q = world.make_query::<Option<&mut Selected>, ()>();
+    for s in q.get_param(&world).iter_mut() {
+        if let Some(mut s) = s {
+            s.0 += 10;
+        }
+    }
+    assert_eq!(world.get_component::<Selected>(es[1]).unwrap(), &Selected(11));
+    assert_eq!(world.get_component::<Selected>(es[3]).unwrap(), &Selected(13));
+
+    // 没有该组件的实体被跳过
+    let mut q = world.make_query::<(Entity, Ref<Selected>), ()>();
+    let mut r = q.get_param(&world).iter().map(|(e, s)| (e, *s)).collect::<Vec<_>>();
+    r.sort_by_key(|(_, s)| s.0);
+    assert_eq!(r, vec![(es[1], Selected(11)), (es[3], Selected(13))]);
+    let mut q = world.make_query::<(Entity, Ticker<&mut Selected>, Option<Ticker<&Flag>>), ()>();
+    for (e, mut s, f) in q.get_param(&world).iter_mut() {
+        assert_eq!(f.map(|f| f.0), Some(e_index(&es, e)));
+        s.0 += 10;
+    }
+    let mut q = world.make_query::<(Entity, Ticker<&Selected>), ()>();
+    let mut r = q.get_param(&world).iter().map(|(e, s)| (e, *s)).collect::<Vec<_>>();
+    r.sort_by_key(|(_, s)| s.0);
+    assert_eq!(r, vec![(es[1], Selected(21)), (es[3], Selected(23))]);
+}
+
+fn e_index(es: &[Entity], e: Entity) -> u32 {
+    es.iter().position(|i| *i == e).unwrap() as u32
+}
+
+#[test]
+fn test_iter_chunks() {
+    let mut world = World::create();
+    let i = world.make_insert::<(Pos, Selected)>();
+    let es = (0..6).map(|n| i.insert(&world, (Pos(n as f32), Selected(n)))).collect::<Vec<_>>();
+    let mut remove = world.make_alter::<(), (), (), (Selected,)>();
+    remove.get_param(&world).alter(es[2], ()).unwrap();
+    let i = world.make_insert::<(Pos,)>();
+    let others = (6..9).map(|n| i.insert(&world, (Pos(n as f32),))).collect::<Vec<_>>();
+    // 倒序添加，稀疏集合中的行和原型上的行顺序相反
+    let mut add = world.make_alter::<(), (), (Selected,), ()>();
+    for e in others.iter().rev() {
+        let n = world.get_component::<Pos>(*e).unwrap().0 as u32;
+        add.get_param(&world).alter(*e, (Selected(n),)).unwrap();
+    }
+
+    let mut state = world.make_query::<(&mut Pos, &Selected), ()>();
+    let mut q = state.get_param(&world);
+    let mut count = 0;
+    for mut chunk in q.iter_chunks_mut() {
+        let len = chunk.len();
+        let entities = chunk.entities().collect::<Vec<_>>();
+        let (pos, selected) = &mut chunk.data;
+        assert_eq!(pos.len(), len);
+        assert_eq!(selected.len(), len);
+        for ((p, s), e) in pos.iter_mut().zip(selected.iter()).zip(entities) {
+            assert_eq!(p.0 as u32, s.0);
+            assert_ne!(e, es[2]);
+            p.0 += 10.0;
+        }
+        count += len;
+    }
+    // 没有该组件的实体不在块内
+    assert_eq!(count, 8);
+    assert_eq!(world.get_component::<Pos>(es[2]).unwrap(), &Pos(2.0));
+    assert_eq!(world.get_component::<Pos>(others[0]).unwrap(), &Pos(16.0));
+
+    let mut state = world.make_query::<&mut Selected, ()>();
+    let mut q = state.get_param(&world);
+    for mut chunk in q.iter_chunks_mut() {
+        for s in chunk.data.iter_mut() {
+            s.0 += 100;
+        }
+    }
+    for (n, e) in es.iter().enumerate() {
+        if n != 2 {
+            assert_eq!(world.get_component::<Selected>(*e).unwrap(), &Selected(n as u32 + 100));
+        }
+    }
+    assert_eq!(world.get_component::<Selected>(others[2]).unwrap(), &Selected(108));
+}
+
+#[test]
+fn test_ambiguity() {
+    fn write_selected(q: Query<&mut Selected>) {
+        let _ = q;
+    }
+    fn read_selected(q: Query<&Selected>) {
+        let _ = q;
+    }
+    fn read_unselected(q: Query<&Pos, Without<Selected>>) {
+        let _ = q;
+    }
+    fn unselect(a: Alter<(), (), (), (Selected,)>) {
+        let _ = a;
+    }
+
+    let mut app = App::new();
+    app.add_system(Update, write_selected);
+    app.add_system(Update, read_selected);
+    app.add_system(Update, read_unselected);
+    app.add_system(Update, unselect);
+
+    let report = app.schedule.ambiguity_report(&mut app.world);
+    // 稀疏存储的组件同样参与冲突检测，Without<Selected>的查询和其他系统不冲突
+    assert_eq!(report.len(), 3);
+    for a in report.ambiguities.iter() {
+        let names = a.systems.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(",");
+        assert_eq!(names.contains("read_unselected"), false);
+        assert!(a.conflicts.iter().all(|c| c.contains("Selected")));
+    }
+}
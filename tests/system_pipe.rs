@@ -0,0 +1,78 @@
+use pi_world::prelude::{App, In, IntoSystem, Resource, SingleRes, SingleResMut, Update};
+
+#[derive(Debug, Default, Resource)]
+pub struct Value(usize);
+
+#[derive(Debug, Default, Resource)]
+pub struct Errors(Vec<String>);
+
+#[test]
+fn test_pipe() {
+    fn compute(v: SingleRes<Value>) -> usize {
+        v.0 * 2
+    }
+
+    fn store(input: In<usize>, mut v: SingleResMut<Value>) {
+        v.0 = *input + 1;
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Value(1));
+    app.add_system(Update, compute.pipe(store));
+
+    app.run();
+    assert_eq!(app.world.get_single_res::<Value>().unwrap().0, 3);
+
+    app.run();
+    assert_eq!(app.world.get_single_res::<Value>().unwrap().0, 7);
+}
+
+#[test]
+fn test_pipe_result() {
+    fn check(v: SingleRes<Value>) -> Result<(), String> {
+        if v.0 > 2 {
+            Err(format!("value too large: {}", v.0))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn log_error(input: In<Result<(), String>>, mut errors: SingleResMut<Errors>) {
+        if let Err(e) = input.into_inner() {
+            errors.0.push(e);
+        }
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Value(1));
+    app.world.insert_single_res(Errors::default());
+    app.add_system(Update, check.pipe(log_error));
+
+    app.run();
+    assert_eq!(app.world.get_single_res::<Errors>().unwrap().0.len(), 0);
+
+    app.world.get_single_res_mut::<Value>().unwrap().0 = 5;
+    app.run();
+    assert_eq!(&app.world.get_single_res::<Errors>().unwrap().0, &vec!["value too large: 5".to_string()]);
+}
+
+#[test]
+fn test_pipe_chain() {
+    fn compute(v: SingleRes<Value>) -> usize {
+        v.0 * 2
+    }
+    fn to_string(input: In<usize>) -> String {
+        format!("{}", *input)
+    }
+    fn store(input: In<String>, mut errors: SingleResMut<Errors>) {
+        errors.0.push(input.into_inner());
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Value(2));
+    app.world.insert_single_res(Errors::default());
+    app.add_system(Update, compute.pipe(to_string).pipe(store));
+
+    app.run();
+    assert_eq!(&app.world.get_single_res::<Errors>().unwrap().0, &vec!["4".to_string()]);
+}
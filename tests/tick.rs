@@ -0,0 +1,87 @@
+use pi_world::prelude::{App, Changed, Component, Insert, IntoSystemConfigs, Query, Resource, SingleResMut, Update};
+use pi_world::world::{Tick, MAX_CHANGE_AGE};
+
+#[test]
+fn test_tick_wrapping() {
+    let a = Tick::from(u32::MAX - 1);
+    let b = Tick::from(2u32);
+    // 回绕后，b仍比a新
+    assert!(b.is_newer_than(a));
+    assert!(!a.is_newer_than(b));
+    assert!(!a.is_newer_than(a));
+
+    let now = Tick::from(5u32);
+    let mut old = Tick::from(now.wrapping_sub(MAX_CHANGE_AGE + 10));
+    assert!(old.check_tick(now));
+    assert_eq!(*old, now.wrapping_sub(MAX_CHANGE_AGE));
+    assert!(!old.check_tick(now));
+    assert!(now.is_newer_than(old));
+}
+
+#[test]
+fn test_check_change_ticks() {
+    let mut app = pi_world::prelude::App::new();
+    app.run();
+    assert!(!app.world.need_check_change_ticks());
+    app.world.check_change_ticks();
+    app.run();
+}
+
+#[derive(Component)]
+pub struct Age(usize);
+
+#[derive(Debug, Default, Resource)]
+pub struct Seen(Vec<usize>);
+
+#[derive(Debug, Default, Resource)]
+pub struct LateSeen(Vec<usize>);
+
+// 本次运行要插入的实体数量
+#[derive(Debug, Default, Resource)]
+pub struct Spawn(usize);
+
+#[test]
+fn test_tick_wrap_around() {
+    fn spawn(i: Insert<(Age,)>, mut n: SingleResMut<Spawn>) {
+        for _ in 0..n.0 {
+            i.insert((Age(0),));
+        }
+        n.0 = 0;
+    }
+    fn changed(q: Query<&Age, Changed<Age>>, mut seen: SingleResMut<Seen>) {
+        seen.0.push(q.iter().count());
+    }
+    fn late_changed(q: Query<&Age, Changed<Age>>, mut seen: SingleResMut<LateSeen>) {
+        seen.0.push(q.iter().count());
+    }
+
+    let mut app = App::new();
+    app.world.insert_single_res(Seen::default());
+    app.world.insert_single_res(LateSeen::default());
+    app.world.insert_single_res(Spawn::default());
+    app.add_system(Update, spawn);
+    app.add_system(Update, changed.after(spawn));
+    app.run();
+    app.world.get_single_res_mut::<Spawn>().unwrap().0 = 1;
+    app.run();
+    assert_eq!(app.world.get_single_res::<Seen>().unwrap().0, vec![0, 1]);
+
+    // 每次前进2^30，多次运行后越过u32的回绕
+    for _ in 0..6 {
+        let now = app.world.tick();
+        app.world.set_tick(Tick::from(now.wrapping_add(1 << 30)));
+        app.run();
+    }
+    assert_eq!(app.world.get_single_res::<Seen>().unwrap().0, vec![0, 1, 0, 0, 0, 0, 0, 0]);
+    // tick的最高位为1时，新的变化仍能被检测到
+    assert!(*app.world.tick() > 1 << 31);
+    app.world.get_single_res_mut::<Spawn>().unwrap().0 = 1;
+    app.run();
+    assert_eq!(app.world.get_single_res::<Seen>().unwrap().0.last(), Some(&1));
+
+    // 后注册的system，第一次运行时所有的组件都是新的
+    let id = app.world.register_system(late_changed);
+    app.world.run_system(id).unwrap();
+    app.world.run_system(id).unwrap();
+    assert_eq!(app.world.get_single_res::<LateSeen>().unwrap().0, vec![2, 0]);
+}
@@ -0,0 +1,30 @@
+
+#[path = "./defined.rs"]
+mod defined;
+use defined::*;
+use pi_world::prelude::World;
+
+#[test]
+fn test_validate() {
+    let mut world = World::create();
+    world.set_validate_on_settle(true);
+    let i = world.make_insert::<(Age1, Age0)>();
+    let e1 = i.insert(&world, (Age1(2), Age0(1)));
+    let e2 = i.insert(&world, (Age1(4), Age0(2)));
+    let e3 = i.insert(&world, (Age1(6), Age0(3)));
+    // 未整理时，也应该是一致的
+    assert_eq!(world.validate(), Ok(()));
+    world.settle();
+    {
+        let mut editor = world.make_entity_editor();
+        let index = editor.init_component::<Age2>();
+        editor.add_components_by_index(e1, &[index]);
+    }
+    world.destroy_entity(e2).unwrap();
+    assert_eq!(world.validate(), Ok(()));
+    // 整理后，实体地址要指向正确的行
+    world.settle();
+    assert_eq!(world.validate(), Ok(()));
+    assert_eq!(world.get_component::<Age0>(e1).unwrap().0, 1);
+    assert_eq!(world.get_component::<Age0>(e3).unwrap().0, 3);
+}